@@ -0,0 +1,45 @@
+// Compares the decoding throughput of the block Huffman methods, with and without interleaved
+// streams. Run with `cargo bench`.
+
+use std::time::Instant;
+
+use comprs::{InputSource, Method, OutputSink};
+
+// Number of times each input is decoded.
+const NUM_RUNS: usize = 20;
+
+fn bench_decode(name: &str, input: &[u8], method: Method) {
+    let mut output = OutputSink::memory(Vec::new());
+    method
+        .encoder()
+        .encode(&mut InputSource::slice(input), &mut output)
+        .unwrap();
+    let encoded = output.take_memory();
+
+    let mut decoder = method.decoder();
+    let start = Instant::now();
+    for _ in 0..NUM_RUNS {
+        let mut output = OutputSink::memory(Vec::with_capacity(input.len()));
+        decoder
+            .decode(&mut InputSource::slice(&encoded), &mut output)
+            .unwrap();
+        assert_eq!(output.take_memory(), input);
+    }
+    let seconds = start.elapsed().as_secs_f64();
+    let throughput = (input.len() * NUM_RUNS) as f64 / seconds / 1e6;
+    println!("{:<20} {:<8} {:8.1} MB/s", method.name(), name, throughput);
+}
+
+fn main() {
+    let text = include_bytes!("../testdata/deflate/text.txt");
+    let binary = include_bytes!("../testdata/deflate/binary.bin");
+    let inputs = [
+        ("text", text.repeat(4 * 1024 * 1024 / text.len() + 1)),
+        ("binary", binary.repeat(4 * 1024 * 1024 / binary.len() + 1)),
+    ];
+    for (name, input) in inputs.iter() {
+        for method in [Method::BlockHuffmanCoding, Method::InterleavedHuffmanCoding] {
+            bench_decode(name, input, method);
+        }
+    }
+}
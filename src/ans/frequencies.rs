@@ -0,0 +1,96 @@
+// Type of the frequency counts.
+type FrequencyType = u32;
+
+/// Scales the symbol `frequencies` so that they add up to `1 << table_bits`.
+///
+/// Every symbol with a non-zero frequency is given a normalized frequency of at least 1.
+/// Returns all zeros if all the frequencies are zero.
+pub fn normalize_frequencies(frequencies: &[FrequencyType], table_bits: u32) -> Vec<FrequencyType> {
+    let num_present = frequencies.iter().filter(|&&f| f > 0).count();
+    assert!(num_present <= 1 << table_bits);
+
+    let total: u64 = frequencies.iter().map(|&f| f as u64).sum();
+    if total == 0 {
+        return vec![0; frequencies.len()];
+    }
+
+    // Scale the frequencies, rounding to the nearest value.
+    let table_size = 1u64 << table_bits;
+    let mut normalized: Vec<FrequencyType> = frequencies
+        .iter()
+        .map(|&f| {
+            if f == 0 {
+                0
+            } else {
+                ((f as u64 * table_size + total / 2) / total).max(1) as FrequencyType
+            }
+        })
+        .collect();
+
+    // Fix up rounding errors. Symbols with larger frequencies absorb the difference, as that has
+    // the least impact on the compression ratio.
+    let sum: u64 = normalized.iter().map(|&f| f as u64).sum();
+    let mut by_frequency: Vec<usize> = (0..normalized.len()).collect();
+    by_frequency.sort_by_key(|&i| std::cmp::Reverse(normalized[i]));
+    if sum < table_size {
+        normalized[by_frequency[0]] += (table_size - sum) as FrequencyType;
+    } else {
+        let mut excess = sum - table_size;
+        while excess > 0 {
+            for &i in by_frequency.iter() {
+                if excess == 0 || normalized[i] <= 1 {
+                    break;
+                }
+                normalized[i] -= 1;
+                excess -= 1;
+            }
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+
+    fn validate(frequencies: &[FrequencyType], table_bits: u32) {
+        let normalized = normalize_frequencies(frequencies, table_bits);
+        assert_eq!(normalized.len(), frequencies.len());
+        assert_eq!(normalized.iter().sum::<FrequencyType>(), 1 << table_bits);
+        for (&f, &n) in frequencies.iter().zip(normalized.iter()) {
+            assert_eq!(f == 0, n == 0);
+        }
+    }
+
+    #[test]
+    fn test_simple() {
+        validate(&[1, 2, 3, 4], 4);
+        validate(&[0, 0, 7, 0], 2);
+        validate(&[1000, 1, 1, 1, 1, 1, 1, 1], 3);
+        assert_eq!(normalize_frequencies(&[10, 30], 2), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_all_zeros() {
+        assert_eq!(normalize_frequencies(&[0, 0, 0], 4), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_random() {
+        for s in 0..100 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let frequencies: Vec<FrequencyType> = (0..256)
+                .map(|_| {
+                    if rng.gen_bool(0.3) {
+                        0
+                    } else {
+                        let bits = rng.gen_range(1..20);
+                        rng.gen_range(1..1 << bits)
+                    }
+                })
+                .collect();
+            validate(&frequencies, 12);
+        }
+    }
+}
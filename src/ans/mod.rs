@@ -0,0 +1,7 @@
+mod frequencies;
+mod rans;
+mod tans;
+
+pub use frequencies::normalize_frequencies;
+pub use rans::{Rans, RANS_SCALE_BITS};
+pub use tans::{Tans, MIN_TABLE_BITS, TANS_TABLE_BITS};
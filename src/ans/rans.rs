@@ -0,0 +1,175 @@
+use crate::bits::{ReverseBitReader, ReverseBitWriter};
+use crate::error::Error;
+
+// Type of the symbols.
+type SymbolType = u16;
+
+// Type of the coder state.
+type StateType = u32;
+
+/// Number of bits in the probability scale. The normalized frequencies add up to 1 << this.
+pub const RANS_SCALE_BITS: u32 = 14;
+
+// Number of bits moved in and out of the state at a time during renormalization.
+const IO_BITS: u32 = 16;
+
+// Lower bound of the normalized state. The state is kept within [RANS_L, RANS_L << IO_BITS).
+const RANS_L: StateType = 1 << 16;
+
+/// Number of bits needed to store the coder state.
+pub const RANS_STATE_BITS: u32 = StateType::BITS;
+
+/// Range ANS (rANS) coding tables for a set of symbols with normalized frequencies.
+///
+/// Symbols are encoded in reverse order into a `ReverseBitWriter`, so that they can be decoded
+/// in forward order from a `ReverseBitReader`.
+pub struct Rans {
+    // Normalized frequency of each symbol.
+    frequencies: Vec<StateType>,
+
+    // Cumulative frequency of the symbols before each symbol.
+    starts: Vec<StateType>,
+
+    // Symbol for each slot in [0, 1 << RANS_SCALE_BITS).
+    slot_symbols: Vec<SymbolType>,
+}
+
+impl Rans {
+    /// Creates an instance from frequencies normalized to add up to `1 << RANS_SCALE_BITS`, or
+    /// all zeros if there are no symbols.
+    pub fn new(normalized_frequencies: &[u32]) -> Self {
+        let mut starts: Vec<StateType> = Vec::with_capacity(normalized_frequencies.len());
+        let mut slot_symbols: Vec<SymbolType> = Vec::with_capacity(1 << RANS_SCALE_BITS);
+        let mut start: StateType = 0;
+        for (symbol, &frequency) in normalized_frequencies.iter().enumerate() {
+            starts.push(start);
+            start += frequency;
+            slot_symbols.resize(start as usize, symbol as SymbolType);
+        }
+        assert!(start == 0 || start == 1 << RANS_SCALE_BITS);
+
+        Self {
+            frequencies: normalized_frequencies.to_vec(),
+            starts,
+            slot_symbols,
+        }
+    }
+
+    /// Returns the initial encoder state.
+    pub fn initial_state(&self) -> StateType {
+        RANS_L
+    }
+
+    /// Encodes a symbol, updating the `state`.
+    pub fn encode(&self, state: &mut StateType, symbol: SymbolType, writer: &mut ReverseBitWriter) {
+        let frequency = self.frequencies[symbol as usize];
+        debug_assert!(frequency > 0);
+
+        // Renormalize so that the state stays within bounds after encoding.
+        let max_state = (((RANS_L >> RANS_SCALE_BITS) as u64) << IO_BITS) * frequency as u64;
+        let mut x = *state as u64;
+        while x >= max_state {
+            writer.write_bits(x & ((1 << IO_BITS) - 1), IO_BITS);
+            x >>= IO_BITS;
+        }
+
+        let frequency = frequency as u64;
+        x = ((x / frequency) << RANS_SCALE_BITS)
+            + (x % frequency)
+            + self.starts[symbol as usize] as u64;
+        *state = x as StateType;
+    }
+
+    /// Writes out the final encoder state. This is read by `read_state` when decoding.
+    pub fn write_state(state: StateType, writer: &mut ReverseBitWriter) {
+        writer.write_bits(state as u64, RANS_STATE_BITS);
+    }
+
+    /// Reads the initial decoder state.
+    pub fn read_state(reader: &mut ReverseBitReader) -> Result<StateType, Error> {
+        let state = reader.read_bits(RANS_STATE_BITS) as StateType;
+        if state < RANS_L {
+            return Err(Error::CorruptData("Invalid rANS state"));
+        }
+        Ok(state)
+    }
+
+    /// Decodes a symbol, updating the `state`.
+    pub fn decode(&self, state: &mut StateType, reader: &mut ReverseBitReader) -> SymbolType {
+        let slot = *state & ((1 << RANS_SCALE_BITS) - 1);
+        let symbol = self.slot_symbols[slot as usize];
+        let frequency = self.frequencies[symbol as usize];
+        let mut x = frequency * (*state >> RANS_SCALE_BITS) + slot - self.starts[symbol as usize];
+
+        // Renormalize.
+        while x < RANS_L {
+            x = (x << IO_BITS) | reader.read_bits(IO_BITS) as StateType;
+        }
+        *state = x;
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ans::normalize_frequencies;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io;
+
+    fn encode_decode(frequencies: &[u32], input: &[SymbolType]) -> usize {
+        let rans = Rans::new(&normalize_frequencies(frequencies, RANS_SCALE_BITS));
+
+        // Encode in reverse order.
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut writer = ReverseBitWriter::new(&mut cursor);
+        let mut state = rans.initial_state();
+        for &symbol in input.iter().rev() {
+            rans.encode(&mut state, symbol, &mut writer);
+        }
+        Rans::write_state(state, &mut writer);
+        let bytes_written = writer.finish().unwrap();
+
+        // Decode in forward order.
+        let mut cursor = io::Cursor::new(cursor.into_inner());
+        let mut reader = ReverseBitReader::new(&mut cursor);
+        let mut state = Rans::read_state(&mut reader).unwrap();
+        for &symbol in input.iter() {
+            assert_eq!(rans.decode(&mut state, &mut reader), symbol);
+        }
+        assert_eq!(state, rans.initial_state());
+        assert!(reader.check_status().is_ok());
+        bytes_written
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let bytes_written = encode_decode(&[0, 5, 0], &[1; 100]);
+        assert_eq!(bytes_written, 5);
+    }
+
+    #[test]
+    fn test_skewed() {
+        let mut input = vec![0; 1000];
+        input[500] = 1;
+        let bytes_written = encode_decode(&[999, 1], &input);
+
+        // 1 bit for the rare symbol would be 125 bytes with whole-bit codes.
+        assert!(bytes_written < 20);
+    }
+
+    #[test]
+    fn test_random() {
+        for s in 0..20 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let input: Vec<SymbolType> = (0..5000)
+                .map(|_| rng.gen_range(0..16u16) * rng.gen_range(0..16u16))
+                .collect();
+            let mut frequencies = vec![0; 256];
+            for &symbol in input.iter() {
+                frequencies[symbol as usize] += 1;
+            }
+            encode_decode(&frequencies, &input);
+        }
+    }
+}
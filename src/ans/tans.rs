@@ -0,0 +1,255 @@
+use crate::bits::{ReverseBitReader, ReverseBitWriter};
+
+// Type of the symbols.
+type SymbolType = u16;
+
+// Type of the coder state.
+type StateType = u32;
+
+/// Default and largest number of bits of the tANS table size.
+pub const TANS_TABLE_BITS: u32 = 12;
+
+/// Smallest number of bits of the tANS table size.
+pub const MIN_TABLE_BITS: u32 = 5;
+
+// An entry in the decoding table.
+#[derive(Clone, Copy, Default)]
+struct DecodeEntry {
+    symbol: SymbolType,
+
+    // Number of bits to read for the next state.
+    num_bits: u8,
+
+    // Base value of the next state, which is added to the bits read.
+    next_state_base: u16,
+}
+
+// Per symbol encoding parameters.
+#[derive(Clone, Copy, Default)]
+struct SymbolTransform {
+    // (State + delta_num_bits) >> 16 is the number of bits to output.
+    delta_num_bits: u32,
+
+    // Offset into `state_table` for the symbol.
+    delta_find_state: i32,
+}
+
+/// Table-based ANS (tANS) coding tables, in the style of Finite State Entropy.
+///
+/// Symbols are spread over a table of `1 << table_bits` states according to their normalized
+/// frequencies. Each symbol is decoded with a single table lookup, followed by reading the bits
+/// of the next state.
+pub struct Tans {
+    table_bits: u32,
+
+    // Encoding: next state for each (symbol, state >> bits) pair, grouped by symbol.
+    state_table: Vec<u16>,
+    symbol_transforms: Vec<SymbolTransform>,
+
+    // Decoding: entry for each state.
+    decode_table: Vec<DecodeEntry>,
+}
+
+impl Tans {
+    /// Creates an instance from frequencies normalized to add up to `1 << table_bits`, with
+    /// `table_bits` in `MIN_TABLE_BITS..=TANS_TABLE_BITS`.
+    pub fn new(normalized_frequencies: &[u32], table_bits: u32) -> Self {
+        assert!((MIN_TABLE_BITS..=TANS_TABLE_BITS).contains(&table_bits));
+        let table_size = 1usize << table_bits;
+        assert_eq!(
+            normalized_frequencies.iter().sum::<u32>() as usize,
+            table_size
+        );
+
+        // Spread the symbols over the table. For the supported table sizes, the step is odd, so
+        // it is coprime with the power-of-two size and visits every position once.
+        let mask = table_size - 1;
+        let step = (table_size >> 1) + (table_size >> 3) + 3;
+        let mut table_symbols: Vec<SymbolType> = vec![0; table_size];
+        let mut pos = 0;
+        for (symbol, &frequency) in normalized_frequencies.iter().enumerate() {
+            for _ in 0..frequency {
+                table_symbols[pos] = symbol as SymbolType;
+                pos = (pos + step) & mask;
+            }
+        }
+        assert_eq!(pos, 0);
+
+        // Build the encoding tables.
+        let mut cumulative: Vec<usize> = Vec::with_capacity(normalized_frequencies.len());
+        let mut symbol_transforms: Vec<SymbolTransform> =
+            Vec::with_capacity(normalized_frequencies.len());
+        let mut total = 0;
+        for &frequency in normalized_frequencies.iter() {
+            cumulative.push(total);
+            let transform = match frequency {
+                0 => SymbolTransform::default(),
+                1 => SymbolTransform {
+                    delta_num_bits: (table_bits << 16) - (1 << table_bits),
+                    delta_find_state: total as i32 - 1,
+                },
+                _ => {
+                    let max_bits_out = table_bits - (frequency - 1).ilog2();
+                    let min_state_plus = frequency << max_bits_out;
+                    SymbolTransform {
+                        delta_num_bits: (max_bits_out << 16) - min_state_plus,
+                        delta_find_state: total as i32 - frequency as i32,
+                    }
+                }
+            };
+            symbol_transforms.push(transform);
+            total += frequency as usize;
+        }
+
+        let mut state_table: Vec<u16> = vec![0; table_size];
+        let mut next = cumulative.clone();
+        for (pos, &symbol) in table_symbols.iter().enumerate() {
+            state_table[next[symbol as usize]] = (table_size + pos) as u16;
+            next[symbol as usize] += 1;
+        }
+
+        // Build the decoding table.
+        let mut next_state: Vec<u32> = normalized_frequencies.to_vec();
+        let decode_table: Vec<DecodeEntry> = table_symbols
+            .iter()
+            .map(|&symbol| {
+                let x = next_state[symbol as usize];
+                next_state[symbol as usize] += 1;
+                let num_bits = table_bits - x.ilog2();
+                DecodeEntry {
+                    symbol,
+                    num_bits: num_bits as u8,
+                    next_state_base: ((x << num_bits) - table_size as u32) as u16,
+                }
+            })
+            .collect();
+
+        Self {
+            table_bits,
+            state_table,
+            symbol_transforms,
+            decode_table,
+        }
+    }
+
+    /// Returns the initial encoder state, which encodes the (last) `symbol` without output bits.
+    pub fn initial_state(&self, symbol: SymbolType) -> StateType {
+        let transform = self.symbol_transforms[symbol as usize];
+        let num_bits = (transform.delta_num_bits + (1 << 15)) >> 16;
+        let state = (num_bits << 16) - transform.delta_num_bits;
+        self.next_state(state >> num_bits, transform)
+    }
+
+    /// Encodes a symbol, updating the `state`.
+    pub fn encode(&self, state: &mut StateType, symbol: SymbolType, writer: &mut ReverseBitWriter) {
+        let transform = self.symbol_transforms[symbol as usize];
+        let num_bits = (*state + transform.delta_num_bits) >> 16;
+        writer.write_bits(*state as u64, num_bits);
+        *state = self.next_state(*state >> num_bits, transform);
+    }
+
+    // Look up the next encoder state.
+    fn next_state(&self, index: StateType, transform: SymbolTransform) -> StateType {
+        self.state_table[(index as i32 + transform.delta_find_state) as usize] as StateType
+    }
+
+    /// Writes out the final encoder state. This is read by `read_state` when decoding.
+    pub fn write_state(&self, state: StateType, writer: &mut ReverseBitWriter) {
+        writer.write_bits((state - (1 << self.table_bits)) as u64, self.table_bits);
+    }
+
+    /// Reads the initial decoder state.
+    pub fn read_state(&self, reader: &mut ReverseBitReader) -> StateType {
+        reader.read_bits(self.table_bits) as StateType
+    }
+
+    /// Returns the symbol at the decoder `state`, without updating the state.
+    /// Use this for the last symbol, which has no next state.
+    #[inline]
+    pub fn peek_symbol(&self, state: StateType) -> SymbolType {
+        self.decode_table[state as usize].symbol
+    }
+
+    /// Decodes a symbol, updating the `state`.
+    #[inline]
+    pub fn decode(&self, state: &mut StateType, reader: &mut ReverseBitReader) -> SymbolType {
+        let entry = self.decode_table[*state as usize];
+        *state = entry.next_state_base as StateType
+            + reader.read_bits(entry.num_bits as u32) as StateType;
+        entry.symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ans::normalize_frequencies;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io;
+
+    fn encode_decode(frequencies: &[u32], table_bits: u32, input: &[SymbolType]) -> usize {
+        let tans = Tans::new(&normalize_frequencies(frequencies, table_bits), table_bits);
+
+        // Encode in reverse order.
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut writer = ReverseBitWriter::new(&mut cursor);
+        let (&last, rest) = input.split_last().unwrap();
+        let mut state = tans.initial_state(last);
+        for &symbol in rest.iter().rev() {
+            tans.encode(&mut state, symbol, &mut writer);
+        }
+        tans.write_state(state, &mut writer);
+        let bytes_written = writer.finish().unwrap();
+
+        // Decode in forward order.
+        let mut cursor = io::Cursor::new(cursor.into_inner());
+        let mut reader = ReverseBitReader::new(&mut cursor);
+        let mut state = tans.read_state(&mut reader);
+        for &symbol in rest.iter() {
+            assert_eq!(tans.decode(&mut state, &mut reader), symbol);
+        }
+        assert_eq!(tans.peek_symbol(state), last);
+        assert!(reader.check_status().is_ok());
+        bytes_written
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let bytes_written = encode_decode(&[0, 5, 0], 5, &[1; 100]);
+        assert_eq!(bytes_written, 1);
+    }
+
+    #[test]
+    fn test_skewed() {
+        let mut input = vec![0; 1000];
+        input[500] = 1;
+        let bytes_written = encode_decode(&[999, 1], 10, &input);
+
+        // 1 bit per symbol would be 125 bytes with whole-bit codes.
+        assert!(bytes_written < 20);
+    }
+
+    #[test]
+    fn test_table_sizes() {
+        // Every position of the table is visited by the spread, for all supported sizes.
+        let input: Vec<SymbolType> = (0..1000).map(|i| (i * i % 7) as SymbolType).collect();
+        for table_bits in MIN_TABLE_BITS..=TANS_TABLE_BITS {
+            encode_decode(&[3, 1, 4, 1, 5, 9, 2], table_bits, &input);
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        for s in 0..20 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let input: Vec<SymbolType> = (0..5000)
+                .map(|_| rng.gen_range(0..16u16) * rng.gen_range(0..16u16))
+                .collect();
+            let mut frequencies = vec![0; 256];
+            for &symbol in input.iter() {
+                frequencies[symbol as usize] += 1;
+            }
+            encode_decode(&frequencies, TANS_TABLE_BITS, &input);
+        }
+    }
+}
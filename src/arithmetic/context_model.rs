@@ -0,0 +1,201 @@
+use crate::arithmetic::frequency_model::DEFAULT_INCREMENT;
+use crate::arithmetic::range_coder::{RangeDecoder, RangeEncoder};
+use crate::arithmetic::FrequencyModel;
+
+// Type of the symbols.
+type SymbolType = u16;
+
+/// Maximum supported context order.
+pub const MAX_ORDER: usize = 2;
+
+// Number of bits of the hash table for order-2 contexts.
+const ORDER2_HASH_BITS: u32 = 12;
+
+/// An adaptive context model, for use with a range coder.
+///
+/// A separate adaptive model is kept for each context of the previous byte (order-1) and hashed
+/// previous two bytes (order-2). A symbol that has not been seen in the current context is coded
+/// as an escape, followed by the symbol in the next lower order context, down to an order-0
+/// model where all symbols are present.
+pub struct ContextModel {
+    // Number of symbols, excluding the escape symbol.
+    num_symbols: SymbolType,
+
+    // Highest context order used.
+    order: usize,
+
+    // Models for each context, for orders 1 up to `order`. Created when first used.
+    contexts: Vec<Vec<Option<FrequencyModel>>>,
+
+    // The order-0 model.
+    order0: FrequencyModel,
+
+    // The previous bytes, most recent in the lowest byte.
+    history: u32,
+}
+
+impl ContextModel {
+    /// Create a new instance for `num_symbols` symbols using contexts of up to `order` bytes.
+    pub fn new(num_symbols: SymbolType, order: usize) -> Self {
+        assert!(order <= MAX_ORDER);
+        let contexts = (1..=order)
+            .map(|o| {
+                let size = if o == 1 { 256 } else { 1 << ORDER2_HASH_BITS };
+                let mut models = Vec::with_capacity(size);
+                models.resize_with(size, || None);
+                models
+            })
+            .collect();
+        Self {
+            num_symbols,
+            order,
+            contexts,
+            order0: FrequencyModel::new(num_symbols),
+            history: 0,
+        }
+    }
+
+    // The escape symbol in the context models.
+    fn escape_symbol(&self) -> SymbolType {
+        self.num_symbols
+    }
+
+    // Index of the current context for the given order.
+    fn context_index(&self, order: usize) -> usize {
+        match order {
+            1 => (self.history & 0xFF) as usize,
+            _ => {
+                ((self.history & 0xFFFF).wrapping_mul(0x9E37_79B1) >> (32 - ORDER2_HASH_BITS))
+                    as usize
+            }
+        }
+    }
+
+    // Returns the model of the current context for the given order, creating it if necessary.
+    // A new model only has the escape symbol.
+    fn context_model(&mut self, order: usize) -> &mut FrequencyModel {
+        let index = self.context_index(order);
+        let num_symbols = self.num_symbols as usize;
+        self.contexts[order - 1][index].get_or_insert_with(|| {
+            let mut frequencies = vec![0; num_symbols + 1];
+            frequencies[num_symbols] = 1;
+            FrequencyModel::with_frequencies(frequencies, DEFAULT_INCREMENT)
+        })
+    }
+
+    // Adds a symbol to the contexts that escaped, and updates the history.
+    fn update(&mut self, symbol: SymbolType, escaped_orders: std::ops::RangeInclusive<usize>) {
+        let escape = self.escape_symbol();
+        for order in escaped_orders {
+            let model = self.context_model(order);
+            model.update(symbol);
+
+            // The more distinct symbols in a context, the more likely a new symbol is.
+            model.update(escape);
+        }
+        self.history = (self.history << 8) | (symbol as u32 & 0xFF);
+    }
+
+    /// Encodes a symbol.
+    pub fn encode(&mut self, symbol: SymbolType, range_encoder: &mut RangeEncoder) {
+        assert!(symbol < self.num_symbols);
+        let escape = self.escape_symbol();
+        for order in (1..=self.order).rev() {
+            let model = self.context_model(order);
+            if model.frequency(symbol) > 0 {
+                let (start, frequency) = model.symbol_range(symbol);
+                range_encoder.encode(start, frequency, model.total());
+                model.update(symbol);
+                self.update(symbol, order + 1..=self.order);
+                return;
+            }
+            let (start, frequency) = model.symbol_range(escape);
+            range_encoder.encode(start, frequency, model.total());
+        }
+
+        let (start, frequency) = self.order0.symbol_range(symbol);
+        range_encoder.encode(start, frequency, self.order0.total());
+        self.order0.update(symbol);
+        self.update(symbol, 1..=self.order);
+    }
+
+    /// Decodes a symbol.
+    pub fn decode(&mut self, range_decoder: &mut RangeDecoder) -> SymbolType {
+        let escape = self.escape_symbol();
+        for order in (1..=self.order).rev() {
+            let model = self.context_model(order);
+            let value = range_decoder.decode_frequency(model.total());
+            let (symbol, start, frequency) = model.find(value);
+            range_decoder.decode(start, frequency);
+            if symbol != escape {
+                model.update(symbol);
+                self.update(symbol, order + 1..=self.order);
+                return symbol;
+            }
+        }
+
+        let value = range_decoder.decode_frequency(self.order0.total());
+        let (symbol, start, frequency) = self.order0.find(value);
+        range_decoder.decode(start, frequency);
+        self.order0.update(symbol);
+        self.update(symbol, 1..=self.order);
+        symbol
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io;
+
+    fn encode_decode(order: usize, input: &[SymbolType]) -> usize {
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut encoder = RangeEncoder::new(&mut cursor);
+        let mut model = ContextModel::new(257, order);
+        for &symbol in input.iter() {
+            model.encode(symbol, &mut encoder);
+        }
+        let bytes_written = encoder.finish().unwrap();
+
+        let mut cursor = io::Cursor::new(cursor.into_inner());
+        let mut decoder = RangeDecoder::new(&mut cursor);
+        let mut model = ContextModel::new(257, order);
+        for &symbol in input.iter() {
+            assert_eq!(model.decode(&mut decoder), symbol);
+        }
+        bytes_written
+    }
+
+    #[test]
+    fn test_random() {
+        for s in 0..5 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let input: Vec<SymbolType> = (0..20000).map(|_| rng.gen_range(0..257)).collect();
+            for order in 0..=MAX_ORDER {
+                encode_decode(order, &input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_correlated_input() {
+        // Each byte is mostly determined by the previous byte.
+        let mut rng = rngs::SmallRng::seed_from_u64(1);
+        let mut input: Vec<SymbolType> = vec![0];
+        for _ in 0..20000 {
+            let prev = *input.last().unwrap();
+            let next = if rng.gen_bool(0.9) {
+                (prev * 7 + 3) % 256
+            } else {
+                rng.gen_range(0..256)
+            };
+            input.push(next);
+        }
+        let order0_size = encode_decode(0, &input);
+        let order1_size = encode_decode(1, &input);
+        let order2_size = encode_decode(2, &input);
+        assert!(order1_size < order0_size / 2);
+        assert!(order2_size < order0_size / 2);
+    }
+}
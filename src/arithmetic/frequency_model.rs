@@ -0,0 +1,190 @@
+// Type of the symbols.
+type SymbolType = u16;
+
+// Type of the frequency counts.
+type FrequencyType = u32;
+
+/// Default amount added to the frequency of a symbol each time it is seen.
+pub const DEFAULT_INCREMENT: FrequencyType = 32;
+
+/// Maximum total frequency. The frequencies are halved when the total exceeds this.
+/// This must not exceed `range_coder::MAX_TOTAL_FREQUENCY`.
+pub const MAX_TOTAL: FrequencyType = 1 << 16;
+
+/// An adaptive order-0 frequency model.
+///
+/// The cumulative frequencies are kept in a Fenwick tree, so looking up and updating a symbol
+/// both take O(log n) time.
+pub struct FrequencyModel {
+    // Fenwick tree of the frequencies. tree[i] (1-based) is the sum of the frequencies of the
+    // symbols in (i - lowbit(i), i].
+    tree: Vec<FrequencyType>,
+
+    // Frequency of each symbol.
+    frequencies: Vec<FrequencyType>,
+
+    // Sum of all frequencies.
+    total: FrequencyType,
+
+    // Amount added to a symbol's frequency on each update.
+    increment: FrequencyType,
+
+    // Largest power of 2 <= number of symbols. Used for searching the tree.
+    top_bit: usize,
+}
+
+impl FrequencyModel {
+    /// Creates a model where all `num_symbols` symbols have a frequency of 1.
+    pub fn new(num_symbols: SymbolType) -> Self {
+        Self::with_frequencies(vec![1; num_symbols as usize], DEFAULT_INCREMENT)
+    }
+
+    /// Creates a model with the initial `frequencies`, and `increment` added on each update.
+    pub fn with_frequencies(frequencies: Vec<FrequencyType>, increment: FrequencyType) -> Self {
+        assert!(!frequencies.is_empty());
+        let mut top_bit = 1;
+        while top_bit * 2 <= frequencies.len() {
+            top_bit *= 2;
+        }
+        let mut model = Self {
+            tree: Vec::new(),
+            frequencies,
+            total: 0,
+            increment,
+            top_bit,
+        };
+        model.rebuild();
+        model
+    }
+
+    // Rebuild the Fenwick tree from the frequencies.
+    fn rebuild(&mut self) {
+        let n = self.frequencies.len();
+        self.tree.clear();
+        self.tree.resize(n + 1, 0);
+        for i in 1..=n {
+            self.tree[i] += self.frequencies[i - 1];
+            let parent = i + (i & i.wrapping_neg());
+            if parent <= n {
+                self.tree[parent] += self.tree[i];
+            }
+        }
+        self.total = self.frequencies.iter().sum();
+    }
+
+    /// Returns the total frequency of all symbols.
+    #[inline]
+    pub fn total(&self) -> FrequencyType {
+        self.total
+    }
+
+    /// Returns the frequency of `symbol`.
+    #[inline]
+    pub fn frequency(&self, symbol: SymbolType) -> FrequencyType {
+        self.frequencies[symbol as usize]
+    }
+
+    /// Returns the sum of the frequencies of the symbols before `symbol`.
+    pub fn cumulative_frequency(&self, symbol: SymbolType) -> FrequencyType {
+        let mut sum = 0;
+        let mut i = symbol as usize;
+        while i > 0 {
+            sum += self.tree[i];
+            i &= i - 1;
+        }
+        sum
+    }
+
+    /// Returns the (start, frequency) range of `symbol`.
+    pub fn symbol_range(&self, symbol: SymbolType) -> (FrequencyType, FrequencyType) {
+        (self.cumulative_frequency(symbol), self.frequency(symbol))
+    }
+
+    /// Finds the symbol whose range contains `target`, which must be less than `total()`.
+    /// Returns (symbol, start, frequency).
+    pub fn find(&self, target: FrequencyType) -> (SymbolType, FrequencyType, FrequencyType) {
+        debug_assert!(target < self.total);
+
+        // Descend the tree, finding the largest position with cumulative frequency <= target.
+        let mut pos = 0;
+        let mut start = 0;
+        let mut step = self.top_bit;
+        while step > 0 {
+            let next = pos + step;
+            if next < self.tree.len() && start + self.tree[next] <= target {
+                pos = next;
+                start += self.tree[next];
+            }
+            step >>= 1;
+        }
+        let symbol = pos as SymbolType;
+        (symbol, start, self.frequency(symbol))
+    }
+
+    /// Updates the model after seeing `symbol`.
+    pub fn update(&mut self, symbol: SymbolType) {
+        self.frequencies[symbol as usize] += self.increment;
+        self.total += self.increment;
+        if self.total > MAX_TOTAL {
+            // Halve the frequencies to stay within range. This also lets the model adapt to
+            // changes in the statistics.
+            for frequency in self.frequencies.iter_mut() {
+                *frequency = (*frequency + 1) >> 1;
+            }
+            self.rebuild();
+            return;
+        }
+
+        let mut i = symbol as usize + 1;
+        while i < self.tree.len() {
+            self.tree[i] += self.increment;
+            i += i & i.wrapping_neg();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+
+    // Check the model against the frequencies.
+    fn validate(model: &FrequencyModel) {
+        let mut start = 0;
+        for symbol in 0..model.frequencies.len() as SymbolType {
+            let frequency = model.frequency(symbol);
+            assert!(frequency > 0);
+            assert_eq!(model.symbol_range(symbol), (start, frequency));
+            assert_eq!(model.find(start), (symbol, start, frequency));
+            assert_eq!(
+                model.find(start + frequency - 1),
+                (symbol, start, frequency)
+            );
+            start += frequency;
+        }
+        assert_eq!(model.total(), start);
+    }
+
+    #[test]
+    fn test_initial() {
+        validate(&FrequencyModel::new(1));
+        validate(&FrequencyModel::new(5));
+        validate(&FrequencyModel::new(257));
+    }
+
+    #[test]
+    fn test_random_updates() {
+        for s in 0..10 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let mut model = FrequencyModel::new(257);
+            for i in 0..10000 {
+                model.update(rng.gen_range(0..16u16) * rng.gen_range(0..17u16));
+                assert!(model.total() <= MAX_TOTAL);
+                if i % 1000 == 0 {
+                    validate(&model);
+                }
+            }
+            validate(&model);
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod context_model;
+mod frequency_model;
+mod range_coder;
+
+pub use context_model::{ContextModel, MAX_ORDER};
+pub use frequency_model::FrequencyModel;
+pub use range_coder::{RangeDecoder, RangeEncoder};
@@ -0,0 +1,175 @@
+use std::io;
+
+use crate::bits::{BitReader, BitWriter};
+
+// Type of the frequency counts.
+type FrequencyType = u32;
+
+/// Maximum total frequency supported by the coder.
+pub const MAX_TOTAL_FREQUENCY: FrequencyType = 1 << 16;
+
+// The range is renormalized when it falls below this.
+const TOP: u32 = 1 << 24;
+
+// Number of bytes the decoder reads to initialize its state.
+const INIT_BYTES: usize = 5;
+
+/// A range encoder (i.e. a multi-symbol arithmetic encoder).
+///
+/// Bytes are output with carry propagation, in the style of the LZMA range coder.
+pub struct RangeEncoder<'a> {
+    // Low end of the range. Bit 32 is the carry.
+    low: u64,
+
+    range: u32,
+
+    // The last byte not yet output, as it may still be changed by a carry.
+    cache: u8,
+
+    // Number of pending bytes: the cache byte followed by (cache_size - 1) 0xFF bytes.
+    cache_size: u64,
+
+    bit_writer: BitWriter<'a>,
+}
+
+impl<'a> RangeEncoder<'a> {
+    /// Create a new instance.
+    pub fn new(writer: &'a mut dyn io::Write) -> Self {
+        Self {
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+            bit_writer: BitWriter::new(writer),
+        }
+    }
+
+    /// Encodes the symbol at [start, start + frequency) of `total`.
+    pub fn encode(&mut self, start: FrequencyType, frequency: FrequencyType, total: FrequencyType) {
+        debug_assert!(frequency > 0 && start + frequency <= total && total <= MAX_TOTAL_FREQUENCY);
+        let r = self.range / total;
+        self.low += (start * r) as u64;
+        self.range = frequency * r;
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    // Output the top byte of `low`, handling the carry.
+    fn shift_low(&mut self) {
+        if (self.low as u32) < 0xFF00_0000 || (self.low >> 32) != 0 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+            loop {
+                self.bit_writer
+                    .write_bits(byte.wrapping_add(carry) as u64, 8);
+                byte = 0xFF;
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+    }
+
+    /// Finish writing and return the total number of bytes written, or the first write error.
+    /// Can only be called once.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        for _ in 0..INIT_BYTES {
+            self.shift_low();
+        }
+        self.bit_writer.finish()
+    }
+}
+
+/// A range decoder for streams written by `RangeEncoder`.
+pub struct RangeDecoder<'a> {
+    code: u32,
+    range: u32,
+    bit_reader: BitReader<'a>,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Create a new instance. This reads the start of the stream.
+    pub fn new(reader: &'a mut dyn io::Read) -> Self {
+        let mut bit_reader = BitReader::new(reader);
+        let mut code = 0;
+        for _ in 0..INIT_BYTES {
+            code = (code << 8) | bit_reader.read_bits(8) as u32;
+        }
+        Self {
+            code,
+            range: u32::MAX,
+            bit_reader,
+        }
+    }
+
+    /// Returns the frequency value of the next symbol, which is in [0, total).
+    /// This must be followed by `decode()` with the range of the symbol containing the value.
+    pub fn decode_frequency(&mut self, total: FrequencyType) -> FrequencyType {
+        self.range /= total;
+        (self.code / self.range).min(total - 1)
+    }
+
+    /// Consumes the symbol at [start, start + frequency).
+    pub fn decode(&mut self, start: FrequencyType, frequency: FrequencyType) {
+        self.code = self.code.wrapping_sub(start * self.range);
+        self.range *= frequency;
+        while self.range < TOP {
+            self.code = (self.code << 8) | self.bit_reader.read_bits(8) as u32;
+            self.range <<= 8;
+        }
+    }
+
+    /// Returns true if bytes past the end of the stream have been read, i.e. the stream is
+    /// truncated.
+    pub fn is_past_end(&self) -> bool {
+        self.bit_reader.is_past_end()
+    }
+
+    /// Finish the decoder and return number of bytes read, or the first read error.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        self.bit_reader.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+
+    #[test]
+    fn test_encode_decode() {
+        for s in 0..20 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let total = rng.gen_range(2..=MAX_TOTAL_FREQUENCY);
+            let ranges: Vec<(u32, u32)> = (0..2000)
+                .map(|_| {
+                    let start = rng.gen_range(0..total);
+                    let frequency = rng.gen_range(1..=total - start);
+                    (start, frequency)
+                })
+                .collect();
+
+            let mut cursor = io::Cursor::new(Vec::new());
+            let mut encoder = RangeEncoder::new(&mut cursor);
+            for &(start, frequency) in ranges.iter() {
+                encoder.encode(start, frequency, total);
+            }
+            encoder.finish().unwrap();
+
+            let mut cursor = io::Cursor::new(cursor.into_inner());
+            let mut decoder = RangeDecoder::new(&mut cursor);
+            for &(start, frequency) in ranges.iter() {
+                let value = decoder.decode_frequency(total);
+                assert!(value >= start && value < start + frequency);
+                decoder.decode(start, frequency);
+            }
+            decoder.finish().unwrap();
+        }
+    }
+}
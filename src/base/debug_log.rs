@@ -1,6 +1,7 @@
 static mut DEBUG_LOGGING_ENABLED: bool = false;
 
 /// Enables or disables debug logging.
+#[allow(dead_code)]
 pub fn enable_debug_logging(enable: bool) {
     unsafe {
         DEBUG_LOGGING_ENABLED = enable;
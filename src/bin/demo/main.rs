@@ -0,0 +1,33 @@
+// Runs each compression method on test data, and checks the round trip.
+
+mod tester;
+
+use comprs::Method;
+
+use crate::tester::Tester;
+
+fn main() {
+    let tester = Tester::new();
+    tester.run(vec![
+        Method::DynamicHuffmanCoding,
+        Method::StaticHuffmanCoding,
+        Method::ZeroRunHuffmanCoding,
+        Method::RansCoding,
+        Method::TansCoding,
+        Method::ArithmeticCoding,
+        Method::Order1ContextCoding,
+        Method::Order2ContextCoding,
+        Method::MultiTableHuffmanCoding,
+        Method::BlockHuffmanCoding,
+        Method::InterleavedHuffmanCoding,
+        Method::LzHuffmanCoding,
+        Method::DeflateCoding,
+        Method::GzipCoding,
+        Method::ZlibCoding,
+        Method::Bzip2Coding,
+        Method::PipelineCoding,
+        Method::Mtf1ZeroRunHuffmanCoding,
+        Method::Order1DynamicHuffmanCoding,
+        Method::Order2DynamicHuffmanCoding,
+    ]);
+}
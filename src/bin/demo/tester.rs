@@ -1,109 +1,36 @@
-use std::error::Error;
-
-use crate::coding::decoder::{DecodeResult, Decoder};
-use crate::coding::dynamic_huffman_coding::{DynamicHuffmanDecoder, DynamicHuffmanEncoder};
-use crate::coding::encoder::{EncodeResult, Encoder};
-use crate::coding::input::InputSource;
-use crate::coding::output::OutputSink;
-use crate::coding::static_huffman_coding::{StaticHuffmanDecoder, StaticHuffmanEncoder};
-
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
-pub enum CompressionMethod {
-    DynamicHuffmanCoding,
-    StaticHuffmanCoding,
-}
-
-type EncoderFactory = fn() -> Box<dyn Encoder>;
-type DecoderFactory = fn() -> Box<dyn Decoder>;
-
-// Dynamic huffman coding
-fn create_dynamic_huffman_coding_encoder() -> Box<dyn Encoder> {
-    Box::new(DynamicHuffmanEncoder::new())
-}
-
-fn create_dynamic_huffman_coding_decoder() -> Box<dyn Decoder> {
-    Box::new(DynamicHuffmanDecoder::new())
-}
-
-fn create_static_huffman_coding_encoder() -> Box<dyn Encoder> {
-    Box::new(StaticHuffmanEncoder::new())
-}
-
-fn create_static_huffman_coding_decoder() -> Box<dyn Decoder> {
-    Box::new(StaticHuffmanDecoder::new())
-}
-
-struct CompressionFactory {
-    name: String,
-    method: CompressionMethod,
-    encoder_factory: EncoderFactory,
-    decoder_factory: DecoderFactory,
-}
-
-struct CompressionFactories {
-    all: Vec<CompressionFactory>,
-}
+use comprs::{
+    DecodeResult, Decoder, EncodeResult, Encoder, Error, InputSource, Method, OutputSink,
+};
 
-impl CompressionFactories {
-    fn new() -> Self {
-        let mut instance = Self { all: Vec::new() };
-        instance.all.push(CompressionFactory {
-            name: "DynamicHuffman".to_string(),
-            method: CompressionMethod::DynamicHuffmanCoding,
-            encoder_factory: || Box::new(DynamicHuffmanEncoder::new()),
-            decoder_factory: || Box::new(DynamicHuffmanDecoder::new()),
-        });
-        instance.all.push(CompressionFactory {
-            name: "StaticHuffman".to_string(),
-            method: CompressionMethod::StaticHuffmanCoding,
-            encoder_factory: || Box::new(StaticHuffmanEncoder::new()),
-            decoder_factory: || Box::new(StaticHuffmanDecoder::new()),
-        });
-        instance
-    }
-
-    fn get_method(&self, method: CompressionMethod) -> &CompressionFactory {
-        for factory in self.all.iter() {
-            if factory.method == method {
-                return factory;
-            }
-        }
-        panic!();
-    }
-}
+type EncodeMemoryResult = (Result<EncodeResult, Error>, Vec<u8>, Vec<u8>);
 
 // For testing all coding methods.
-pub struct Tester {
-    factories: CompressionFactories,
-}
+pub struct Tester {}
 
 impl Tester {
     pub fn new() -> Self {
-        Self {
-            factories: CompressionFactories::new(),
-        }
+        Self {}
     }
 
     /// Test encode a file.
-    pub fn encode(&self, method: CompressionMethod) {
-        let factory = self.factories.get_method(method);
-        let mut encoder = (factory.encoder_factory)();
+    #[allow(dead_code)]
+    pub fn encode(&self, method: Method) {
+        let mut encoder = method.encoder();
 
         let input_file = "/tmp/test";
         let mut input_data = InputSource::file(input_file);
-        let mut output_data = OutputSink ::memory( Vec::new());
+        let mut output_data = OutputSink::memory(Vec::new());
         println!("{} -> {}", input_data, output_data);
         let result = encoder.encode(&mut input_data, &mut output_data);
         self.report_encode_result(&result);
     }
 
     /// Run a series of tests using the methods.
-    pub fn run(&self, methods: Vec<CompressionMethod>) {
+    pub fn run(&self, methods: Vec<Method>) {
         for &method in methods.iter() {
-            let factory = self.factories.get_method(method);
-            println!("{}:", factory.name);
-            let mut encoder = (factory.encoder_factory)();
-            let mut decoder = (factory.decoder_factory)();
+            println!("{}:", method.name());
+            let mut encoder = method.encoder();
+            let mut decoder = method.decoder();
 
             {
                 // Create input data.
@@ -153,7 +80,7 @@ impl Tester {
         encoder: &mut Box<dyn Encoder>,
         input_file: &str,
         output_file: &str,
-    ) -> Result<EncodeResult, Box<dyn Error>> {
+    ) -> Result<EncodeResult, Error> {
         let mut input_data = InputSource::file(input_file);
         let mut output_data = OutputSink::file(output_file);
         println!("{} -> {}", input_data, output_data);
@@ -165,12 +92,16 @@ impl Tester {
         encoder: &mut Box<dyn Encoder>,
         input_vec: Vec<u8>,
         output_vec: Vec<u8>,
-    ) -> (Result<EncodeResult, Box<dyn Error>>, Vec<u8>, Vec<u8>) {
+    ) -> EncodeMemoryResult {
         let mut input_data = InputSource::memory(input_vec);
         let mut output_data = OutputSink::memory(output_vec);
         println!("{} -> {}", input_data, output_data);
         let result = encoder.encode(&mut input_data, &mut output_data);
-        (result, input_data.take_memory(), output_data.take_memory())
+        (
+            result,
+            input_data.take_memory().unwrap(),
+            output_data.take_memory(),
+        )
     }
 
     fn decode_memory_to_memory(
@@ -178,7 +109,7 @@ impl Tester {
         decoder: &mut Box<dyn Decoder>,
         input_vec: Vec<u8>,
         output_vec: Vec<u8>,
-    ) -> (Result<DecodeResult, Box<dyn Error>>, Vec<u8>) {
+    ) -> (Result<DecodeResult, Error>, Vec<u8>) {
         let mut input_data = InputSource::memory(input_vec);
         let mut output_data = OutputSink::memory(output_vec);
         println!("{} -> {}", input_data, output_data);
@@ -191,21 +122,21 @@ impl Tester {
         decoder: &mut Box<dyn Decoder>,
         input_file: &str,
         output_file: &str,
-    ) -> Result<DecodeResult, Box<dyn Error>> {
+    ) -> Result<DecodeResult, Error> {
         let mut input_data = InputSource::file(input_file);
         let mut output_data = OutputSink::file(output_file);
         println!("{} -> {}", input_data, output_data);
         decoder.decode(&mut input_data, &mut output_data)
     }
 
-    fn report_encode_result(&self, result: &Result<EncodeResult, Box<dyn Error>>) {
+    fn report_encode_result(&self, result: &Result<EncodeResult, Error>) {
         match result {
             Ok(result) => println!("  Encode result: {}", result),
             Err(e) => println!("  Error: {}", e),
         }
     }
 
-    fn report_decode_result(&self, result: &Result<DecodeResult, Box<dyn Error>>) {
+    fn report_decode_result(&self, result: &Result<DecodeResult, Error>) {
         match result {
             Ok(result) => println!("  Decode result: {}", result),
             Err(e) => println!("  Error: {}", e),
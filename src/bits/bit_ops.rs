@@ -21,3 +21,9 @@ pub fn shift_right(data: u64, bits: u32) -> u64 {
         data >> bits
     }
 }
+
+/// Returns a mask of the lowest `bits` bits, up to 64 bits.
+#[inline]
+pub fn low_bits_mask(bits: u32) -> u64 {
+    shift_left(1, bits).wrapping_sub(1)
+}
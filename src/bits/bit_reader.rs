@@ -4,6 +4,7 @@ use std::io;
 
 use crate::base::DebugLog;
 use crate::bits::bit_ops::*;
+use crate::bits::PeekBits;
 
 // If true, print debug information.
 const DEBUG: bool = true;
@@ -39,8 +40,11 @@ pub struct BitReader<'a> {
     // Number of bytes read.
     bytes_read: usize,
 
-    // Number of read errors that have occurred.
-    num_read_errors: usize,
+    // Number of zero bytes supplied past the end of the stream.
+    bytes_past_end: usize,
+
+    // The first error of `reader`, after which the stream is treated as ended.
+    error: Option<io::Error>,
 }
 
 impl<'a> BitReader<'a> {
@@ -54,7 +58,8 @@ impl<'a> BitReader<'a> {
             buf_end: 0,
             reader,
             bytes_read: 0,
-            num_read_errors: 0,
+            bytes_past_end: 0,
+            error: None,
         }
     }
 
@@ -112,22 +117,97 @@ impl<'a> BitReader<'a> {
         result | shift_right(next, self.bits_avail)
     }
 
-    /// Finish the reader and return number of bytes read.
-    pub fn finish(&mut self) -> usize {
+    /// Skip the bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        self.consume(self.bits_avail % 8);
+    }
+
+    /// Read whole bytes into `bytes`. The stream must be byte aligned.
+    ///
+    /// Returns the number of bytes read, which is less than `bytes.len()` at end of stream.
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> usize {
+        assert!(self.bits_avail.is_multiple_of(8));
+
+        // Use up the bytes in the data buffer first, except the padding past the end.
+        let mut pos = 0;
+        while self.bits_avail / 8 > self.bytes_past_end as u32 && pos < bytes.len() {
+            bytes[pos] = self.read_bits(8) as u8;
+            pos += 1;
+        }
+        if pos == bytes.len() || self.bytes_past_end > 0 {
+            return pos;
+        }
+
+        // The data buffer may hold bits past `bits_avail`, which are copied below.
+        self.data = 0;
+        while pos < bytes.len() {
+            if self.buf_pos == self.buf_end {
+                self.fill_buf();
+                if self.buf_pos == self.buf_end {
+                    break;
+                }
+            }
+            let len = (self.buf_end - self.buf_pos).min(bytes.len() - pos);
+            bytes[pos..pos + len].copy_from_slice(&self.buf[self.buf_pos..self.buf_pos + len]);
+            self.buf_pos += len;
+            pos += len;
+        }
+        pos
+    }
+
+    /// Returns true if bits past the end of the stream have been read.
+    pub fn is_past_end(&self) -> bool {
+        // The padding bytes in `data` may not have been read yet.
+        self.bytes_past_end > (self.bits_avail / 8) as usize
+    }
+
+    /// Fails with the first error of the reader, or with `UnexpectedEof` if bits past the end of
+    /// the stream have been read. Decoders call this to stop on truncated or unreadable input.
+    pub fn check_status(&mut self) -> io::Result<()> {
+        if let Some(error) = self.take_error() {
+            return Err(error);
+        }
+        if self.is_past_end() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Read past the end of the stream",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Finish the reader and return number of bytes read, or the first error of the reader.
+    pub fn finish(&mut self) -> io::Result<usize> {
         if DEBUG {
             LOG.print("Finish");
         }
+        if let Some(error) = self.take_error() {
+            return Err(error);
+        }
         self.bytes_read -= self.buf_end - self.buf_pos;
         self.buf_pos = 0;
         self.buf_end = 0;
-        self.bytes_read
+        Ok(self.bytes_read)
+    }
+
+    // Returns the first error of the reader. The reader stays failed, with an error of the same
+    // kind for later calls.
+    fn take_error(&mut self) -> Option<io::Error> {
+        let error = self.error.take()?;
+        self.error = Some(io::Error::from(error.kind()));
+        Some(error)
     }
 
-    pub fn num_read_errors(&self) -> usize {
-        self.num_read_errors
+    // Record an error in the data read, e.g. an invalid code. The stream is treated as ended,
+    // as after an error of the reader. Errors past the end of the stream are due to truncation.
+    pub(super) fn add_read_error(&mut self, message: &'static str) {
+        if self.error.is_none() && !self.is_past_end() {
+            self.error = Some(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
     }
 
     // Reads the next 64-bit value.
+    #[allow(clippy::needless_range_loop)]
     fn next_u64(&mut self) -> u64 {
         let pos = self.buf_pos;
         self.buf_pos += 8;
@@ -143,14 +223,21 @@ impl<'a> BitReader<'a> {
         for i in 0..bytes {
             data[i] = self.buf[pos + i];
         }
+        self.buf_pos = self.buf_end;
 
-        // Fill buffer and add remaining bytes.
-        self.fill_buf();
+        // Fill the buffer as needed for the remaining bytes, as reads may be short.
         for i in bytes..8 {
-            if self.buf_pos < self.buf_end {
-                data[i] = self.buf[self.buf_pos];
-                self.buf_pos += 1;
+            if self.buf_pos == self.buf_end {
+                self.fill_buf();
+
+                // If it's end of stream, let it be padded with 0s.
+                if self.buf_pos == self.buf_end {
+                    self.bytes_past_end += 8 - i;
+                    break;
+                }
             }
+            data[i] = self.buf[self.buf_pos];
+            self.buf_pos += 1;
         }
         if DEBUG {
             LOG.print(&format!("Read {:#x}", u64::from_be_bytes(data)));
@@ -160,6 +247,7 @@ impl<'a> BitReader<'a> {
 
     // Reads the next `num_bytes` bytes.
     // Returns the data in big-endian format. The data may contain more than `num_bytes` bytes.
+    #[allow(clippy::needless_range_loop)]
     fn next_bytes(&mut self, num_bytes: usize) -> u64 {
         if DEBUG {
             LOG.print(&format!("Next {} bytes", num_bytes));
@@ -180,6 +268,7 @@ impl<'a> BitReader<'a> {
 
                 // If it's end of stream, let it be padded with 0s.
                 if self.buf_pos == self.buf_end {
+                    self.bytes_past_end += num_bytes - i;
                     break;
                 }
             }
@@ -193,7 +282,16 @@ impl<'a> BitReader<'a> {
     fn fill_buf(&mut self) {
         assert!(self.buf_pos >= self.buf_end);
         self.buf_pos = 0;
-        let result = self.reader.read(&mut self.buf[..]);
+        if self.error.is_some() {
+            self.buf_end = 0;
+            return;
+        }
+        let result = loop {
+            match self.reader.read(&mut self.buf[..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
         match result {
             Ok(size) => {
                 if DEBUG {
@@ -208,21 +306,34 @@ impl<'a> BitReader<'a> {
                 }
             }
             Err(e) => {
-                // Instead of just panicking, allow the reader to continue.
-                // The client should check the read_errors() of this reader.
-                self.buf_end = 0;
-                self.num_read_errors += 1;
+                // Allow the reader to continue as if the stream ended, without reading again.
+                // The client gets the error from check_status() or finish().
                 LOG.print(&format!("Error: {}", e));
-
-                // But panic if there are too many errors to avoid infinite loops.
-                if self.num_read_errors > 100 {
-                    panic!("Too many read errors");
-                }
+                self.buf_end = 0;
+                self.error = Some(e);
             }
         }
     }
 }
 
+impl PeekBits for BitReader<'_> {
+    fn fill_data(&mut self) {
+        BitReader::fill_data(self)
+    }
+
+    fn peek(&self) -> u64 {
+        BitReader::peek(self)
+    }
+
+    fn bits_avail(&self) -> u32 {
+        BitReader::bits_avail(self)
+    }
+
+    fn consume(&mut self, bits: u32) {
+        BitReader::consume(self, bits)
+    }
+}
+
 impl Debug for BitReader<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("BitReader")
@@ -245,8 +356,8 @@ mod tests {
         let mut reader: Cursor<Vec<u8>> = Cursor::new(buffer);
         let mut bit_reader = BitReader::new(&mut reader);
         assert_eq!(bit_reader.read_bits(0), 0);
-        assert_eq!(bit_reader.num_read_errors(), 0);
-        let bytes_read = bit_reader.finish();
+        assert!(bit_reader.check_status().is_ok());
+        let bytes_read = bit_reader.finish().unwrap();
         assert_eq!(bytes_read, 0);
         Ok(())
     }
@@ -263,8 +374,11 @@ mod tests {
         assert_eq!(bit_reader.read_bits(8), 3);
         assert_eq!(bit_reader.read_bits(32), 0xff815378);
         assert_eq!(bit_reader.read_bits(32), 0x1225ab00);
-        assert_eq!(bit_reader.num_read_errors(), 0);
-        let bytes_read = bit_reader.finish();
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let bytes_read = bit_reader.finish().unwrap();
         assert_eq!(bytes_read, 10);
         Ok(())
     }
@@ -278,8 +392,11 @@ mod tests {
 
         assert_eq!(bit_reader.read_bits(64), 0x0102030400000000);
         assert_eq!(bit_reader.read_bits(64), 0);
-        assert_eq!(bit_reader.num_read_errors(), 0);
-        let bytes_read = bit_reader.finish();
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let bytes_read = bit_reader.finish().unwrap();
         assert_eq!(bytes_read, 4);
         Ok(())
     }
@@ -305,9 +422,129 @@ mod tests {
         assert_eq!(bit_reader.read_bits(64), 0x2468acf000000000);
         assert_eq!(bit_reader.peek(), 0);
 
-        assert_eq!(bit_reader.num_read_errors(), 0);
-        let bytes_read = bit_reader.finish();
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let bytes_read = bit_reader.finish().unwrap();
         assert_eq!(bytes_read, 4);
         Ok(())
     }
+
+    #[test]
+    // Read bytes after aligning to a byte boundary.
+    fn test_read_bytes() {
+        let buffer: Vec<u8> = (0..=255).cycle().take(20000).collect();
+        let mut reader = Cursor::new(buffer.clone());
+        let mut bit_reader = BitReader::new(&mut reader);
+
+        assert_eq!(bit_reader.read_bits(3), 0);
+        bit_reader.align_to_byte();
+        let mut bytes = vec![0; 10000];
+        assert_eq!(bit_reader.read_bytes(&mut bytes), 10000);
+        assert_eq!(bytes, buffer[1..10001]);
+
+        assert_eq!(bit_reader.read_bits(12), 0x111);
+        bit_reader.align_to_byte();
+        assert_eq!(bit_reader.read_bytes(&mut bytes), 9997);
+        assert_eq!(bytes[..9997], buffer[10003..]);
+        assert_eq!(bit_reader.finish().unwrap(), 20000);
+    }
+
+    // Returns at most 3 bytes per read.
+    struct ShortReader<'a>(&'a [u8]);
+
+    impl io::Read for ShortReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.0.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    // Short reads from the reader don't insert zeros.
+    fn test_short_reads() {
+        let buffer: Vec<u8> = (1..=40).collect();
+        let mut reader = ShortReader(&buffer);
+        let mut bit_reader = BitReader::new(&mut reader);
+        for &byte in buffer.iter() {
+            assert_eq!(bit_reader.read_bits(8), byte as u64);
+        }
+        assert!(!bit_reader.is_past_end());
+        assert_eq!(bit_reader.read_bits(8), 0);
+        assert!(bit_reader.is_past_end());
+        assert_eq!(bit_reader.finish().unwrap(), 40);
+
+        let mut reader = ShortReader(&buffer);
+        let mut bit_reader = BitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(4), 0);
+        assert_eq!(bit_reader.read_bits(64), 0x1020304050607080);
+        assert_eq!(bit_reader.read_bits(60), 0x90a0b0c0d0e0f10);
+    }
+
+    #[test]
+    // The padding past the end of the stream is not returned as bytes.
+    fn test_read_bytes_end_of_stream() {
+        let mut reader = Cursor::new(vec![1, 2, 3, 4, 5]);
+        let mut bit_reader = BitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(16), 0x0102);
+        assert!(!bit_reader.is_past_end());
+        let mut bytes = [0; 4];
+        assert_eq!(bit_reader.read_bytes(&mut bytes), 3);
+        assert_eq!(bytes[..3], [3, 4, 5]);
+        assert!(!bit_reader.is_past_end());
+        assert_eq!(bit_reader.read_bytes(&mut bytes), 0);
+        assert_eq!(bit_reader.read_bits(8), 0);
+        assert!(bit_reader.is_past_end());
+    }
+
+    // Returns `data` in one read, then fails every read.
+    struct FailingReader<'a> {
+        data: &'a [u8],
+        num_reads: usize,
+    }
+
+    impl io::Read for FailingReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.num_reads += 1;
+            if self.data.is_empty() {
+                return Err(io::Error::other("Read failed"));
+            }
+            let len = buf.len().min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    // A read error ends the stream, and is returned by check_status() and finish().
+    fn test_read_error() {
+        let mut reader = FailingReader {
+            data: &[1, 2, 3],
+            num_reads: 0,
+        };
+        let mut bit_reader = BitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(16), 0x0102);
+        assert_eq!(bit_reader.read_bits(16), 0x0300);
+        assert_eq!(bit_reader.read_bits(64), 0);
+
+        let error = bit_reader.check_status().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(error.to_string(), "Read failed");
+
+        // The error is sticky.
+        assert_eq!(bit_reader.read_bits(64), 0);
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::Other
+        );
+        assert_eq!(
+            bit_reader.finish().unwrap_err().kind(),
+            io::ErrorKind::Other
+        );
+        assert_eq!(reader.num_reads, 2);
+    }
 }
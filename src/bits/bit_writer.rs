@@ -29,8 +29,8 @@ pub struct BitWriter<'a> {
     // Total number of bytes written.
     bytes_written: usize,
 
-    // Number of errors that occurred.
-    write_errors: usize,
+    // The first error of `writer`, after which nothing more is written.
+    error: Option<io::Error>,
 }
 
 impl<'a> BitWriter<'a> {
@@ -42,7 +42,7 @@ impl<'a> BitWriter<'a> {
             buf: Vec::with_capacity(BUF_SIZE),
             writer,
             bytes_written: 0,
-            write_errors: 0,
+            error: None,
         }
     }
 
@@ -70,25 +70,39 @@ impl<'a> BitWriter<'a> {
         self.bits_avail = new_bits_avail;
     }
 
+    /// Write zero bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        self.write_bits(0, self.bits_avail % 8);
+    }
+
+    /// Write whole bytes. The stream must be byte aligned.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        assert!(self.bits_avail.is_multiple_of(8));
+        for &byte in bytes.iter() {
+            self.write_bits(byte as u64, 8);
+        }
+    }
+
     // Flush the buffer to the writer.
     fn flush(&mut self) {
         if DEBUG {
             LOG.print("Flush");
         }
-        let result = self.writer.write_all(&self.buf);
-        if let Err(e) = result {
-            // Allow the writer to continue, but keep track of errors.
-            // Clients should check the number of write errors.
-            LOG.print(&format!("Error writing to writer: {}", e));
-            self.write_errors += 1;
+        if self.error.is_none() {
+            if let Err(e) = self.writer.write_all(&self.buf) {
+                // Allow the writer to continue, discarding the output.
+                // The client gets the error from finish().
+                LOG.print(&format!("Error writing to writer: {}", e));
+                self.error = Some(e);
+            }
         }
         self.bytes_written += self.buf.len();
         self.buf.clear();
     }
 
-    /// Finish writing and return the total number of bytes written.
-    /// Can only be called once.
-    pub fn finish(&mut self) -> usize {
+    /// Finish writing and return the total number of bytes written, or the first error of the
+    /// writer. Can only be called once.
+    pub fn finish(&mut self) -> io::Result<usize> {
         if DEBUG {
             LOG.print("Finish");
         }
@@ -104,12 +118,10 @@ impl<'a> BitWriter<'a> {
         }
 
         self.flush();
-        self.bytes_written
-    }
-
-    /// Return the number of write errors encountered.
-    pub fn num_write_errors(&self) -> usize {
-        self.write_errors
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.bytes_written),
+        }
     }
 
     // Write 8 bytes to the buffer. Flush the buffer if full.
@@ -124,7 +136,6 @@ impl<'a> BitWriter<'a> {
     }
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,9 +146,8 @@ mod tests {
         let mut bw = BitWriter::new(&mut writer);
 
         bw.write_bits(1, 1);
-        let bytes_written = bw.finish();
+        let bytes_written = bw.finish().unwrap();
         assert_eq!(bytes_written, 1);
-        assert_eq!(bw.num_write_errors(), 0);
         assert_eq!(writer.into_inner(), vec![0x80]);
     }
 
@@ -149,13 +159,64 @@ mod tests {
         bw.write_bits(1, 8);
         bw.write_bits(0x1234567890AB, 48);
         bw.write_bits(1, 8);
-        let bytes_written = bw.finish();
+        let bytes_written = bw.finish()?;
         assert_eq!(bytes_written, 8);
-        assert_eq!(bw.num_write_errors(), 0);
         assert_eq!(
             writer.into_inner(),
             vec![1, 0x12, 0x34, 0x56, 0x78, 0x90, 0xAB, 1]
         );
         Ok(())
     }
+
+    #[test]
+    fn test_write_bytes() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bw = BitWriter::new(&mut writer);
+
+        bw.write_bits(1, 1);
+        bw.align_to_byte();
+        bw.write_bytes(&[0x12, 0x34]);
+        bw.align_to_byte();
+        bw.write_bits(0xf, 4);
+        assert_eq!(bw.finish().unwrap(), 4);
+        assert_eq!(writer.into_inner(), vec![0x80, 0x12, 0x34, 0xf0]);
+    }
+
+    // Fails every write after the first `writes_left` writes.
+    struct FailingWriter {
+        writes_left: usize,
+        num_writes: usize,
+    }
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.num_writes += 1;
+            if self.writes_left == 0 {
+                return Err(io::Error::other("Write failed"));
+            }
+            self.writes_left -= 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    // The first write error is returned by finish(), and nothing is written after it.
+    fn test_write_error() {
+        let mut writer = FailingWriter {
+            writes_left: 1,
+            num_writes: 0,
+        };
+        let mut bw = BitWriter::new(&mut writer);
+        for i in 0..3 * BUF_SIZE {
+            bw.write_bits(i as u64, 8);
+        }
+        let error = bw.finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(error.to_string(), "Write failed");
+        assert_eq!(writer.num_writes, 2);
+    }
 }
@@ -1,4 +1,5 @@
 /// A bitstream used mainly for development and debugging ANS compressed bitstreams.
+/// See `ReverseBitWriter` and `ReverseBitReader` for the byte-packed implementation.
 pub struct DevReverseBitStream {
     // Store 1 bit in each u8.
     data: Vec<u8>,
@@ -14,6 +15,7 @@ impl DevReverseBitStream {
     }
 
     /// Return number of bits in the bit stream.
+    #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -57,6 +59,7 @@ impl DevReverseBitStream {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
 
@@ -0,0 +1,360 @@
+use std::io;
+
+use crate::bits::bit_ops::*;
+use crate::bits::PeekBits;
+
+// Buffer size.
+const BUF_SIZE: usize = 8 * 1024;
+
+// Largest number of bits that can be read at a time.
+const MAX_READ_BITS: u32 = 56;
+
+/// Read a bit stream whose bits are packed starting from the lsb of each byte, as in DEFLATE.
+pub struct LsbBitReader<'a> {
+    // The next bits to be read, starting from the lsb.
+    data: u64,
+
+    // Number of bits available in `data`.
+    bits_avail: u32,
+
+    // Internal buffer.
+    buf: Box<[u8; BUF_SIZE]>,
+
+    // Position in the buffer.
+    buf_pos: usize,
+
+    // End of the buffer.
+    buf_end: usize,
+
+    // Reader.
+    reader: &'a mut dyn io::Read,
+
+    // Number of bytes read.
+    bytes_read: usize,
+
+    // Number of zero bytes supplied past the end of the stream.
+    bytes_past_end: usize,
+
+    // The first error of `reader`, after which the stream is treated as ended.
+    error: Option<io::Error>,
+}
+
+impl<'a> LsbBitReader<'a> {
+    /// Create a new instance.
+    pub fn new(reader: &'a mut dyn io::Read) -> Self {
+        Self {
+            data: 0,
+            bits_avail: 0,
+            buf: Box::new([0; BUF_SIZE]),
+            buf_pos: 0,
+            buf_end: 0,
+            reader,
+            bytes_read: 0,
+            bytes_past_end: 0,
+            error: None,
+        }
+    }
+
+    /// Read the next `bits` number of bits. Up to 56 bits can be read.
+    ///
+    /// The first bit read is the lsb of the returned value. Past the end of the stream, zero bits
+    /// are returned.
+    pub fn read_bits(&mut self, bits: u32) -> u64 {
+        assert!(bits <= MAX_READ_BITS);
+        while self.bits_avail < bits {
+            let byte = self.next_byte().unwrap_or(0);
+            self.data |= (byte as u64) << self.bits_avail;
+            self.bits_avail += 8;
+        }
+        let result = self.data & low_bits_mask(bits);
+        self.data = shift_right(self.data, bits);
+        self.bits_avail -= bits;
+        result
+    }
+
+    /// Fill the data buffer with more bits so that more bits will be available via `peek()`.
+    pub fn fill_data(&mut self) {
+        let num_bytes = (64 - self.bits_avail) / 8;
+
+        // Fast path: we have >= 8 bytes available.
+        if self.buf_end - self.buf_pos >= 8 {
+            let bytes: [u8; 8] = self.buf[self.buf_pos..self.buf_pos + 8].try_into().unwrap();
+            let data = u64::from_le_bytes(bytes) & low_bits_mask(num_bytes * 8);
+            self.data |= shift_left(data, self.bits_avail);
+            self.bits_avail += num_bytes * 8;
+            self.buf_pos += num_bytes as usize;
+            return;
+        }
+
+        // Slow path: 1 byte at a time. Past the end of the stream, the data is padded with 0s.
+        for _ in 0..num_bytes {
+            let byte = self.next_byte().unwrap_or(0);
+            self.data |= (byte as u64) << self.bits_avail;
+            self.bits_avail += 8;
+        }
+    }
+
+    /// Peek at the current data buffer.
+    ///
+    /// The next bits to be read are msb-aligned, in stream order: the first bit of the stream,
+    /// which is the lsb of its byte, is the msb of the returned value. `bits_avail()` number of
+    /// bits are available.
+    #[inline]
+    pub fn peek(&self) -> u64 {
+        self.data.reverse_bits()
+    }
+
+    /// Returns number of bits in the `peek()` buffer.
+    #[inline]
+    pub fn bits_avail(&self) -> u32 {
+        self.bits_avail
+    }
+
+    /// Consume the next `bits` number of bits.
+    /// This assumes that `bits` <= `bits_avail()`.
+    pub fn consume(&mut self, bits: u32) {
+        self.data = shift_right(self.data, bits);
+        self.bits_avail -= bits;
+    }
+
+    /// Skip the bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        self.read_bits(self.bits_avail % 8);
+    }
+
+    /// Read whole bytes into `bytes`. The stream must be byte aligned.
+    ///
+    /// Returns the number of bytes read, which is less than `bytes.len()` at end of stream.
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> usize {
+        assert!(self.bits_avail.is_multiple_of(8));
+        let mut pos = 0;
+        while self.bits_avail > 0 && pos < bytes.len() {
+            bytes[pos] = self.read_bits(8) as u8;
+            pos += 1;
+        }
+        while pos < bytes.len() {
+            if self.buf_pos == self.buf_end {
+                self.fill_buf();
+                if self.buf_pos == self.buf_end {
+                    break;
+                }
+            }
+            let len = (self.buf_end - self.buf_pos).min(bytes.len() - pos);
+            bytes[pos..pos + len].copy_from_slice(&self.buf[self.buf_pos..self.buf_pos + len]);
+            self.buf_pos += len;
+            pos += len;
+        }
+        pos
+    }
+
+    /// Returns true if bits past the end of the stream have been read.
+    pub fn is_past_end(&self) -> bool {
+        // The padding bytes in `data` may not have been read yet.
+        self.bytes_past_end > (self.bits_avail / 8) as usize
+    }
+
+    /// Fails with the first error of the reader, or with `UnexpectedEof` if bits past the end of
+    /// the stream have been read. Decoders call this to stop on truncated or unreadable input.
+    pub fn check_status(&mut self) -> io::Result<()> {
+        if let Some(error) = self.take_error() {
+            return Err(error);
+        }
+        if self.is_past_end() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Read past the end of the stream",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Finish the reader and return number of bytes read, up to the last byte with bits read, or
+    /// the first error of the reader.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        if let Some(error) = self.take_error() {
+            return Err(error);
+        }
+        let unread_bytes = (self.bits_avail / 8) as usize;
+        Ok(self.bytes_read + self.bytes_past_end.min(unread_bytes)
+            - (self.buf_end - self.buf_pos)
+            - unread_bytes)
+    }
+
+    // Returns the first error of the reader. The reader stays failed, with an error of the same
+    // kind for later calls.
+    fn take_error(&mut self) -> Option<io::Error> {
+        let error = self.error.take()?;
+        self.error = Some(io::Error::from(error.kind()));
+        Some(error)
+    }
+
+    // Returns the next byte, or None at end of stream.
+    fn next_byte(&mut self) -> Option<u8> {
+        if self.buf_pos == self.buf_end {
+            self.fill_buf();
+            if self.buf_pos == self.buf_end {
+                self.bytes_past_end += 1;
+                return None;
+            }
+        }
+        let byte = self.buf[self.buf_pos];
+        self.buf_pos += 1;
+        Some(byte)
+    }
+
+    // Fill the buffer with more data.
+    fn fill_buf(&mut self) {
+        assert!(self.buf_pos >= self.buf_end);
+        self.buf_pos = 0;
+        if self.error.is_some() {
+            self.buf_end = 0;
+            return;
+        }
+        let result = loop {
+            match self.reader.read(&mut self.buf[..]) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result,
+            }
+        };
+        match result {
+            Ok(size) => {
+                self.buf_end = size;
+                self.bytes_read += size;
+            }
+            Err(e) => {
+                // Allow the reader to continue as if the stream ended, without reading again.
+                // The client gets the error from check_status() or finish().
+                self.buf_end = 0;
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl PeekBits for LsbBitReader<'_> {
+    fn fill_data(&mut self) {
+        LsbBitReader::fill_data(self)
+    }
+
+    fn peek(&self) -> u64 {
+        LsbBitReader::peek(self)
+    }
+
+    fn bits_avail(&self) -> u32 {
+        LsbBitReader::bits_avail(self)
+    }
+
+    fn consume(&mut self, bits: u32) {
+        LsbBitReader::consume(self, bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_bits() {
+        let data: Vec<u8> = vec![0b1111_1101, 0b0000_0111, 0x12, 0x34, 0x9a, 0x78, 0x56, 0x03];
+        let mut reader = io::Cursor::new(data);
+        let mut bit_reader = LsbBitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(1), 1);
+        assert_eq!(bit_reader.read_bits(2), 0b10);
+        assert_eq!(bit_reader.read_bits(8), 0xff);
+        bit_reader.align_to_byte();
+        let mut bytes = [0; 2];
+        assert_eq!(bit_reader.read_bytes(&mut bytes), 2);
+        assert_eq!(bytes, [0x12, 0x34]);
+        assert_eq!(bit_reader.read_bits(24), 0x56789a);
+        assert_eq!(bit_reader.read_bits(3), 0x3);
+        assert!(!bit_reader.is_past_end());
+        assert_eq!(bit_reader.finish().unwrap(), 8);
+
+        assert_eq!(bit_reader.read_bits(16), 0);
+        assert!(bit_reader.is_past_end());
+    }
+
+    #[test]
+    fn test_peek_consume() {
+        let data: Vec<u8> = (1..=20).collect();
+        let mut reader = io::Cursor::new(data);
+        let mut bit_reader = LsbBitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(3), 1);
+        bit_reader.fill_data();
+        assert_eq!(bit_reader.bits_avail(), 61);
+
+        // The bits after the first 3 bits of 0x01, then 0x02, in stream order.
+        assert_eq!(bit_reader.peek() >> 51, 0b00000_01000000);
+        bit_reader.consume(13);
+        assert_eq!(bit_reader.read_bits(8), 3);
+
+        // Mix with the slow path near the end of the stream, and past the end.
+        for expected in 4..=20u8 {
+            bit_reader.fill_data();
+            assert_eq!((bit_reader.peek() >> 56) as u8, expected.reverse_bits());
+            bit_reader.consume(8);
+        }
+        bit_reader.fill_data();
+        assert_eq!(bit_reader.peek(), 0);
+        assert!(!bit_reader.is_past_end());
+        assert_eq!(bit_reader.finish().unwrap(), 20);
+        bit_reader.consume(1);
+        assert!(bit_reader.is_past_end());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        use crate::bits::LsbBitWriter;
+        use rand::{rngs, Rng, SeedableRng};
+
+        let mut rng = rngs::SmallRng::seed_from_u64(0);
+        let values: Vec<(u64, u32)> = (0..10000)
+            .map(|_| {
+                let bits = rng.gen_range(0..=MAX_READ_BITS);
+                (rng.gen::<u64>() & low_bits_mask(bits), bits)
+            })
+            .collect();
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = LsbBitWriter::new(&mut writer);
+        for &(value, bits) in values.iter() {
+            bit_writer.write_bits(value, bits);
+        }
+        let bytes_written = bit_writer.finish().unwrap();
+
+        let mut reader = io::Cursor::new(writer.into_inner());
+        let mut bit_reader = LsbBitReader::new(&mut reader);
+        for &(value, bits) in values.iter() {
+            assert_eq!(bit_reader.read_bits(bits), value);
+        }
+        assert_eq!(bit_reader.finish().unwrap(), bytes_written);
+    }
+
+    // Fails every read.
+    struct ErrorReader;
+
+    impl io::Read for ErrorReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("Read failed"))
+        }
+    }
+
+    #[test]
+    // A read error ends the stream, and is returned by check_status() and finish().
+    fn test_read_error() {
+        let mut reader = io::Read::chain(&[0x12][..], ErrorReader);
+        let mut bit_reader = LsbBitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(8), 0x12);
+        assert!(bit_reader.check_status().is_ok());
+        assert_eq!(bit_reader.read_bits(16), 0);
+
+        let error = bit_reader.check_status().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(error.to_string(), "Read failed");
+
+        // The error is sticky.
+        assert_eq!(
+            bit_reader.finish().unwrap_err().kind(),
+            io::ErrorKind::Other
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::io;
+
+use crate::bits::bit_ops::*;
+
+/// Number of bytes to buffer before flushing.
+const BUF_SIZE: usize = 8 * 1024;
+
+// Largest number of bits that can be written at a time.
+const MAX_WRITE_BITS: u32 = 56;
+
+/// A bit stream writer that packs bits starting from the lsb of each byte, as in DEFLATE.
+pub struct LsbBitWriter<'a> {
+    // The bits to be written, starting from the lsb.
+    data: u64,
+
+    // Number of bits in `data`. Less than 8 between writes.
+    bits_used: u32,
+
+    // Buffer to store the bytes to be written out to `writer`.
+    buf: Vec<u8>,
+
+    // External writer.
+    writer: &'a mut dyn io::Write,
+
+    // Total number of bytes written.
+    bytes_written: usize,
+
+    // The first error of `writer`, after which nothing more is written.
+    error: Option<io::Error>,
+}
+
+impl<'a> LsbBitWriter<'a> {
+    /// Create a new instance.
+    pub fn new(writer: &'a mut dyn io::Write) -> Self {
+        Self {
+            data: 0,
+            bits_used: 0,
+            buf: Vec::with_capacity(BUF_SIZE),
+            writer,
+            bytes_written: 0,
+            error: None,
+        }
+    }
+
+    /// Write the low `bits` number of bits of `data`, lsb first. Up to 56 bits can be written.
+    pub fn write_bits(&mut self, data: u64, bits: u32) {
+        assert!(bits <= MAX_WRITE_BITS);
+        self.data |= (data & low_bits_mask(bits)) << self.bits_used;
+        self.bits_used += bits;
+        while self.bits_used >= 8 {
+            self.buf.push(self.data as u8);
+            self.data >>= 8;
+            self.bits_used -= 8;
+        }
+        if self.buf.len() >= BUF_SIZE {
+            self.flush();
+        }
+    }
+
+    /// Write a prefix code of `bits` bits, msb first, as Huffman codes are packed in DEFLATE.
+    ///
+    /// The code reads back msb-aligned from `LsbBitReader::peek()`, the same as a code written
+    /// by `BitWriter::write_bits()` does from `BitReader::peek()`.
+    pub fn write_code(&mut self, code: u64, bits: u32) {
+        self.write_bits(shift_right(code.reverse_bits(), 64 - bits), bits);
+    }
+
+    /// Write zero bits up to the next byte boundary.
+    pub fn align_to_byte(&mut self) {
+        self.write_bits(0, (8 - self.bits_used) % 8);
+    }
+
+    /// Write whole bytes. The stream must be byte aligned.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        assert!(self.bits_used == 0);
+        self.buf.extend_from_slice(bytes);
+        if self.buf.len() >= BUF_SIZE {
+            self.flush();
+        }
+    }
+
+    /// Finish writing and return the total number of bytes written, or the first error of the
+    /// writer. The last byte is padded with zero bits.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        self.align_to_byte();
+        self.flush();
+        match self.error.take() {
+            Some(error) => Err(error),
+            None => Ok(self.bytes_written),
+        }
+    }
+
+    // Flush the buffer to the writer.
+    fn flush(&mut self) {
+        if self.error.is_none() {
+            if let Err(e) = self.writer.write_all(&self.buf) {
+                // Allow the writer to continue, discarding the output.
+                // The client gets the error from finish().
+                self.error = Some(e);
+            }
+        }
+        self.bytes_written += self.buf.len();
+        self.buf.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_bits() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = LsbBitWriter::new(&mut writer);
+        bit_writer.write_bits(1, 1);
+        bit_writer.write_bits(0b10, 2);
+        bit_writer.write_bits(0x1ff, 8);
+        bit_writer.align_to_byte();
+        bit_writer.write_bytes(&[0x12, 0x34]);
+        bit_writer.write_bits(0x56789a, 24);
+        bit_writer.write_bits(0x3, 3);
+        assert_eq!(bit_writer.finish().unwrap(), 8);
+        assert_eq!(
+            writer.into_inner(),
+            vec![0b1111_1101, 0b0000_0111, 0x12, 0x34, 0x9a, 0x78, 0x56, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_write_code() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = LsbBitWriter::new(&mut writer);
+        bit_writer.write_code(0b110, 3);
+        bit_writer.write_code(0b0_0001, 5);
+        bit_writer.write_code(0, 0);
+        bit_writer.write_code(0b1, 1);
+        assert_eq!(bit_writer.finish().unwrap(), 2);
+        assert_eq!(writer.into_inner(), vec![0b1000_0011, 0b0000_0001]);
+    }
+
+    #[test]
+    fn test_write_error() {
+        // Writing to a full slice fails, and the error is returned by finish().
+        let mut buf = [0; 1];
+        let mut writer: &mut [u8] = &mut buf;
+        let mut bit_writer = LsbBitWriter::new(&mut writer);
+        bit_writer.write_bits(0x1234, 16);
+        let error = bit_writer.finish().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+    }
+}
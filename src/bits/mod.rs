@@ -2,7 +2,21 @@ mod bit_ops;
 mod bit_reader;
 mod bit_writer;
 mod dev_bit_stream;
+mod lsb_bit_reader;
+mod lsb_bit_writer;
+mod peek_bits;
+mod reverse_bit_reader;
+mod reverse_bit_writer;
+mod slice_bit_reader;
+mod universal_codes;
 
 pub use bit_reader::BitReader;
 pub use bit_writer::BitWriter;
 pub use dev_bit_stream::DevReverseBitStream;
+pub use lsb_bit_reader::LsbBitReader;
+pub use lsb_bit_writer::LsbBitWriter;
+pub use peek_bits::PeekBits;
+pub use reverse_bit_reader::ReverseBitReader;
+pub use reverse_bit_writer::ReverseBitWriter;
+pub use slice_bit_reader::SliceBitReader;
+pub use universal_codes::{AdaptiveRice, MAX_UNARY_VALUE};
@@ -0,0 +1,19 @@
+/// A bit reader that allows peeking at the next bits before consuming them, as used by the
+/// table based prefix code decoders.
+///
+/// The next bits are presented in stream order, msb-aligned, whatever the bit order of the
+/// bytes. The first bit read from the stream is the msb of `peek()`.
+pub trait PeekBits {
+    /// Fill the data buffer with more bits so that more bits will be available via `peek()`.
+    fn fill_data(&mut self);
+
+    /// Peek at the current data buffer. `bits_avail()` number of bits are available.
+    fn peek(&self) -> u64;
+
+    /// Returns number of bits in the `peek()` buffer.
+    fn bits_avail(&self) -> u32;
+
+    /// Consume the next `bits` number of bits.
+    /// This assumes that `bits` <= `bits_avail()`.
+    fn consume(&mut self, bits: u32);
+}
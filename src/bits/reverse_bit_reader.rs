@@ -0,0 +1,132 @@
+use std::io;
+
+use crate::base::DebugLog;
+use crate::bits::BitReader;
+
+// If true, print debug information.
+const DEBUG: bool = false;
+
+// Debug log.
+const LOG: DebugLog = DebugLog::new("ReverseBitReader");
+
+/// Reads a bit stream written by `ReverseBitWriter`.
+///
+/// Values are returned in the reverse order that they were written.
+pub struct ReverseBitReader<'a> {
+    bit_reader: BitReader<'a>,
+
+    // Set if the stream does not start with a valid marker.
+    missing_marker: bool,
+}
+
+impl<'a> ReverseBitReader<'a> {
+    /// Create a new instance. This reads the start of the stream.
+    pub fn new(reader: &'a mut dyn io::Read) -> Self {
+        let mut bit_reader = BitReader::new(reader);
+
+        // Skip the zero padding and the marker bit, which is in the first byte.
+        bit_reader.fill_data();
+        let padding = bit_reader.peek().leading_zeros();
+        let missing_marker = padding >= 8;
+        if missing_marker {
+            LOG.print("Missing marker bit");
+        } else {
+            if DEBUG {
+                LOG.print(&format!("Skipping {} padding bits", padding));
+            }
+            bit_reader.consume(padding + 1);
+        }
+
+        Self {
+            bit_reader,
+            missing_marker,
+        }
+    }
+
+    /// Read the next `bits` number of bits.
+    ///
+    /// Returned value is lsb-aligned.
+    #[inline]
+    pub fn read_bits(&mut self, bits: u32) -> u64 {
+        self.bit_reader.read_bits(bits)
+    }
+
+    /// Returns true if bits past the end of the stream have been read.
+    pub fn is_past_end(&self) -> bool {
+        self.bit_reader.is_past_end()
+    }
+
+    /// Fails with the first error of the reader, with `UnexpectedEof` if bits past the end of the
+    /// stream have been read, or with `InvalidData` if the stream doesn't start with a marker.
+    pub fn check_status(&mut self) -> io::Result<()> {
+        self.bit_reader.check_status()?;
+        if self.missing_marker {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing marker bit",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Finish the reader and return number of bytes read, or the first read error.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        self.bit_reader.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bits::bit_ops::low_bits_mask;
+    use crate::bits::ReverseBitWriter;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_empty_stream() {
+        let mut reader = Cursor::new(Vec::new());
+        let mut bit_reader = ReverseBitReader::new(&mut reader);
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_read_bits() {
+        let mut reader = Cursor::new(vec![0x13, 0x45, 0x12]);
+        let mut bit_reader = ReverseBitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_bits(12), 0x345);
+        assert_eq!(bit_reader.read_bits(8), 0x12);
+        assert!(bit_reader.check_status().is_ok());
+        assert_eq!(bit_reader.finish().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_random() {
+        for s in 0..20 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let values: Vec<(u64, u32)> = (0..1000)
+                .map(|_| {
+                    let bits = rng.gen_range(0..=64);
+                    (rng.gen::<u64>() & low_bits_mask(bits), bits)
+                })
+                .collect();
+
+            let mut cursor = Cursor::new(Vec::new());
+            let mut writer = ReverseBitWriter::new(&mut cursor);
+            for &(value, bits) in values.iter() {
+                writer.write_bits(value, bits);
+            }
+            writer.finish().unwrap();
+
+            let mut cursor = Cursor::new(cursor.into_inner());
+            let mut reader = ReverseBitReader::new(&mut cursor);
+            for &(value, bits) in values.iter().rev() {
+                assert_eq!(reader.read_bits(bits), value);
+            }
+            assert!(reader.check_status().is_ok());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::io;
+
+use crate::base::DebugLog;
+use crate::bits::bit_ops::*;
+
+// If true, print debug information.
+const DEBUG: bool = false;
+
+// Debug log.
+const LOG: DebugLog = DebugLog::new("ReverseBitWriter");
+
+/// A bit stream writer whose output is read back in reverse order.
+///
+/// The bits written are buffered in memory until `finish()`. The output is laid out so that a
+/// `ReverseBitReader` reads the values in the reverse order that they were written, i.e. the last
+/// value written is the first one read. This is the order needed by ANS coders, which encode
+/// symbols backwards so that they can be decoded forwards.
+///
+/// Output format: the last (partial) word is written first, prefixed with zero padding and a
+/// single 1 marker bit so that the stream is byte aligned. This is followed by the full 64-bit
+/// words in reverse order. Each value is stored msb first, so the reader can use regular msb-first
+/// bit reading.
+pub struct ReverseBitWriter<'a> {
+    // The current word. New bits are added above the existing bits.
+    data: u64,
+
+    // Number of bits used in `data`.
+    bits_used: u32,
+
+    // Full words, in the order that they were filled.
+    words: Vec<u64>,
+
+    // External writer.
+    writer: &'a mut dyn io::Write,
+}
+
+impl<'a> ReverseBitWriter<'a> {
+    /// Create a new instance.
+    pub fn new(writer: &'a mut dyn io::Write) -> Self {
+        Self {
+            data: 0,
+            bits_used: 0,
+            words: Vec::new(),
+            writer,
+        }
+    }
+
+    /// Write `bits` number of bits from `data` (lsb aligned).
+    pub fn write_bits(&mut self, data: u64, bits: u32) {
+        if DEBUG {
+            LOG.print(&format!("write_bits {:#x} {}", data, bits));
+        }
+        let data = data & low_bits_mask(bits);
+
+        // Fast path: we have enough space in self.data.
+        let bits_avail = 64 - self.bits_used;
+        if bits_avail >= bits {
+            self.data |= shift_left(data, self.bits_used);
+            self.bits_used += bits;
+            return;
+        }
+
+        // The low bits fill up the current word. They are read after the high bits, which go
+        // into the next word.
+        self.data |= shift_left(data, self.bits_used);
+        self.words.push(self.data);
+        self.data = shift_right(data, bits_avail);
+        self.bits_used = bits - bits_avail;
+    }
+
+    /// Return the number of bits written so far.
+    pub fn len(&self) -> usize {
+        self.words.len() * 64 + self.bits_used as usize
+    }
+
+    /// Return true if no bits have been written.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Finish writing and return the total number of bytes written, or the error of the writer.
+    /// Can only be called once.
+    pub fn finish(&mut self) -> io::Result<usize> {
+        if DEBUG {
+            LOG.print("Finish");
+        }
+
+        // Add the marker bit.
+        self.write_bits(1, 1);
+
+        // Output the partial word, without its leading zero bytes.
+        let num_bytes = self.bits_used.div_ceil(8) as usize;
+        let mut buf: Vec<u8> = Vec::with_capacity(num_bytes + self.words.len() * 8);
+        buf.extend_from_slice(&self.data.to_be_bytes()[8 - num_bytes..]);
+
+        // Output the full words, last one first.
+        for word in self.words.iter().rev() {
+            buf.extend_from_slice(&word.to_be_bytes());
+        }
+
+        self.data = 0;
+        self.bits_used = 0;
+        self.words.clear();
+        if let Err(e) = self.writer.write_all(&buf) {
+            LOG.print(&format!("Error writing to writer: {}", e));
+            return Err(e);
+        }
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marker_only() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bw = ReverseBitWriter::new(&mut writer);
+        assert!(bw.is_empty());
+        assert_eq!(bw.finish().unwrap(), 1);
+        assert_eq!(writer.into_inner(), vec![0x01]);
+    }
+
+    #[test]
+    fn test_reverse_order() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bw = ReverseBitWriter::new(&mut writer);
+
+        bw.write_bits(0x12, 8);
+        bw.write_bits(0x345, 12);
+        assert_eq!(bw.len(), 20);
+        assert_eq!(bw.finish().unwrap(), 3);
+
+        // Marker bit, then 0x345, then 0x12.
+        assert_eq!(writer.into_inner(), vec![0x13, 0x45, 0x12]);
+    }
+
+    #[test]
+    fn test_word_boundary() {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bw = ReverseBitWriter::new(&mut writer);
+
+        bw.write_bits(0x1234567890AB, 48);
+        bw.write_bits(0xCDEF01, 24);
+        assert_eq!(bw.finish().unwrap(), 10);
+        assert_eq!(
+            writer.into_inner(),
+            vec![0x01, 0xCD, 0xEF, 0x01, 0x12, 0x34, 0x56, 0x78, 0x90, 0xAB]
+        );
+    }
+
+    #[test]
+    fn test_write_error() {
+        let mut buf = [0; 2];
+        let mut writer: &mut [u8] = &mut buf;
+        let mut bw = ReverseBitWriter::new(&mut writer);
+        bw.write_bits(0x12345, 20);
+        assert_eq!(bw.finish().unwrap_err().kind(), io::ErrorKind::WriteZero);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::bits::bit_ops::*;
+use crate::bits::PeekBits;
+
+/// Read a bit stream from a byte slice.
+///
+/// Unlike `BitReader`, the whole stream is in memory, so there is no internal buffer and no
+/// reader to fail. This makes the reader small and cheap to refill, for decoders that advance
+/// several streams at a time.
+pub struct SliceBitReader<'a> {
+    // The current data buffer. Stores the next bits aligned to msb.
+    data: u64,
+
+    // Number of bits available in `data`.
+    bits_avail: u32,
+
+    // The stream.
+    bytes: &'a [u8],
+
+    // Position of the next byte in `bytes`.
+    pos: usize,
+
+    // Number of zero bytes supplied past the end of the stream.
+    bytes_past_end: usize,
+}
+
+impl<'a> SliceBitReader<'a> {
+    /// Create a new instance.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            data: 0,
+            bits_avail: 0,
+            bytes,
+            pos: 0,
+            bytes_past_end: 0,
+        }
+    }
+
+    /// Fill the data buffer with more bits so that more bits will be available via `peek()`.
+    #[inline]
+    pub fn fill_data(&mut self) {
+        let num_bytes = (64 - self.bits_avail) / 8;
+        let data = self.next_bytes(num_bytes as usize);
+
+        self.data |= shift_right(data, self.bits_avail);
+        self.bits_avail += num_bytes * 8;
+    }
+
+    /// Peek at the current data buffer.
+    ///
+    /// The next bits to be read are msb-aligned. `bits_avail()` number of bits are available.
+    #[inline]
+    pub fn peek(&self) -> u64 {
+        self.data
+    }
+
+    /// Returns number of bits in the `data()` buffer.
+    #[inline]
+    pub fn bits_avail(&self) -> u32 {
+        self.bits_avail
+    }
+
+    /// Consume the next `bits` number of bits.
+    /// This assumes that `bits`` <= `bits_avail()`.
+    #[inline]
+    pub fn consume(&mut self, bits: u32) {
+        self.data = shift_left(self.data, bits);
+        self.bits_avail -= bits;
+    }
+
+    /// Returns true if bits past the end of the stream have been read.
+    pub fn is_past_end(&self) -> bool {
+        // The padding bytes in `data` may not have been read yet.
+        self.bytes_past_end > (self.bits_avail / 8) as usize
+    }
+
+    // Reads the next `num_bytes` bytes.
+    // Returns the data in big-endian format. The data may contain more than `num_bytes` bytes.
+    #[inline]
+    fn next_bytes(&mut self, num_bytes: usize) -> u64 {
+        // Fast path: we have >= 8 bytes available.
+        if let Some(bytes) = self.bytes.get(self.pos..self.pos + 8) {
+            self.pos += num_bytes;
+            return u64::from_be_bytes(bytes.try_into().unwrap());
+        }
+
+        // Slow path: near the end, pad with 0s.
+        let mut data: [u8; 8] = [0; 8];
+        let len = num_bytes.min(self.bytes.len() - self.pos);
+        data[..len].copy_from_slice(&self.bytes[self.pos..self.pos + len]);
+        self.pos += len;
+        self.bytes_past_end += num_bytes - len;
+        u64::from_be_bytes(data)
+    }
+}
+
+impl PeekBits for SliceBitReader<'_> {
+    #[inline]
+    fn fill_data(&mut self) {
+        SliceBitReader::fill_data(self)
+    }
+
+    #[inline]
+    fn peek(&self) -> u64 {
+        SliceBitReader::peek(self)
+    }
+
+    #[inline]
+    fn bits_avail(&self) -> u32 {
+        SliceBitReader::bits_avail(self)
+    }
+
+    #[inline]
+    fn consume(&mut self, bits: u32) {
+        SliceBitReader::consume(self, bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // Peeked bits match the stream, with 0 bits past the end.
+    fn test_peek() {
+        let bytes: Vec<u8> = (1..=10).collect();
+        let mut bit_reader = SliceBitReader::new(&bytes);
+        bit_reader.fill_data();
+        assert_eq!(bit_reader.peek(), 0x0102030405060708);
+        assert_eq!(bit_reader.bits_avail(), 64);
+        bit_reader.consume(12);
+        bit_reader.fill_data();
+        assert_eq!(bit_reader.peek(), 0x2030405060708090);
+        assert_eq!(bit_reader.bits_avail(), 60);
+        bit_reader.consume(60);
+        bit_reader.fill_data();
+        assert_eq!(bit_reader.peek(), 0x0a00000000000000);
+        assert!(!bit_reader.is_past_end());
+        bit_reader.consume(8);
+        assert!(!bit_reader.is_past_end());
+        bit_reader.consume(1);
+        assert!(bit_reader.is_past_end());
+    }
+}
@@ -0,0 +1,280 @@
+// Universal codes for integers: unary, Elias gamma, Elias delta, Exp-Golomb and Golomb-Rice.
+
+use crate::bits::bit_ops::low_bits_mask;
+use crate::bits::{BitReader, BitWriter};
+
+/// Maximum value that can be coded in unary, including the quotient of Rice codes.
+pub const MAX_UNARY_VALUE: u64 = 1 << 16;
+
+// Maximum parameter chosen by `AdaptiveRice`.
+const MAX_RICE_PARAMETER: u32 = 32;
+
+// Halve the statistics of `AdaptiveRice` after this number of values.
+const ADAPTIVE_RICE_RESET: u64 = 64;
+
+impl BitWriter<'_> {
+    /// Write `value` in unary: `value` zero bits followed by a one bit.
+    pub fn write_unary(&mut self, value: u64) {
+        assert!(value <= MAX_UNARY_VALUE);
+        let mut zeros = value;
+        while zeros >= 63 {
+            self.write_bits(0, 63);
+            zeros -= 63;
+        }
+        self.write_bits(1, zeros as u32 + 1);
+    }
+
+    /// Write `value` >= 1 in Elias gamma code: the number of bits after the leading one bit in
+    /// unary, followed by those bits.
+    pub fn write_elias_gamma(&mut self, value: u64) {
+        assert!(value > 0);
+        let num_bits = 63 - value.leading_zeros();
+        self.write_bits(0, num_bits);
+        self.write_bits(value, num_bits + 1);
+    }
+
+    /// Write `value` >= 1 in Elias delta code: the number of bits in Elias gamma code, followed
+    /// by the bits after the leading one bit.
+    pub fn write_elias_delta(&mut self, value: u64) {
+        assert!(value > 0);
+        let num_bits = 63 - value.leading_zeros();
+        self.write_elias_gamma(num_bits as u64 + 1);
+        self.write_bits(value & low_bits_mask(num_bits), num_bits);
+    }
+
+    /// Write `value` in Exp-Golomb code of order `k`: `(value >> k) + 1` in Elias gamma code,
+    /// followed by the low `k` bits.
+    pub fn write_exp_golomb(&mut self, value: u64, k: u32) {
+        assert!(k < 64 && (value >> k) < u64::MAX);
+        self.write_elias_gamma((value >> k) + 1);
+        self.write_bits(value & low_bits_mask(k), k);
+    }
+
+    /// Write `value` in Golomb-Rice code with parameter `k`: `value >> k` in unary, followed by
+    /// the low `k` bits. `value >> k` must be at most `MAX_UNARY_VALUE`.
+    pub fn write_rice(&mut self, value: u64, k: u32) {
+        assert!(k < 64);
+        self.write_unary(value >> k);
+        self.write_bits(value & low_bits_mask(k), k);
+    }
+}
+
+impl BitReader<'_> {
+    /// Read a value in unary code.
+    ///
+    /// A value larger than `MAX_UNARY_VALUE` is a read error.
+    pub fn read_unary(&mut self) -> u64 {
+        let mut value: u64 = 0;
+        loop {
+            if self.bits_avail() < 32 {
+                self.fill_data();
+            }
+            let bits_avail = self.bits_avail();
+            let zeros = self.peek().leading_zeros().min(bits_avail);
+            if zeros < bits_avail {
+                self.consume(zeros + 1);
+                return value + zeros as u64;
+            }
+            self.consume(zeros);
+            value += zeros as u64;
+            if value > MAX_UNARY_VALUE {
+                self.add_read_error("Invalid universal code");
+                return value;
+            }
+        }
+    }
+
+    /// Read a value in Elias gamma code. Returns 0 on error.
+    pub fn read_elias_gamma(&mut self) -> u64 {
+        let num_bits = self.read_unary();
+        if num_bits > 63 {
+            self.add_read_error("Invalid universal code");
+            return 0;
+        }
+        (1 << num_bits) | self.read_bits(num_bits as u32)
+    }
+
+    /// Read a value in Elias delta code. Returns 0 on error.
+    pub fn read_elias_delta(&mut self) -> u64 {
+        let num_bits = self.read_elias_gamma();
+        if num_bits == 0 || num_bits > 64 {
+            self.add_read_error("Invalid universal code");
+            return 0;
+        }
+        (1 << (num_bits - 1)) | self.read_bits(num_bits as u32 - 1)
+    }
+
+    /// Read a value in Exp-Golomb code of order `k`.
+    pub fn read_exp_golomb(&mut self, k: u32) -> u64 {
+        let high = self.read_elias_gamma().wrapping_sub(1);
+        (high << k) | self.read_bits(k)
+    }
+
+    /// Read a value in Golomb-Rice code with parameter `k`.
+    pub fn read_rice(&mut self, k: u32) -> u64 {
+        let high = self.read_unary();
+        (high << k) | self.read_bits(k)
+    }
+}
+
+/// Estimates the Golomb-Rice parameter from the recent values, as in LOCO-I.
+///
+/// The encoder and the decoder must update the estimator with the same values.
+pub struct AdaptiveRice {
+    // Sum of the recent values.
+    sum: u64,
+
+    // Number of the recent values.
+    count: u64,
+}
+
+impl Default for AdaptiveRice {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AdaptiveRice {
+    pub fn new() -> Self {
+        Self { sum: 0, count: 1 }
+    }
+
+    /// Returns the parameter for the next value.
+    pub fn parameter(&self) -> u32 {
+        let mut k = 0;
+        while k < MAX_RICE_PARAMETER && (self.count << k) < self.sum {
+            k += 1;
+        }
+        k
+    }
+
+    /// Adds the coded `value` to the statistics.
+    pub fn update(&mut self, value: u64) {
+        self.sum = self.sum.saturating_add(value);
+        self.count += 1;
+        if self.count >= ADAPTIVE_RICE_RESET {
+            self.sum >>= 1;
+            self.count >>= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io;
+
+    // Writes the values with `write`, then reads them back with `read`.
+    fn round_trip(
+        values: &[u64],
+        write: fn(&mut BitWriter, u64),
+        read: fn(&mut BitReader) -> u64,
+    ) -> usize {
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = BitWriter::new(&mut writer);
+        for &value in values.iter() {
+            write(&mut bit_writer, value);
+        }
+        let bytes_written = bit_writer.finish().unwrap();
+
+        let mut reader = io::Cursor::new(writer.into_inner());
+        let mut bit_reader = BitReader::new(&mut reader);
+        for &value in values.iter() {
+            assert_eq!(read(&mut bit_reader), value);
+        }
+        assert!(bit_reader.check_status().is_ok());
+        bytes_written
+    }
+
+    fn test_values() -> Vec<u64> {
+        let mut rng = rngs::SmallRng::seed_from_u64(0);
+        let mut values: Vec<u64> = (1..300).collect();
+        values.extend([u32::MAX as u64, u64::MAX >> 1, u64::MAX - 1]);
+        for _ in 0..1000 {
+            let bits = rng.gen_range(1..=63);
+            values.push(rng.gen::<u64>() & low_bits_mask(bits) | 1);
+        }
+        values
+    }
+
+    #[test]
+    fn test_elias_gamma() {
+        // 1 -> "1", 2 -> "010", 5 -> "00101".
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = BitWriter::new(&mut writer);
+        bit_writer.write_elias_gamma(1);
+        bit_writer.write_elias_gamma(2);
+        bit_writer.write_elias_gamma(5);
+        bit_writer.finish().unwrap();
+        assert_eq!(writer.into_inner(), vec![0b1010_0010, 0b1000_0000]);
+
+        round_trip(
+            &test_values(),
+            |w, v| w.write_elias_gamma(v),
+            |r| r.read_elias_gamma(),
+        );
+    }
+
+    #[test]
+    fn test_elias_delta() {
+        round_trip(
+            &test_values(),
+            |w, v| w.write_elias_delta(v),
+            |r| r.read_elias_delta(),
+        );
+    }
+
+    #[test]
+    fn test_exp_golomb() {
+        let mut values = test_values();
+        values.push(0);
+        round_trip(
+            &values,
+            |w, v| w.write_exp_golomb(v, 0),
+            |r| r.read_exp_golomb(0),
+        );
+        round_trip(
+            &values,
+            |w, v| w.write_exp_golomb(v, 3),
+            |r| r.read_exp_golomb(3),
+        );
+    }
+
+    #[test]
+    fn test_rice() {
+        let values: Vec<u64> = (0..1000).map(|i| i * 37 % 500).collect();
+        round_trip(&values, |w, v| w.write_rice(v, 4), |r| r.read_rice(4));
+        round_trip(&values, |w, v| w.write_rice(v, 0), |r| r.read_rice(0));
+        round_trip(
+            &[MAX_UNARY_VALUE],
+            |w, v| w.write_unary(v),
+            |r| r.read_unary(),
+        );
+    }
+
+    #[test]
+    fn test_corrupt_unary() {
+        let mut reader = io::Cursor::new(vec![0; MAX_UNARY_VALUE as usize / 8 + 100]);
+        let mut bit_reader = BitReader::new(&mut reader);
+        assert_eq!(bit_reader.read_elias_gamma(), 0);
+        assert_eq!(
+            bit_reader.check_status().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_adaptive_rice() {
+        let mut rice = AdaptiveRice::new();
+        assert_eq!(rice.parameter(), 0);
+        for _ in 0..100 {
+            rice.update(1000);
+        }
+        assert_eq!(rice.parameter(), 10);
+        for _ in 0..500 {
+            rice.update(1);
+        }
+        assert!(rice.parameter() <= 1);
+    }
+}
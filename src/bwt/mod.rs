@@ -0,0 +1,4 @@
+mod suffix_array;
+mod transform;
+
+pub use transform::{forward_transform, inverse_transform};
@@ -0,0 +1,185 @@
+// Marks an empty slot of the suffix array during induced sorting.
+const EMPTY: u32 = u32::MAX;
+
+/// Returns the suffix array of `data`: the start positions of its suffixes in sorted order.
+///
+/// This uses SA-IS (induced sorting), which runs in linear time. A suffix that is a prefix of
+/// another suffix sorts before it.
+#[cfg(test)]
+pub fn suffix_array(data: &[u8]) -> Vec<u32> {
+    let text: Vec<u32> = data.iter().map(|&byte| byte as u32).collect();
+    suffix_array_of_symbols(&text, u8::MAX as u32)
+}
+
+/// Returns the suffix array of `text` of symbols up to `max_symbol`.
+pub fn suffix_array_of_symbols(text: &[u32], max_symbol: u32) -> Vec<u32> {
+    assert!(text.len() < EMPTY as usize);
+    let n = text.len();
+    match n {
+        0 => return Vec::new(),
+        1 => return vec![0],
+        2 => {
+            return if text[0] < text[1] {
+                vec![0, 1]
+            } else {
+                vec![1, 0]
+            }
+        }
+        _ => {}
+    }
+
+    // Classify the suffixes: S-type if smaller than the next suffix, else L-type.
+    // The last suffix is L-type, as it's larger than the empty suffix.
+    let mut is_s = vec![false; n];
+    for i in (0..n - 1).rev() {
+        is_s[i] = if text[i] == text[i + 1] {
+            is_s[i + 1]
+        } else {
+            text[i] < text[i + 1]
+        };
+    }
+
+    // Bucket boundaries of each symbol: the L-type suffixes come first in each bucket, so
+    // `l_starts` is the start of each bucket and `s_starts` the start of its S-type suffixes.
+    let num_symbols = max_symbol as usize + 1;
+    let mut l_starts = vec![0u32; num_symbols + 1];
+    let mut s_starts = vec![0u32; num_symbols + 1];
+    for (i, &symbol) in text.iter().enumerate() {
+        if is_s[i] {
+            l_starts[symbol as usize + 1] += 1;
+        } else {
+            s_starts[symbol as usize] += 1;
+        }
+    }
+    for i in 0..num_symbols {
+        s_starts[i] += l_starts[i];
+        l_starts[i + 1] += s_starts[i];
+    }
+
+    // A leftmost S-type (LMS) position is an S-type suffix following an L-type suffix.
+    let is_lms = |i: usize| i > 0 && is_s[i] && !is_s[i - 1];
+    let lms: Vec<u32> = (1..n).filter(|&i| is_lms(i)).map(|i| i as u32).collect();
+
+    let mut sa = vec![EMPTY; n];
+    let induce = |sa: &mut [u32], lms: &[u32]| {
+        sa.fill(EMPTY);
+        let mut next = s_starts.clone();
+        for &pos in lms.iter() {
+            let symbol = text[pos as usize] as usize;
+            sa[next[symbol] as usize] = pos;
+            next[symbol] += 1;
+        }
+
+        // Induce the L-type suffixes from left to right.
+        next.copy_from_slice(&l_starts);
+        let symbol = text[n - 1] as usize;
+        sa[next[symbol] as usize] = (n - 1) as u32;
+        next[symbol] += 1;
+        for i in 0..n {
+            let pos = sa[i];
+            if pos != EMPTY && pos > 0 && !is_s[pos as usize - 1] {
+                let symbol = text[pos as usize - 1] as usize;
+                sa[next[symbol] as usize] = pos - 1;
+                next[symbol] += 1;
+            }
+        }
+
+        // Induce the S-type suffixes from right to left.
+        next.copy_from_slice(&l_starts);
+        for i in (0..n).rev() {
+            let pos = sa[i];
+            if pos != EMPTY && pos > 0 && is_s[pos as usize - 1] {
+                let symbol = text[pos as usize - 1] as usize;
+                next[symbol + 1] -= 1;
+                sa[next[symbol + 1] as usize] = pos - 1;
+            }
+        }
+    };
+
+    // Sort the LMS substrings by induced sorting from their unsorted positions.
+    induce(&mut sa, &lms);
+    if lms.is_empty() {
+        return sa;
+    }
+
+    // Name the LMS substrings by their rank, giving equal substrings the same name.
+    let mut lms_index = vec![EMPTY; n];
+    for (index, &pos) in lms.iter().enumerate() {
+        lms_index[pos as usize] = index as u32;
+    }
+    let sorted_lms: Vec<u32> = sa
+        .iter()
+        .copied()
+        .filter(|&pos| lms_index[pos as usize] != EMPTY)
+        .collect();
+    let substring_end = |pos: u32| {
+        let index = lms_index[pos as usize] as usize + 1;
+        if index < lms.len() {
+            lms[index] as usize
+        } else {
+            n
+        }
+    };
+    let mut reduced = vec![0u32; lms.len()];
+    let mut name = 0;
+    for i in 1..sorted_lms.len() {
+        let (left, right) = (sorted_lms[i - 1] as usize, sorted_lms[i] as usize);
+        let (left_end, right_end) = (substring_end(left as u32), substring_end(right as u32));
+        let same = left_end - left == right_end - right
+            && right_end < n
+            && left_end < n
+            && text[left..=left_end] == text[right..=right_end];
+        if !same {
+            name += 1;
+        }
+        reduced[lms_index[right] as usize] = name;
+    }
+
+    // Sort the LMS suffixes by recursing on the names, then induce the order of all suffixes.
+    let reduced_sa = suffix_array_of_symbols(&reduced, name);
+    let sorted_lms: Vec<u32> = reduced_sa
+        .iter()
+        .map(|&index| lms[index as usize])
+        .collect();
+    induce(&mut sa, &sorted_lms);
+    sa
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+
+    fn naive_suffix_array(data: &[u8]) -> Vec<u32> {
+        let mut sa: Vec<u32> = (0..data.len() as u32).collect();
+        sa.sort_by_key(|&pos| &data[pos as usize..]);
+        sa
+    }
+
+    #[test]
+    fn test_suffix_array() {
+        assert_eq!(suffix_array(b""), Vec::<u32>::new());
+        assert_eq!(suffix_array(b"a"), vec![0]);
+        assert_eq!(suffix_array(b"banana"), vec![5, 3, 1, 0, 4, 2]);
+        assert_eq!(suffix_array(b"aaaa"), vec![3, 2, 1, 0]);
+        assert_eq!(
+            suffix_array(b"mississippi"),
+            vec![10, 7, 4, 1, 0, 9, 8, 6, 3, 5, 2]
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rng = rngs::SmallRng::seed_from_u64(0);
+        for len in [3, 10, 100, 1000, 10000] {
+            for alphabet in [1, 2, 4, 256] {
+                let data: Vec<u8> = (0..len).map(|_| rng.gen_range(0..alphabet) as u8).collect();
+                assert_eq!(suffix_array(&data), naive_suffix_array(&data));
+            }
+        }
+
+        // Repetitive data, with deep recursion.
+        let data = b"abaabaaabaaaab".repeat(100);
+        assert_eq!(suffix_array(&data), naive_suffix_array(&data));
+    }
+}
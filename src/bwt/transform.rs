@@ -0,0 +1,154 @@
+use crate::bwt::suffix_array::suffix_array_of_symbols;
+use crate::error::Error;
+
+/// Largest block size supported by the transforms.
+pub const MAX_BLOCK_SIZE: usize = 1 << 30;
+
+/// Burrows-Wheeler transform of `data`.
+///
+/// The rotations of `data` are sorted, and the last byte of each rotation is output. Returns the
+/// output, with the origin: the index of `data` itself among the sorted rotations. This is the
+/// transform used by bzip2.
+///
+/// The rotations are sorted as the suffixes of `data` repeated twice, which are decided within
+/// the first `data.len()` bytes unless the rotations are equal.
+pub fn forward_transform(data: &[u8]) -> (Vec<u8>, usize) {
+    assert!(data.len() <= MAX_BLOCK_SIZE);
+    let n = data.len();
+    let text: Vec<u32> = data.iter().chain(data).map(|&byte| byte as u32).collect();
+    let sa = suffix_array_of_symbols(&text, u8::MAX as u32);
+
+    let mut output: Vec<u8> = Vec::with_capacity(n);
+    let mut origin = 0;
+    for pos in sa
+        .into_iter()
+        .map(|pos| pos as usize)
+        .filter(|&pos| pos < n)
+    {
+        if pos == 0 {
+            origin = output.len();
+            output.push(data[n - 1]);
+        } else {
+            output.push(data[pos - 1]);
+        }
+    }
+    (output, origin)
+}
+
+/// Inverse of `forward_transform()`, given its output and origin.
+pub fn inverse_transform(data: &[u8], origin: usize) -> Result<Vec<u8>, Error> {
+    if data.len() > MAX_BLOCK_SIZE {
+        return Err(Error::CorruptHeader("Block too large"));
+    }
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    if origin >= data.len() {
+        return Err(Error::CorruptHeader("Invalid origin"));
+    }
+
+    // Start of each byte value in the sorted first bytes of the rotations.
+    let mut starts = [0u32; 256];
+    for &byte in data.iter() {
+        starts[byte as usize] += 1;
+    }
+    let mut sum = 0;
+    for start in starts.iter_mut() {
+        (*start, sum) = (sum, sum + *start);
+    }
+
+    // next[i] = index of the rotation that starts one byte after rotation i. The k-th occurrence
+    // of a byte in `data` is the last byte of the rotation following the k-th rotation that
+    // starts with that byte.
+    let mut next = vec![0u32; data.len()];
+    for (i, &byte) in data.iter().enumerate() {
+        next[starts[byte as usize] as usize] = i as u32;
+        starts[byte as usize] += 1;
+    }
+
+    let mut output: Vec<u8> = Vec::with_capacity(data.len());
+    let mut pos = next[origin] as usize;
+    for _ in 0..data.len() {
+        output.push(data[pos]);
+        pos = next[pos] as usize;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+
+    // Transform by sorting all the rotations.
+    fn naive_transform(data: &[u8]) -> (Vec<u8>, usize) {
+        let n = data.len();
+        let rotation = |i: usize| [&data[i..], &data[..i]].concat();
+        let mut rotations: Vec<usize> = (0..n).collect();
+        rotations.sort_by_key(|&i| rotation(i));
+        let output = rotations.iter().map(|&i| data[(i + n - 1) % n]).collect();
+        let origin = rotations
+            .iter()
+            .position(|&i| rotation(i) == data)
+            .unwrap_or(0);
+        (output, origin)
+    }
+
+    fn round_trip(data: &[u8]) {
+        let (output, origin) = forward_transform(data);
+        let (expected, expected_origin) = naive_transform(data);
+        assert_eq!(output, expected);
+
+        // With equal rotations, the origin may be the index of any of them.
+        assert_eq!(
+            inverse_transform(&output, origin).unwrap(),
+            inverse_transform(&output, expected_origin).unwrap()
+        );
+        assert_eq!(inverse_transform(&output, origin).unwrap(), data);
+    }
+
+    #[test]
+    fn test_transform() {
+        assert_eq!(forward_transform(b"banana"), (b"nnbaaa".to_vec(), 3));
+        assert_eq!(forward_transform(b""), (Vec::new(), 0));
+        assert_eq!(forward_transform(b"x"), (b"x".to_vec(), 0));
+        for data in [
+            &b"banana"[..],
+            b"abab",
+            b"aaaa",
+            b"mississippi",
+            b"ab",
+            b"ba",
+        ] {
+            round_trip(data);
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rng = rngs::SmallRng::seed_from_u64(0);
+        for len in [2, 10, 100, 1000] {
+            for alphabet in [1, 2, 4, 256] {
+                let data: Vec<u8> = (0..len).map(|_| rng.gen_range(0..alphabet) as u8).collect();
+                round_trip(&data);
+            }
+        }
+        round_trip(&b"abcabcabc".repeat(50));
+    }
+
+    #[test]
+    fn test_large_block() {
+        let mut data: Vec<u8> = Vec::new();
+        for i in 0..1_000_000u32 {
+            data.push(b"the quick brown fox "[(i.wrapping_mul(2654435761) >> 28) as usize]);
+        }
+        let (output, origin) = forward_transform(&data);
+        assert_eq!(inverse_transform(&output, origin).unwrap(), data);
+    }
+
+    #[test]
+    fn test_invalid_origin() {
+        assert!(inverse_transform(b"nnbaaa", 6).is_err());
+        assert_eq!(inverse_transform(b"", 0).unwrap(), b"");
+    }
+}
@@ -0,0 +1,138 @@
+use crate::bits::BitReader;
+use crate::bwt::inverse_transform;
+use crate::bzip2::format::{GROUP_SIZE, MAX_CODE_LENGTH, MAX_TABLES, MIN_TABLES};
+use crate::error::Error;
+use crate::huffman::{PrefixCode, PrefixDecoder};
+use crate::transform::{MoveToFront, MtfVariant, ZeroRunDecoder};
+
+/// Reads a block that follows the block magic, with at most `max_block_size` bytes before the
+/// initial run-length decoding. Returns the block, still run-length coded, with its CRC.
+pub fn decode_block(
+    bit_reader: &mut BitReader,
+    max_block_size: usize,
+) -> Result<(Vec<u8>, u32), Error> {
+    let crc = bit_reader.read_bits(32) as u32;
+    if bit_reader.read_bits(1) != 0 {
+        return Err(Error::Unsupported("Randomized blocks are not supported"));
+    }
+    let origin = bit_reader.read_bits(24) as usize;
+
+    // The bytes in use.
+    let mut bytes_in_use: Vec<u8> = Vec::with_capacity(256);
+    let ranges_in_use = bit_reader.read_bits(16);
+    for range in 0..16 {
+        if ranges_in_use & (0x8000 >> range) != 0 {
+            let bits = bit_reader.read_bits(16);
+            for i in 0..16 {
+                if bits & (0x8000 >> i) != 0 {
+                    bytes_in_use.push((range * 16 + i) as u8);
+                }
+            }
+        }
+    }
+    if bytes_in_use.is_empty() {
+        return Err(Error::InvalidCodeTable("No bytes in use"));
+    }
+    let num_in_use = bytes_in_use.len();
+    let end_of_block = num_in_use as u16 + 1;
+    let num_symbols = num_in_use + 2;
+
+    // The selectors, in unary after move-to-front coding.
+    let num_tables = bit_reader.read_bits(3) as usize;
+    if !(MIN_TABLES..=MAX_TABLES).contains(&num_tables) {
+        return Err(Error::CorruptHeader("Invalid number of tables"));
+    }
+    let num_selectors = bit_reader.read_bits(15) as usize;
+    if num_selectors == 0 {
+        return Err(Error::CorruptHeader("Invalid number of selectors"));
+    }
+    let mut mtf_selectors: Vec<u8> = Vec::with_capacity(num_selectors);
+    for _ in 0..num_selectors {
+        let mut index = 0;
+        while bit_reader.read_bits(1) == 1 {
+            index += 1;
+            if index >= num_tables {
+                return Err(Error::CorruptData("Invalid selector"));
+            }
+        }
+        mtf_selectors.push(index as u8);
+    }
+    let mut selectors: Vec<u8> = Vec::with_capacity(num_selectors);
+    MoveToFront::new(num_tables, MtfVariant::Standard).decode(&mtf_selectors, &mut selectors)?;
+
+    let decoders: Vec<PrefixDecoder> = (0..num_tables)
+        .map(|_| read_table(bit_reader, num_symbols))
+        .collect::<Result<_, _>>()?;
+
+    // Decode the symbols by groups, and undo the zero run-length and move-to-front coding.
+    let mut zero_run = ZeroRunDecoder::with_max_len(max_block_size as u64);
+    let mut mtf_indices: Vec<u8> = Vec::new();
+    let mut group: Vec<u16> = Vec::with_capacity(GROUP_SIZE);
+    let mut selector_iter = selectors.iter();
+    'groups: loop {
+        let Some(&selector) = selector_iter.next() else {
+            return Err(Error::CorruptData("Missing end of block"));
+        };
+        let decoder = &decoders[selector as usize];
+        group.clear();
+        for _ in 0..GROUP_SIZE {
+            let symbol = decoder.decode(bit_reader)?;
+            if symbol == end_of_block {
+                zero_run.decode(&group, &mut mtf_indices)?;
+                zero_run.finish(&mut mtf_indices);
+                break 'groups;
+            }
+            group.push(symbol);
+        }
+        zero_run.decode(&group, &mut mtf_indices)?;
+        if mtf_indices.len() > max_block_size {
+            return Err(Error::CorruptData("Block too large"));
+        }
+    }
+    if mtf_indices.len() > max_block_size {
+        return Err(Error::CorruptData("Block too large"));
+    }
+    if origin >= mtf_indices.len() {
+        return Err(Error::CorruptData("Invalid origin"));
+    }
+
+    let mut indices: Vec<u8> = Vec::with_capacity(mtf_indices.len());
+    MoveToFront::new(num_in_use, MtfVariant::Standard).decode(&mtf_indices, &mut indices)?;
+    let transformed: Vec<u8> = indices
+        .iter()
+        .map(|&index| bytes_in_use[index as usize])
+        .collect();
+    Ok((inverse_transform(&transformed, origin)?, crc))
+}
+
+// Reads the delta coded code lengths of a table, and returns its decoder.
+fn read_table(bit_reader: &mut BitReader, num_symbols: usize) -> Result<PrefixDecoder, Error> {
+    let mut code_lengths: Vec<u8> = Vec::with_capacity(num_symbols);
+    let mut length = bit_reader.read_bits(5) as u8;
+    for _ in 0..num_symbols {
+        loop {
+            if length == 0 || length > MAX_CODE_LENGTH {
+                return Err(Error::InvalidCodeTable("Invalid code length"));
+            }
+            if bit_reader.read_bits(1) == 0 {
+                break;
+            }
+            if bit_reader.read_bits(1) == 0 {
+                length += 1;
+            } else {
+                length -= 1;
+            }
+        }
+        code_lengths.push(length);
+    }
+
+    // The code must be complete, as the decoder has no invalid codes.
+    let kraft_sum: u32 = code_lengths
+        .iter()
+        .map(|&length| 1 << (MAX_CODE_LENGTH - length))
+        .sum();
+    if kraft_sum != 1 << MAX_CODE_LENGTH {
+        return Err(Error::InvalidCodeTable("Invalid code lengths"));
+    }
+    Ok(PrefixCode::from_code_lengths(&code_lengths).generate_decoder())
+}
@@ -0,0 +1,140 @@
+use crate::bits::BitWriter;
+use crate::bwt::forward_transform;
+use crate::bzip2::format::{
+    BLOCK_MAGIC, GROUP_SIZE, MAX_ENCODE_CODE_LENGTH, MAX_TABLES, MIN_TABLES,
+};
+use crate::huffman::{LengthLimitedHuffman, MultiTableHuffman, PrefixCode};
+use crate::transform::{MoveToFront, MtfVariant, ZeroRunEncoder};
+
+// Number of symbols below which each number of tables is used, as in bzip2.
+const NUM_TABLES_THRESHOLDS: [usize; MAX_TABLES - MIN_TABLES] = [200, 600, 1200, 2400];
+
+/// Writes a block of `data`, the output of the initial run-length coding, with the CRC of the
+/// original bytes of the block.
+///
+/// The block is transformed by the Burrows-Wheeler transform, move-to-front and zero run-length
+/// coding, and coded with multiple Huffman tables.
+pub fn encode_block(data: &[u8], crc: u32, bit_writer: &mut BitWriter) {
+    assert!(!data.is_empty());
+    bit_writer.write_bits(BLOCK_MAGIC, 48);
+    bit_writer.write_bits(crc as u64, 32);
+
+    // Not randomized.
+    bit_writer.write_bits(0, 1);
+
+    let (transformed, origin) = forward_transform(data);
+    bit_writer.write_bits(origin as u64, 24);
+
+    // The bytes in use, as a bitmap of the ranges of 16 bytes with bytes in use, followed by a
+    // bitmap of each of these ranges.
+    let mut in_use = [false; 256];
+    for &byte in data.iter() {
+        in_use[byte as usize] = true;
+    }
+    let mut ranges_in_use = 0;
+    for (range, bytes) in in_use.chunks(16).enumerate() {
+        if bytes.contains(&true) {
+            ranges_in_use |= 0x8000 >> range;
+        }
+    }
+    bit_writer.write_bits(ranges_in_use, 16);
+    for bytes in in_use.chunks(16).filter(|bytes| bytes.contains(&true)) {
+        let bits = bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, &used)| used)
+            .fold(0, |bits, (i, _)| bits | 0x8000 >> i);
+        bit_writer.write_bits(bits, 16);
+    }
+
+    // Move-to-front coding of the indices of the bytes among the bytes in use, followed by zero
+    // run-length coding and the end of block symbol.
+    let mut byte_index = [0u8; 256];
+    let mut num_in_use = 0;
+    for byte in 0..256 {
+        if in_use[byte] {
+            byte_index[byte] = num_in_use as u8;
+            num_in_use += 1;
+        }
+    }
+    let indices: Vec<u8> = transformed
+        .iter()
+        .map(|&byte| byte_index[byte as usize])
+        .collect();
+    let mut mtf_indices: Vec<u8> = Vec::with_capacity(indices.len());
+    MoveToFront::new(num_in_use, MtfVariant::Standard).encode(&indices, &mut mtf_indices);
+    let mut symbols: Vec<u16> = Vec::with_capacity(mtf_indices.len() + 1);
+    let mut zero_run = ZeroRunEncoder::new();
+    zero_run.encode(&mtf_indices, &mut symbols);
+    zero_run.finish(&mut symbols);
+    let end_of_block = ZeroRunEncoder::num_output_symbols(num_in_use);
+    symbols.push(end_of_block as u16);
+    let num_symbols = end_of_block + 1;
+
+    encode_symbols(&symbols, num_symbols, bit_writer);
+}
+
+// Writes the tables, the selectors and the coded `symbols`.
+fn encode_symbols(symbols: &[u16], num_symbols: usize, bit_writer: &mut BitWriter) {
+    let num_tables = MIN_TABLES
+        + NUM_TABLES_THRESHOLDS
+            .iter()
+            .filter(|&&threshold| symbols.len() >= threshold)
+            .count();
+    let selectors = MultiTableHuffman::new(num_symbols as u16, MAX_ENCODE_CODE_LENGTH, GROUP_SIZE)
+        .build(symbols, num_tables)
+        .selectors;
+
+    // Every symbol must have a code in every table, so rebuild the tables from the frequencies
+    // in their groups with one added to each.
+    let mut frequencies: Vec<Vec<u32>> = vec![vec![1; num_symbols]; num_tables];
+    for (group, &selector) in symbols.chunks(GROUP_SIZE).zip(selectors.iter()) {
+        for &symbol in group.iter() {
+            frequencies[selector as usize][symbol as usize] += 1;
+        }
+    }
+    let huffman = LengthLimitedHuffman::new(num_symbols as u16, MAX_ENCODE_CODE_LENGTH);
+    let code_lengths: Vec<Vec<u8>> = frequencies
+        .iter()
+        .map(|weights| huffman.build_from_weights(weights).code_lengths())
+        .collect();
+
+    // The selectors are move-to-front coded, and written in unary.
+    bit_writer.write_bits(num_tables as u64, 3);
+    bit_writer.write_bits(selectors.len() as u64, 15);
+    let mut mtf_selectors: Vec<u8> = Vec::with_capacity(selectors.len());
+    MoveToFront::new(num_tables, MtfVariant::Standard).encode(&selectors, &mut mtf_selectors);
+    for &index in mtf_selectors.iter() {
+        bit_writer.write_bits(((1 << index) - 1) << 1, index as u32 + 1);
+    }
+
+    // The code lengths of each table are delta coded: a 5-bit start length, then for each
+    // symbol, '10' to increment the length or '11' to decrement it, and '0' to move on.
+    for lengths in code_lengths.iter() {
+        let mut current = lengths[0];
+        bit_writer.write_bits(current as u64, 5);
+        for &length in lengths.iter() {
+            while current < length {
+                bit_writer.write_bits(0b10, 2);
+                current += 1;
+            }
+            while current > length {
+                bit_writer.write_bits(0b11, 2);
+                current -= 1;
+            }
+            bit_writer.write_bits(0, 1);
+        }
+    }
+
+    let encoder_tables: Vec<Vec<(u32, u8)>> = code_lengths
+        .iter()
+        .map(|lengths| PrefixCode::from_code_lengths(lengths).generate_encoder_table())
+        .collect();
+    for (group, &selector) in symbols.chunks(GROUP_SIZE).zip(selectors.iter()) {
+        let table = &encoder_tables[selector as usize];
+        for &symbol in group.iter() {
+            let (code, length) = table[symbol as usize];
+            bit_writer.write_bits(code as u64, length as u32);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// Magic bytes at the start of a stream, followed by the level as an ASCII digit.
+pub const STREAM_MAGIC: &[u8; 3] = b"BZh";
+
+/// 48-bit magic number at the start of a block: the digits of pi.
+pub const BLOCK_MAGIC: u64 = 0x314159265359;
+
+/// 48-bit magic number at the end of a stream: the digits of sqrt(pi).
+pub const END_OF_STREAM_MAGIC: u64 = 0x177245385090;
+
+/// Highest level. The largest block size is the level times `BLOCK_SIZE_UNIT`.
+pub const MAX_LEVEL: u32 = 9;
+
+/// Unit of the largest block size.
+pub const BLOCK_SIZE_UNIT: usize = 100_000;
+
+/// Number of symbols coded with the same table.
+pub const GROUP_SIZE: usize = 50;
+
+/// Limits of the number of tables.
+pub const MIN_TABLES: usize = 2;
+pub const MAX_TABLES: usize = 6;
+
+/// Largest code length in a valid stream.
+pub const MAX_CODE_LENGTH: u8 = 20;
+
+/// Largest code length written by the encoder, as in bzip2.
+pub const MAX_ENCODE_CODE_LENGTH: usize = 17;
+
+/// Adds the CRC of a block to the combined CRC of the stream.
+pub fn combine_crc(combined_crc: u32, block_crc: u32) -> u32 {
+    combined_crc.rotate_left(1) ^ block_crc
+}
@@ -0,0 +1,11 @@
+mod block_decoder;
+mod block_encoder;
+mod format;
+mod run_length;
+
+pub use block_decoder::decode_block;
+pub use block_encoder::encode_block;
+pub use format::{
+    combine_crc, BLOCK_MAGIC, BLOCK_SIZE_UNIT, END_OF_STREAM_MAGIC, MAX_LEVEL, STREAM_MAGIC,
+};
+pub use run_length::{decode_runs, RunLengthEncoder};
@@ -0,0 +1,107 @@
+// Length of a run that is followed by a count of further repeats.
+const RUN_THRESHOLD: usize = 4;
+
+// Longest run, with the largest count.
+const MAX_RUN_LENGTH: usize = RUN_THRESHOLD + u8::MAX as usize;
+
+/// The initial run-length coding of bzip2, applied before the Burrows-Wheeler transform.
+///
+/// A run of 4 to 255 equal bytes is coded as 4 bytes, followed by a byte with the number of
+/// further repeats. Longer runs are split.
+pub struct RunLengthEncoder {
+    byte: u8,
+    run: usize,
+}
+
+impl RunLengthEncoder {
+    pub fn new() -> Self {
+        Self { byte: 0, run: 0 }
+    }
+
+    /// Add `byte` to the input, appending the finished runs to `output`.
+    pub fn push(&mut self, byte: u8, output: &mut Vec<u8>) {
+        if self.run > 0 && (byte != self.byte || self.run == MAX_RUN_LENGTH) {
+            self.flush(output);
+        }
+        self.byte = byte;
+        self.run += 1;
+    }
+
+    /// Append the pending run to `output`. The next byte starts a new run.
+    pub fn flush(&mut self, output: &mut Vec<u8>) {
+        if self.run < RUN_THRESHOLD {
+            output.resize(output.len() + self.run, self.byte);
+        } else {
+            output.resize(output.len() + RUN_THRESHOLD, self.byte);
+            output.push((self.run - RUN_THRESHOLD) as u8);
+        }
+        self.run = 0;
+    }
+}
+
+/// Decodes the runs of a block of `RunLengthEncoder` output, appending the bytes to `output`.
+pub fn decode_runs(data: &[u8], output: &mut Vec<u8>) {
+    let mut run = 0;
+    let mut prev_byte = 0;
+    let mut iter = data.iter();
+    while let Some(&byte) = iter.next() {
+        if run > 0 && byte == prev_byte {
+            run += 1;
+        } else {
+            run = 1;
+        }
+        prev_byte = byte;
+        output.push(byte);
+
+        // The count may be missing at the end of a block.
+        if run == RUN_THRESHOLD {
+            if let Some(&count) = iter.next() {
+                output.resize(output.len() + count as usize, byte);
+            }
+            run = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let mut encoder = RunLengthEncoder::new();
+        let mut output = Vec::new();
+        for &byte in data.iter() {
+            encoder.push(byte, &mut output);
+        }
+        encoder.flush(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        assert_eq!(encode(b"abbcccdddd"), b"abbcccdddd\x00");
+        assert_eq!(encode(b"xaaaaaaay"), b"xaaaa\x03y");
+        assert_eq!(encode(&[7; 259]), [7, 7, 7, 7, 255]);
+        assert_eq!(encode(&[7; 260]), [7, 7, 7, 7, 255, 7]);
+
+        for data in [
+            &b""[..],
+            b"a",
+            b"aaaa",
+            b"aaaaabbbbbbbbbbbbc",
+            &[0; 1000],
+            &b"ab".repeat(100),
+        ] {
+            let mut decoded = Vec::new();
+            decode_runs(&encode(data), &mut decoded);
+            assert_eq!(decoded, data);
+        }
+    }
+
+    #[test]
+    fn test_decode_missing_count() {
+        let mut decoded = Vec::new();
+        decode_runs(b"xaaaa", &mut decoded);
+        assert_eq!(decoded, b"xaaaa");
+    }
+}
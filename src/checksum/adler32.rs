@@ -0,0 +1,65 @@
+use crate::checksum::Checksum;
+
+// Largest prime smaller than 65536.
+const MODULUS: u32 = 65521;
+
+// Largest number of bytes that can be summed before the sums may overflow.
+const MAX_BYTES_PER_MODULO: usize = 5552;
+
+/// Adler-32 checksum, as used by zlib (RFC 1950).
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+
+    /// Returns the Adler-32 of `data`.
+    #[cfg(test)]
+    pub fn checksum(data: &[u8]) -> u32 {
+        let mut adler = Self::new();
+        adler.update(data);
+        adler.value()
+    }
+}
+
+impl Checksum for Adler32 {
+    fn update(&mut self, data: &[u8]) {
+        for chunk in data.chunks(MAX_BYTES_PER_MODULO) {
+            for &byte in chunk.iter() {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= MODULUS;
+            self.b %= MODULUS;
+        }
+    }
+
+    fn value(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adler32() {
+        assert_eq!(Adler32::checksum(b""), 1);
+        assert_eq!(Adler32::checksum(b"Wikipedia"), 0x11e60398);
+
+        // Long input, to check the modulo.
+        let data = vec![0xff; 100000];
+        let mut a: u64 = 1;
+        let mut b: u64 = 0;
+        for &byte in data.iter() {
+            a = (a + byte as u64) % MODULUS as u64;
+            b = (b + a) % MODULUS as u64;
+        }
+        assert_eq!(Adler32::checksum(&data), ((b << 16) | a) as u32);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::checksum::Checksum;
+
+// Polynomial of CRC-32, not reversed, as the bits are processed msb first.
+const POLYNOMIAL: u32 = 0x04c11db7;
+
+// CRC of each byte value.
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80000000 != 0 {
+                (crc << 1) ^ POLYNOMIAL
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-32 checksum of bzip2, which processes the bits msb first, unlike `Crc32`.
+pub struct Bzip2Crc32 {
+    crc: u32,
+}
+
+impl Bzip2Crc32 {
+    pub fn new() -> Self {
+        Self { crc: 0xffffffff }
+    }
+
+    /// Returns the CRC-32 of `data`.
+    pub fn checksum(data: &[u8]) -> u32 {
+        let mut crc = Self::new();
+        crc.update(data);
+        crc.value()
+    }
+}
+
+impl Checksum for Bzip2Crc32 {
+    fn update(&mut self, data: &[u8]) {
+        let mut crc = self.crc;
+        for &byte in data.iter() {
+            crc = TABLE[((crc >> 24) ^ byte as u32) as usize] ^ (crc << 8);
+        }
+        self.crc = crc;
+    }
+
+    fn value(&self) -> u32 {
+        !self.crc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(Bzip2Crc32::checksum(b""), 0);
+        assert_eq!(Bzip2Crc32::checksum(b"123456789"), 0xfc891918);
+
+        let mut crc = Bzip2Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(crc.value(), 0xfc891918);
+    }
+}
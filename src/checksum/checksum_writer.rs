@@ -0,0 +1,43 @@
+use std::io;
+
+use crate::checksum::Checksum;
+
+/// A writer that computes the checksum and the length of the data written through it.
+pub struct ChecksumWriter<'a, C: Checksum> {
+    writer: &'a mut dyn io::Write,
+    checksum: C,
+    bytes_written: usize,
+}
+
+impl<'a, C: Checksum> ChecksumWriter<'a, C> {
+    pub fn new(writer: &'a mut dyn io::Write, checksum: C) -> Self {
+        Self {
+            writer,
+            checksum,
+            bytes_written: 0,
+        }
+    }
+
+    /// Returns the checksum of the data written so far.
+    pub fn value(&self) -> u32 {
+        self.checksum.value()
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+}
+
+impl<C: Checksum> io::Write for ChecksumWriter<'_, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.writer.write(buf)?;
+        self.checksum.update(&buf[..len]);
+        self.bytes_written += len;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
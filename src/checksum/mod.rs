@@ -0,0 +1,20 @@
+mod adler32;
+mod bzip2_crc32;
+mod checksum_writer;
+mod crc32;
+mod xxhash64;
+
+pub use adler32::Adler32;
+pub use bzip2_crc32::Bzip2Crc32;
+pub use checksum_writer::ChecksumWriter;
+pub use crc32::Crc32;
+pub use xxhash64::XxHash64;
+
+/// A checksum computed over a stream of bytes.
+pub trait Checksum {
+    /// Adds `data` to the checksum.
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the checksum of the data so far.
+    fn value(&self) -> u32;
+}
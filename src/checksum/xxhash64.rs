@@ -0,0 +1,181 @@
+// Primes of xxHash64.
+const PRIME_1: u64 = 0x9e3779b185ebca87;
+const PRIME_2: u64 = 0xc2b2ae3d27d4eb4f;
+const PRIME_3: u64 = 0x165667b19e3779f9;
+const PRIME_4: u64 = 0x85ebca77c2b2ae63;
+const PRIME_5: u64 = 0x27d4eb2f165667c5;
+
+// Size of a stripe, which updates the 4 accumulators.
+const STRIPE_SIZE: usize = 32;
+
+/// xxHash64, a fast non-cryptographic 64-bit hash, which is faster than CRC-32 on large data.
+///
+/// Unlike `Checksum`, the value has 64 bits.
+pub struct XxHash64 {
+    seed: u64,
+
+    // Accumulators of the stripes.
+    acc: [u64; 4],
+
+    // Bytes of the partial stripe.
+    buf: [u8; STRIPE_SIZE],
+    buf_len: usize,
+
+    // Total number of bytes.
+    total_len: u64,
+}
+
+impl XxHash64 {
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            acc: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            buf: [0; STRIPE_SIZE],
+            buf_len: 0,
+            total_len: 0,
+        }
+    }
+
+    /// Returns the hash of `data`, with seed 0.
+    #[cfg(test)]
+    pub fn hash(data: &[u8]) -> u64 {
+        let mut hash = Self::new();
+        hash.update(data);
+        hash.value()
+    }
+
+    /// Adds `data` to the hash.
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+
+        // Complete the partial stripe.
+        if self.buf_len > 0 {
+            let len = (STRIPE_SIZE - self.buf_len).min(data.len());
+            self.buf[self.buf_len..self.buf_len + len].copy_from_slice(&data[..len]);
+            self.buf_len += len;
+            data = &data[len..];
+            if self.buf_len < STRIPE_SIZE {
+                return;
+            }
+            let stripe = self.buf;
+            self.update_stripe(&stripe);
+            self.buf_len = 0;
+        }
+
+        let mut stripes = data.chunks_exact(STRIPE_SIZE);
+        for stripe in stripes.by_ref() {
+            self.update_stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buf[..rest.len()].copy_from_slice(rest);
+        self.buf_len = rest.len();
+    }
+
+    /// Returns the hash of the data so far.
+    pub fn value(&self) -> u64 {
+        let mut hash = if self.total_len >= STRIPE_SIZE as u64 {
+            let [acc1, acc2, acc3, acc4] = self.acc;
+            let mut hash = acc1
+                .rotate_left(1)
+                .wrapping_add(acc2.rotate_left(7))
+                .wrapping_add(acc3.rotate_left(12))
+                .wrapping_add(acc4.rotate_left(18));
+            for acc in self.acc {
+                hash = (hash ^ round(0, acc))
+                    .wrapping_mul(PRIME_1)
+                    .wrapping_add(PRIME_4);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        // The bytes of the partial stripe.
+        let mut rest = &self.buf[..self.buf_len];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^=
+                (u32::from_le_bytes(rest[..4].try_into().unwrap()) as u64).wrapping_mul(PRIME_1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest.iter() {
+            hash ^= (byte as u64).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        // Final mix.
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^ (hash >> 32)
+    }
+
+    fn update_stripe(&mut self, stripe: &[u8]) {
+        for (acc, lane) in self.acc.iter_mut().zip(stripe.chunks_exact(8)) {
+            *acc = round(*acc, read_u64(lane));
+        }
+    }
+}
+
+// Mixes a lane into an accumulator.
+fn round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xxhash64() {
+        assert_eq!(XxHash64::hash(b""), 0xef46db3751d8e999);
+        assert_eq!(XxHash64::hash(b"a"), 0xd24ec4f1a98c6e5b);
+        assert_eq!(XxHash64::hash(b"abc"), 0x44bc2cf5ad770999);
+        assert_eq!(
+            XxHash64::hash(b"The quick brown fox jumps over the lazy dog"),
+            0x0b242d361fda71bc
+        );
+        let data: Vec<u8> = (0..100).collect();
+        assert_eq!(XxHash64::hash(&data), 0x6ac1e58032166597);
+
+        let mut hash = XxHash64::with_seed(1);
+        hash.update(b"abc");
+        assert_eq!(hash.value(), 0xbea9ca8199328908);
+
+        // Updating in pieces gives the same result.
+        for split in [0, 1, 31, 32, 33, 64, 99] {
+            let mut hash = XxHash64::new();
+            hash.update(&data[..split]);
+            hash.update(&data[split..]);
+            assert_eq!(hash.value(), 0x6ac1e58032166597);
+        }
+    }
+}
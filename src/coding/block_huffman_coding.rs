@@ -0,0 +1,395 @@
+use std::io;
+use std::io::Read;
+use std::mem;
+
+use crate::bits::{BitReader, BitWriter, SliceBitReader};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+use crate::huffman::{LengthLimitedHuffman, MultiSymbolDecoder, PrefixCode, MAX_SYMBOLS_PER_ENTRY};
+
+const NUM_SYMBOLS: u16 = 256;
+
+/// Default number of input bytes in each block.
+pub const DEFAULT_BLOCK_SIZE: usize = 128 * 1024;
+
+/// Largest supported block size.
+pub const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+// Maximum code length.
+const MAX_CODE_LENGTH: usize = 24;
+
+// Number of bits to store the block length.
+const BLOCK_LENGTH_BITS: u32 = 32;
+
+// Number of bit streams in an interleaved block.
+const NUM_STREAMS: usize = 4;
+
+// Number of bits to store the size of each stream in an interleaved block.
+const STREAM_SIZE_BITS: u32 = 32;
+
+/// Semi-adaptive static Huffman encoder, which builds a new table for each block of input.
+///
+/// Unlike `StaticHuffmanEncoder`, the input is read only once and its length does not need to
+/// be known in advance, so this can be used for pipes and other streams.
+///
+/// Each block has a header with a last-block flag, an interleaved flag and the block length,
+/// followed by the coding table and the coded symbols.
+///
+/// In interleaved mode, the block is split into 4 segments, each coded into its own bit stream.
+/// A jump table of the stream sizes follows the coding table, and the streams are stored
+/// byte-aligned one after another. The decoder advances the 4 streams in lockstep, which
+/// breaks the serial dependency between symbols and improves decoding throughput.
+pub struct BlockHuffmanEncoder {
+    block_size: usize,
+    interleaved: bool,
+    huffman: LengthLimitedHuffman,
+}
+
+impl BlockHuffmanEncoder {
+    pub fn new() -> Self {
+        Self::with_block_size(DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Create an instance that buffers `block_size` bytes of input at a time.
+    pub fn with_block_size(block_size: usize) -> Self {
+        assert!(block_size > 0 && block_size <= MAX_BLOCK_SIZE);
+        Self {
+            block_size,
+            interleaved: false,
+            huffman: LengthLimitedHuffman::new(NUM_SYMBOLS, MAX_CODE_LENGTH),
+        }
+    }
+
+    /// Create an instance that codes each block of `block_size` bytes into 4 interleaved streams.
+    pub fn with_interleaved_streams(block_size: usize) -> Self {
+        Self {
+            interleaved: true,
+            ..Self::with_block_size(block_size)
+        }
+    }
+
+    fn encode_block(&self, block: &[u8], last: bool, bit_writer: &mut BitWriter) {
+        // Write the block header.
+        bit_writer.write_bits(last as u64, 1);
+        bit_writer.write_bits(self.interleaved as u64, 1);
+        bit_writer.write_bits(block.len() as u64, BLOCK_LENGTH_BITS);
+        if block.is_empty() {
+            return;
+        }
+
+        // Write the coding table.
+        let mut frequencies: Vec<u32> = vec![0; NUM_SYMBOLS as usize];
+        for &byte in block.iter() {
+            frequencies[byte as usize] += 1;
+        }
+        let prefix_code = self.huffman.build_from_weights(&frequencies);
+        prefix_code.encode_coding_table(bit_writer);
+
+        // Write the symbols.
+        let encoder_table = prefix_code.generate_encoder_table();
+        if !self.interleaved {
+            Self::encode_symbols(block, &encoder_table, bit_writer);
+            return;
+        }
+
+        // Code each segment into its own stream.
+        let streams: Vec<Vec<u8>> = block
+            .chunks(segment_len(block.len()))
+            .map(|segment| {
+                let mut stream = Vec::new();
+                let mut stream_writer = BitWriter::new(&mut stream);
+                Self::encode_symbols(segment, &encoder_table, &mut stream_writer);
+                stream_writer
+                    .finish()
+                    .expect("Writing to memory should not fail");
+                stream
+            })
+            .collect();
+
+        // Write the jump table, followed by the streams.
+        for i in 0..NUM_STREAMS {
+            let size = streams.get(i).map_or(0, |stream| stream.len());
+            bit_writer.write_bits(size as u64, STREAM_SIZE_BITS);
+        }
+        bit_writer.align_to_byte();
+        for stream in streams.iter() {
+            bit_writer.write_bytes(stream);
+        }
+    }
+
+    fn encode_symbols(symbols: &[u8], encoder_table: &[(u32, u8)], bit_writer: &mut BitWriter) {
+        for &symbol in symbols.iter() {
+            let code = encoder_table[symbol as usize];
+            bit_writer.write_bits(code.0 as u64, code.1 as u32);
+        }
+    }
+
+    fn encode_loop(
+        &self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        let mut block: Vec<u8> = Vec::with_capacity(self.block_size);
+        let mut next_block: Vec<u8> = Vec::with_capacity(self.block_size);
+        let mut bytes_read = reader
+            .take(self.block_size as u64)
+            .read_to_end(&mut block)?;
+        loop {
+            // Read ahead to find out if this is the last block.
+            next_block.clear();
+            let len = if block.len() < self.block_size {
+                0
+            } else {
+                reader
+                    .take(self.block_size as u64)
+                    .read_to_end(&mut next_block)?
+            };
+            bytes_read += len;
+
+            let last = len == 0;
+            self.encode_block(&block, last, &mut bit_writer);
+            if last {
+                break;
+            }
+            mem::swap(&mut block, &mut next_block);
+        }
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for BlockHuffmanEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+/// Decoder for `BlockHuffmanEncoder`.
+pub struct BlockHuffmanDecoder {}
+
+impl BlockHuffmanDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = BitReader::new(reader);
+        let mut block: Vec<u8> = Vec::new();
+        let mut bytes_written = 0;
+        loop {
+            // Read the block header.
+            let last = bit_reader.read_bits(1) == 1;
+            let interleaved = bit_reader.read_bits(1) == 1;
+            let block_len = bit_reader.read_bits(BLOCK_LENGTH_BITS) as usize;
+            bit_reader.check_status()?;
+            if block_len > MAX_BLOCK_SIZE {
+                return Err(Error::CorruptData("Invalid block length"));
+            }
+
+            if block_len > 0 {
+                let prefix_code = PrefixCode::decode_coding_table(&mut bit_reader)?;
+                let decoder = prefix_code.generate_multi_symbol_decoder();
+                block.clear();
+                if interleaved {
+                    Self::decode_interleaved(&decoder, block_len, &mut bit_reader, &mut block)?;
+                } else {
+                    // Decode several symbols at a time, then the last few one by one.
+                    while block.len() + MAX_SYMBOLS_PER_ENTRY <= block_len {
+                        let (symbols, count) = decoder.decode(&mut bit_reader)?;
+                        block.extend(symbols[..count].iter().map(|&symbol| symbol as u8));
+                    }
+                    while block.len() < block_len {
+                        block.push(decoder.decode_one(&mut bit_reader)? as u8);
+                    }
+                }
+                bit_reader.check_status()?;
+                writer.write_all(&block)?;
+                bytes_written += block_len;
+            }
+            if last {
+                break;
+            }
+        }
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+
+    // Decode the symbols of an interleaved block.
+    fn decode_interleaved(
+        decoder: &MultiSymbolDecoder,
+        block_len: usize,
+        bit_reader: &mut BitReader,
+        block: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        // Read the jump table and the streams.
+        let max_stream_size = segment_len(block_len) * MAX_CODE_LENGTH / 8 + 1;
+        let mut stream_sizes = [0; NUM_STREAMS];
+        for size in stream_sizes.iter_mut() {
+            *size = bit_reader.read_bits(STREAM_SIZE_BITS) as usize;
+            if *size > max_stream_size {
+                return Err(Error::CorruptData("Invalid stream size"));
+            }
+        }
+        let mut streams: Vec<u8> = vec![0; stream_sizes.iter().sum()];
+        bit_reader.align_to_byte();
+        if bit_reader.read_bytes(&mut streams) != streams.len() {
+            return Err(Error::Truncated);
+        }
+        let mut rest = &streams[..];
+        let mut bit_readers: [SliceBitReader; NUM_STREAMS] = std::array::from_fn(|i| {
+            let (stream, next) = rest.split_at(stream_sizes[i]);
+            rest = next;
+            SliceBitReader::new(stream)
+        });
+
+        // Segment `i` is decoded from `positions[i]` to `ends[i]`. All segments but the last have
+        // `segment_len` symbols.
+        let segment_len = segment_len(block_len);
+        let mut positions: [usize; NUM_STREAMS] =
+            std::array::from_fn(|i| (segment_len * i).min(block_len));
+        let ends: [usize; NUM_STREAMS] =
+            std::array::from_fn(|i| (segment_len * (i + 1)).min(block_len));
+        block.resize(block_len, 0);
+
+        // Advance all streams in lockstep, several symbols at a time. Each round decodes at most
+        // `MAX_SYMBOLS_PER_ENTRY` symbols per stream, so the number of rounds that fit in the
+        // shortest remaining segment needs no bounds checks.
+        loop {
+            let rounds = positions
+                .iter()
+                .zip(ends.iter())
+                .map(|(&position, &end)| (end - position) / MAX_SYMBOLS_PER_ENTRY)
+                .min()
+                .unwrap();
+            if rounds == 0 {
+                break;
+            }
+            for _ in 0..rounds {
+                for (bit_reader, position) in bit_readers.iter_mut().zip(positions.iter_mut()) {
+                    let (symbols, count) = decoder.decode(bit_reader)?;
+                    let output = &mut block[*position..*position + MAX_SYMBOLS_PER_ENTRY];
+                    for (byte, &symbol) in output.iter_mut().zip(symbols.iter()) {
+                        *byte = symbol as u8;
+                    }
+                    *position += count;
+                }
+            }
+        }
+
+        // Finish the remaining symbols one by one.
+        for ((bit_reader, position), &end) in bit_readers
+            .iter_mut()
+            .zip(positions.iter_mut())
+            .zip(ends.iter())
+        {
+            for byte in block[*position..end].iter_mut() {
+                *byte = decoder.decode_one(bit_reader)? as u8;
+            }
+            *position = end;
+            if bit_reader.is_past_end() {
+                return Err(Error::CorruptData("Stream too short"));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Number of symbols in each segment of an interleaved block, except possibly the last one.
+fn segment_len(block_len: usize) -> usize {
+    block_len.div_ceil(NUM_STREAMS)
+}
+
+impl Decoder for BlockHuffmanDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A reader that returns data in small pieces, like a pipe.
+    struct PipeReader {
+        data: Vec<u8>,
+        pos: usize,
+    }
+
+    impl io::Read for PipeReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(7).min(self.data.len() - self.pos);
+            buf[..len].copy_from_slice(&self.data[self.pos..self.pos + len]);
+            self.pos += len;
+            Ok(len)
+        }
+    }
+
+    fn encode_decode(input: Vec<u8>, block_size: usize) {
+        encode_decode_with(
+            BlockHuffmanEncoder::with_block_size(block_size),
+            input.clone(),
+        );
+        encode_decode_with(
+            BlockHuffmanEncoder::with_interleaved_streams(block_size),
+            input,
+        );
+    }
+
+    fn encode_decode_with(encoder: BlockHuffmanEncoder, input: Vec<u8>) {
+        let mut reader = PipeReader {
+            data: input.clone(),
+            pos: 0,
+        };
+        let mut encoded = Vec::new();
+        encoder.encode_loop(&mut reader, &mut encoded).unwrap();
+
+        let mut decoded = Vec::new();
+        BlockHuffmanDecoder::decode_loop(&mut io::Cursor::new(encoded), &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn test_empty() {
+        encode_decode(Vec::new(), 100);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let input: Vec<u8> = (0..1000u32).map(|i| (i * i % 7 + i / 100) as u8).collect();
+        encode_decode(input.clone(), 1);
+        encode_decode(input.clone(), 100);
+        encode_decode(input.clone(), 999);
+        encode_decode(input.clone(), 1000);
+        encode_decode(input, 1001);
+    }
+
+    #[test]
+    fn test_interleaved_segments() {
+        // Block lengths that leave the last segment shorter than the others, or empty.
+        for len in [1, 2, 3, 4, 5, 6, 7, 9, 13, 4096] {
+            let input: Vec<u8> = (0..len as u32).map(|i| (i * 31 % 11) as u8).collect();
+            encode_decode_with(BlockHuffmanEncoder::with_interleaved_streams(len), input);
+        }
+    }
+}
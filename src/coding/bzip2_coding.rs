@@ -0,0 +1,254 @@
+use std::io;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::bzip2::{
+    combine_crc, decode_block, decode_runs, encode_block, RunLengthEncoder, BLOCK_MAGIC,
+    BLOCK_SIZE_UNIT, END_OF_STREAM_MAGIC, MAX_LEVEL, STREAM_MAGIC,
+};
+use crate::checksum::{Bzip2Crc32, Checksum};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+
+// Input buffer size.
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+// Margin below the largest block size, for the end of the last run, as in bzip2.
+const BLOCK_SIZE_MARGIN: usize = 19;
+
+/// Encoder of the bzip2 file format, which can be read by `bunzip2`.
+pub struct Bzip2Encoder {
+    level: u32,
+}
+
+impl Bzip2Encoder {
+    pub fn new() -> Self {
+        Self::with_level(MAX_LEVEL)
+    }
+
+    /// Create an instance with `level` from 1 to 9, which sets the block size to `level` times
+    /// 100k bytes.
+    pub fn with_level(level: u32) -> Self {
+        assert!((1..=MAX_LEVEL).contains(&level));
+        Self { level }
+    }
+
+    fn encode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        bit_writer.write_bytes(STREAM_MAGIC);
+        bit_writer.write_bytes(&[b'0' + self.level as u8]);
+
+        let max_block_len = self.level as usize * BLOCK_SIZE_UNIT - BLOCK_SIZE_MARGIN;
+        let mut block: Vec<u8> = Vec::with_capacity(max_block_len);
+        let mut run_length = RunLengthEncoder::new();
+        let mut crc = Bzip2Crc32::new();
+        let mut combined_crc = 0;
+        let mut input_buf: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut bytes_read = 0;
+        loop {
+            let len = reader.read(input_buf.as_mut_slice())?;
+            if len == 0 {
+                break;
+            }
+            bytes_read += len;
+
+            // Start of the bytes that are not in the CRC yet.
+            let mut start = 0;
+            for (i, &byte) in input_buf[..len].iter().enumerate() {
+                run_length.push(byte, &mut block);
+                if block.len() >= max_block_len {
+                    run_length.flush(&mut block);
+                    crc.update(&input_buf[start..=i]);
+                    start = i + 1;
+                    encode_block(&block, crc.value(), &mut bit_writer);
+                    combined_crc = combine_crc(combined_crc, crc.value());
+                    block.clear();
+                    crc = Bzip2Crc32::new();
+                }
+            }
+            crc.update(&input_buf[start..len]);
+        }
+        run_length.flush(&mut block);
+        if !block.is_empty() {
+            encode_block(&block, crc.value(), &mut bit_writer);
+            combined_crc = combine_crc(combined_crc, crc.value());
+        }
+
+        bit_writer.write_bits(END_OF_STREAM_MAGIC, 48);
+        bit_writer.write_bits(combined_crc as u64, 32);
+        bit_writer.align_to_byte();
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for Bzip2Encoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+/// Decoder of the bzip2 file format, including concatenated streams.
+pub struct Bzip2Decoder {}
+
+impl Bzip2Decoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn decode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = BitReader::new(reader);
+        let mut output: Vec<u8> = Vec::new();
+        let mut bytes_written = 0;
+        let mut first = true;
+        loop {
+            let mut header = [0; 4];
+            let len = bit_reader.read_bytes(&mut header);
+            if len == 0 && !first {
+                break;
+            }
+            if len < header.len()
+                || header[..3] != *STREAM_MAGIC
+                || !(b'1'..=b'0' + MAX_LEVEL as u8).contains(&header[3])
+            {
+                return Err(Error::CorruptHeader("Invalid bzip2 header"));
+            }
+            let max_block_size = (header[3] - b'0') as usize * BLOCK_SIZE_UNIT;
+
+            let mut combined_crc = 0;
+            loop {
+                let magic = bit_reader.read_bits(48);
+                if magic == END_OF_STREAM_MAGIC {
+                    break;
+                }
+                if magic != BLOCK_MAGIC {
+                    return Err(Error::CorruptHeader("Invalid block header"));
+                }
+                let (block, expected_crc) = decode_block(&mut bit_reader, max_block_size)?;
+                output.clear();
+                decode_runs(&block, &mut output);
+                let crc = Bzip2Crc32::checksum(&output);
+                if crc != expected_crc {
+                    return Err(Error::ChecksumMismatch);
+                }
+                combined_crc = combine_crc(combined_crc, crc);
+                writer.write_all(&output)?;
+                bytes_written += output.len();
+            }
+            if bit_reader.read_bits(32) as u32 != combined_crc {
+                return Err(Error::ChecksumMismatch);
+            }
+            bit_reader.align_to_byte();
+            first = false;
+        }
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for Bzip2Decoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn encode(input: &[u8], level: u32) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        Bzip2Encoder::with_level(level)
+            .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+            .unwrap();
+        encoded
+    }
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        Bzip2Decoder::new().decode_loop(&mut io::Cursor::new(encoded), &mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Written by bzip2.
+        let encoded = include_bytes!("../../testdata/bzip2/text.txt.bz2");
+        assert_eq!(decode(encoded).unwrap(), TEXT);
+        assert_eq!(
+            decode(include_bytes!("../../testdata/bzip2/empty.bz2")).unwrap(),
+            b""
+        );
+
+        // Two streams.
+        assert_eq!(
+            decode(include_bytes!("../../testdata/bzip2/multi.bz2")).unwrap(),
+            b"Hello, world!\nHello again!\n"
+        );
+
+        // Several blocks of level 1.
+        assert_eq!(
+            decode(include_bytes!("../../testdata/bzip2/blocks.bz2")).unwrap(),
+            TEXT.repeat(10)
+        );
+
+        // Corrupt data.
+        let mut corrupt = encoded.to_vec();
+        corrupt[100] ^= 1;
+        assert!(decode(&corrupt).is_err());
+        assert!(decode(&encoded[..encoded.len() - 4]).is_err());
+        assert!(decode(b"").is_err());
+        assert!(decode(b"BZh0").is_err());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for input in [&b""[..], b"a", b"aaaaaaaaaa", &[0; 1000], TEXT] {
+            let encoded = encode(input, 9);
+            assert_eq!(&encoded[..4], b"BZh9");
+            assert_eq!(decode(&encoded).unwrap(), input);
+        }
+
+        // Matches the size of the test vector from bzip2.
+        let encoded = encode(TEXT, 9);
+        let expected = include_bytes!("../../testdata/bzip2/text.txt.bz2");
+        assert!(encoded.len() <= expected.len() * 11 / 10);
+    }
+
+    #[test]
+    fn test_blocks() {
+        // Long runs, and data that crosses the block boundaries of level 1.
+        let mut input = vec![b'x'; 1000];
+        input.extend(TEXT.repeat(10));
+        input.extend([0; 100_000]);
+        let encoded = encode(&input, 1);
+        assert_eq!(&encoded[..4], b"BZh1");
+        assert_eq!(decode(&encoded).unwrap(), input);
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+use std::io::Read;
+
+use crate::bzip2::STREAM_MAGIC;
+use crate::coding::bzip2_coding::Bzip2Decoder;
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::gzip_coding::{GzipDecoder, GZIP_MAGIC};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::coding::zlib_coding::{is_zlib_header, ZlibDecoder};
+use crate::error::Error;
+
+/// Decoder for gzip, zlib or bzip2 data, detected from the first bytes.
+pub struct ContainerDecoder {
+    gzip: GzipDecoder,
+    zlib: ZlibDecoder,
+    bzip2: Bzip2Decoder,
+}
+
+impl ContainerDecoder {
+    pub fn new() -> Self {
+        Self {
+            gzip: GzipDecoder::new(),
+            zlib: ZlibDecoder::new(),
+            bzip2: Bzip2Decoder::new(),
+        }
+    }
+
+    fn decode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut magic = [0; 3];
+        let mut len = 0;
+        while len < magic.len() {
+            match reader.read(&mut magic[len..])? {
+                0 => break,
+                size => len += size,
+            }
+        }
+
+        // Put the bytes back in front of the rest of the stream.
+        let mut chained = magic[..len].chain(reader);
+        let header = [magic[0], magic[1]];
+        if len >= 2 && header == GZIP_MAGIC {
+            self.gzip.decode_loop(&mut chained, writer)
+        } else if len >= 2 && is_zlib_header(header) {
+            self.zlib.decode_loop(&mut chained, writer)
+        } else if len == magic.len() && magic == *STREAM_MAGIC {
+            self.bzip2.decode_loop(&mut chained, writer)
+        } else if len < magic.len() {
+            // Too short for any of the containers.
+            Err(Error::Truncated)
+        } else {
+            Err(Error::CorruptHeader("Unknown container format"))
+        }
+    }
+}
+
+impl Decoder for ContainerDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        ContainerDecoder::new().decode_loop(&mut io::Cursor::new(encoded), &mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_detect() {
+        let text = include_bytes!("../../testdata/deflate/text.txt");
+        let gzip = decode(include_bytes!("../../testdata/gzip/text.txt.gz")).unwrap();
+        assert_eq!(&gzip, text);
+        let zlib = decode(include_bytes!("../../testdata/gzip/text.zlib")).unwrap();
+        assert_eq!(&zlib, text);
+        let bzip2 = decode(include_bytes!("../../testdata/bzip2/text.txt.bz2")).unwrap();
+        assert_eq!(&bzip2, text);
+
+        assert!(decode(b"").is_err());
+        assert!(decode(b"\x1f").is_err());
+        assert!(decode(include_bytes!(
+            "../../testdata/deflate/text.dynamic.deflate"
+        ))
+        .is_err());
+    }
+}
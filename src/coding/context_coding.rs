@@ -0,0 +1,125 @@
+use std::io;
+
+use crate::arithmetic::{ContextModel, RangeDecoder, RangeEncoder, MAX_ORDER};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+
+// Symbol 256 = end of stream.
+const NUM_SYMBOLS: u16 = 257;
+const END_OF_STREAM: u16 = 256;
+
+// Input buffer size.
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Encoder using a range coder with an adaptive order-1 or order-2 context model.
+pub struct ContextEncoder {
+    order: usize,
+}
+
+impl ContextEncoder {
+    /// Create a new instance using contexts of up to `order` previous bytes.
+    pub fn new(order: usize) -> Self {
+        assert!(order <= MAX_ORDER);
+        Self { order }
+    }
+
+    fn encode_loop(
+        order: usize,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut buffer: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut range_encoder = RangeEncoder::new(writer);
+        let mut model = ContextModel::new(NUM_SYMBOLS, order);
+        let mut bytes_read = 0;
+        loop {
+            let len = reader.read(buffer.as_mut_slice())?;
+            if len == 0 {
+                break;
+            }
+            bytes_read += len;
+            for &symbol in buffer[0..len].iter() {
+                model.encode(symbol as u16, &mut range_encoder);
+            }
+        }
+
+        // Write the end of file marker.
+        model.encode(END_OF_STREAM, &mut range_encoder);
+        let bytes_written = range_encoder.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for ContextEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::encode_loop(self.order, &mut reader, &mut writer)
+    }
+}
+
+/// Decoder for `ContextEncoder`.
+pub struct ContextDecoder {
+    order: usize,
+}
+
+impl ContextDecoder {
+    /// Create a new instance using contexts of up to `order` previous bytes.
+    pub fn new(order: usize) -> Self {
+        assert!(order <= MAX_ORDER);
+        Self { order }
+    }
+
+    fn decode_loop(
+        order: usize,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut buffer: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut range_decoder = RangeDecoder::new(reader);
+        let mut model = ContextModel::new(NUM_SYMBOLS, order);
+        let mut buffer_pos = 0;
+        let mut bytes_written = 0;
+        loop {
+            let symbol = model.decode(&mut range_decoder);
+            if range_decoder.is_past_end() {
+                return Err(Error::Truncated);
+            }
+            if symbol == END_OF_STREAM {
+                break;
+            }
+            buffer[buffer_pos] = symbol as u8;
+            buffer_pos += 1;
+            if buffer_pos == READ_BUFFER_SIZE {
+                writer.write_all(buffer.as_ref())?;
+                buffer_pos = 0;
+                bytes_written += READ_BUFFER_SIZE;
+            }
+        }
+        let bytes_read = range_decoder.finish()?;
+        writer.write_all(&buffer[0..buffer_pos])?;
+        bytes_written += buffer_pos;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for ContextDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::decode_loop(self.order, &mut reader, &mut writer)
+    }
+}
@@ -1,8 +1,8 @@
-use std::error::Error;
 use std::fmt;
 
 use crate::coding::input::InputSource;
 use crate::coding::output::OutputSink;
+use crate::error::Error;
 
 pub struct DecodeResult {
     bytes_read: usize,
@@ -16,6 +16,14 @@ impl DecodeResult {
             bytes_written,
         }
     }
+
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
 }
 
 impl fmt::Display for DecodeResult {
@@ -33,5 +41,11 @@ pub trait Decoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<DecodeResult, Box<dyn Error>>;
+    ) -> Result<DecodeResult, Error>;
+
+    /// Sets the largest number of bytes to decode, if the container knows it, e.g. from the
+    /// original size in a frame header. Decoders that read the output length from the data fail
+    /// on a larger length, so that invalid data can't make them write without bound. The limit
+    /// is ignored by default.
+    fn set_max_output_len(&mut self, _max_output_len: u64) {}
 }
@@ -0,0 +1,265 @@
+use std::io;
+use std::io::Read;
+use std::mem;
+
+use crate::bits::{LsbBitReader, LsbBitWriter};
+use crate::checksum::Checksum;
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::deflate::{Deflater, Inflater};
+use crate::error::Error;
+
+/// Number of input bytes in each block.
+pub const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Raw DEFLATE (RFC 1951) encoder.
+pub struct DeflateEncoder {
+    deflater: Deflater,
+}
+
+impl DeflateEncoder {
+    pub fn new() -> Self {
+        Self {
+            deflater: Deflater::new(),
+        }
+    }
+
+    fn encode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = LsbBitWriter::new(writer);
+        let bytes_read = deflate_stream(&mut self.deflater, reader, &mut bit_writer, None)?;
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+/// Compresses all of `reader` into DEFLATE blocks, also adding the data to `checksum` if given.
+/// Returns the number of bytes read.
+pub(crate) fn deflate_stream(
+    deflater: &mut Deflater,
+    reader: &mut dyn io::Read,
+    bit_writer: &mut LsbBitWriter,
+    mut checksum: Option<&mut dyn Checksum>,
+) -> io::Result<usize> {
+    deflater.reset();
+    let mut block: Vec<u8> = Vec::with_capacity(BLOCK_SIZE);
+    let mut next_block: Vec<u8> = Vec::with_capacity(BLOCK_SIZE);
+    let mut bytes_read = reader.take(BLOCK_SIZE as u64).read_to_end(&mut block)?;
+    loop {
+        // Read ahead to find out if this is the last block.
+        next_block.clear();
+        let len = if block.len() < BLOCK_SIZE {
+            0
+        } else {
+            reader
+                .take(BLOCK_SIZE as u64)
+                .read_to_end(&mut next_block)?
+        };
+        bytes_read += len;
+
+        if let Some(checksum) = checksum.as_mut() {
+            checksum.update(&block);
+        }
+        let last = len == 0;
+        deflater.write_block(&block, last, bit_writer);
+        if last {
+            break;
+        }
+        mem::swap(&mut block, &mut next_block);
+    }
+    Ok(bytes_read)
+}
+
+impl Encoder for DeflateEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+/// Raw DEFLATE (RFC 1951) decoder.
+pub struct DeflateDecoder {
+    inflater: Inflater,
+}
+
+impl DeflateDecoder {
+    pub fn new() -> Self {
+        Self {
+            inflater: Inflater::new(),
+        }
+    }
+
+    fn decode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = LsbBitReader::new(reader);
+        let bytes_written = self.inflater.inflate(&mut bit_reader, writer)?;
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for DeflateDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coding::test_util::check_io_errors;
+    use crate::deflate::{BlockType, DEFAULT_SEARCH_DEPTH};
+
+    // Test vectors produced by zlib, with the data they decode to.
+    const TEST_VECTORS: [(&[u8], &[u8]); 7] = [
+        (
+            include_bytes!("../../testdata/deflate/hello.stored.deflate"),
+            include_bytes!("../../testdata/deflate/hello.txt"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/hello.fixed.deflate"),
+            include_bytes!("../../testdata/deflate/hello.txt"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/text.stored.deflate"),
+            include_bytes!("../../testdata/deflate/text.txt"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/text.fixed.deflate"),
+            include_bytes!("../../testdata/deflate/text.txt"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/text.dynamic.deflate"),
+            include_bytes!("../../testdata/deflate/text.txt"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/binary.dynamic.deflate"),
+            include_bytes!("../../testdata/deflate/binary.bin"),
+        ),
+        (
+            include_bytes!("../../testdata/deflate/empty.fixed.deflate"),
+            b"",
+        ),
+    ];
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        let result =
+            DeflateDecoder::new().decode_loop(&mut io::Cursor::new(encoded), &mut decoded)?;
+        let expected = format!(
+            "{} bytes read, {} bytes written",
+            encoded.len(),
+            decoded.len()
+        );
+        assert_eq!(result.to_string(), expected);
+        Ok(decoded)
+    }
+
+    // An encoder that writes blocks of `block_type` only.
+    fn block_type_encoder(block_type: BlockType) -> DeflateEncoder {
+        DeflateEncoder {
+            deflater: Deflater::with_options(DEFAULT_SEARCH_DEPTH, Some(block_type)),
+        }
+    }
+
+    fn encode(input: &[u8], mut encoder: DeflateEncoder) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        encoder
+            .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+            .unwrap();
+        encoded
+    }
+
+    #[test]
+    fn test_vectors() {
+        for (encoded, expected) in TEST_VECTORS.iter() {
+            assert_eq!(&decode(encoded).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_encode_block_types() {
+        for (_, input) in TEST_VECTORS.iter() {
+            for block_type in [BlockType::Stored, BlockType::Fixed, BlockType::Dynamic] {
+                let encoded = encode(input, block_type_encoder(block_type));
+                assert_eq!(&decode(&encoded).unwrap(), input);
+            }
+            let encoded = encode(input, DeflateEncoder::new());
+            assert_eq!(&decode(&encoded).unwrap(), input);
+        }
+
+        // An encoder can be reused for another stream.
+        let mut encoder = DeflateEncoder::new();
+        for (_, input) in TEST_VECTORS.iter() {
+            let mut encoded = Vec::new();
+            encoder
+                .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+                .unwrap();
+            assert_eq!(&decode(&encoded).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn test_large_input() {
+        // Spans several blocks, with matches across blocks and a long stored block.
+        let mut input: Vec<u8> = Vec::new();
+        for i in 0..300000u32 {
+            input.push((i.wrapping_mul(2654435761) >> 24) as u8);
+        }
+        input.extend(b"abcdefghijklmnopqrstuvwxyz".repeat(10000));
+        for block_type in [BlockType::Stored, BlockType::Dynamic] {
+            let encoded = encode(&input, block_type_encoder(block_type));
+            assert_eq!(decode(&encoded).unwrap(), input);
+        }
+        let encoded = encode(&input, DeflateEncoder::new());
+        assert!(encoded.len() < 320000);
+        assert_eq!(decode(&encoded).unwrap(), input);
+    }
+
+    #[test]
+    fn test_invalid_streams() {
+        // Reserved block type.
+        assert!(decode(&[0x07]).is_err());
+
+        // Stored block with a bad length complement.
+        assert!(decode(&[0x01, 0x05, 0x00, 0x00, 0x00]).is_err());
+
+        // Distance too far back: fixed block with a match at the start.
+        assert!(decode(&[0x03, 0x02, 0x00]).is_err());
+
+        // Truncated streams.
+        let encoded = &TEST_VECTORS[4].0;
+        for len in [0, 1, encoded.len() / 2, encoded.len() - 1] {
+            assert!(decode(&encoded[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_io_errors() {
+        check_io_errors(
+            || Box::new(DeflateEncoder::new()),
+            || Box::new(DeflateDecoder::new()),
+        );
+    }
+}
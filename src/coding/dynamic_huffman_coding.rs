@@ -1,4 +1,3 @@
-use std::error::Error;
 use std::io;
 
 use crate::bits::{BitReader, BitWriter};
@@ -6,51 +5,82 @@ use crate::coding::decoder::{DecodeResult, Decoder};
 use crate::coding::encoder::{EncodeResult, Encoder};
 use crate::coding::input::InputSource;
 use crate::coding::output::OutputSink;
-use crate::huffman::DynamicHuffman;
+use crate::error::Error;
+use crate::huffman::{ContextHuffman, MAX_CONTEXT_ORDER};
+use crate::transform::{TransformChain, TransformStage};
 
-// Symbol 256 = end of stream.
-const NUM_SYMBOLS: u16 = 257;
+// Number of input symbols. The symbol after the transformed symbols marks the end of stream.
+const NUM_SYMBOLS: u16 = 256;
 const VALIDATE_TREE: bool = false;
 
 // Input buffer size.
 const READ_BUFFER_SIZE: usize = 8 * 1024;
 
 pub struct DynamicHuffmanEncoder {
-    huffman: DynamicHuffman,
+    model: ContextHuffman,
+    transforms: TransformChain,
 }
 
 impl DynamicHuffmanEncoder {
     pub fn new() -> Self {
+        Self::with_transforms(&[])
+    }
+
+    /// Create an instance that applies the transform `stages` to the bytes before coding.
+    pub fn with_transforms(stages: &[TransformStage]) -> Self {
+        Self::with_options(stages, 0)
+    }
+
+    /// Create an instance that codes each byte with a tree for its context of up to `order`
+    /// previous bytes, escaping to lower orders for bytes new to the context.
+    pub fn with_context_order(order: usize) -> Self {
+        Self::with_options(&[], order)
+    }
+
+    fn with_options(stages: &[TransformStage], order: usize) -> Self {
+        assert!(order <= MAX_CONTEXT_ORDER);
+        let transforms = TransformChain::new(stages, NUM_SYMBOLS as usize);
         Self {
-            huffman: DynamicHuffman::new(NUM_SYMBOLS),
+            model: ContextHuffman::new(transforms.num_output_symbols() as u16 + 1, order),
+            transforms,
         }
     }
 
     fn encode_loop(
-        huffman: &mut DynamicHuffman,
+        &mut self,
         reader: &mut dyn io::Read,
         writer: &mut dyn io::Write,
-    ) -> Result<EncodeResult, Box<dyn Error>> {
+    ) -> Result<EncodeResult, Error> {
         let mut buffer: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut symbols: Vec<u16> = Vec::with_capacity(READ_BUFFER_SIZE);
         let mut bit_writer = BitWriter::new(writer);
         let mut bytes_read = 0;
+        self.transforms.reset();
         loop {
             let len = reader.read(buffer.as_mut_slice())?;
+            symbols.clear();
             if len == 0 {
-                break;
+                self.transforms.finish_encode(&mut symbols);
+            } else {
+                let bytes: Vec<u16> = buffer[0..len].iter().map(|&byte| byte as u16).collect();
+                self.transforms.encode(&bytes, &mut symbols);
             }
             bytes_read += len;
-            for &symbol in buffer[0..len].iter() {
-                huffman.encode(symbol as u16, &mut bit_writer);
+            for &symbol in symbols.iter() {
+                self.model.encode(symbol, &mut bit_writer);
                 if VALIDATE_TREE {
-                    huffman.validate();
+                    self.model.validate();
                 }
             }
+            if len == 0 {
+                break;
+            }
         }
 
         // Write the end of file marker.
-        huffman.encode(256, &mut bit_writer);
-        let bytes_written = bit_writer.finish();
+        let end_symbol = self.transforms.num_output_symbols() as u16;
+        self.model.encode(end_symbol, &mut bit_writer);
+        let bytes_written = bit_writer.finish()?;
 
         Ok(EncodeResult::new(bytes_read, bytes_written))
     }
@@ -61,49 +91,82 @@ impl Encoder for DynamicHuffmanEncoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<EncodeResult, Box<dyn Error>> {
-        let mut reader = input.reader();
-        let mut writer = output.writer();
-        Self::encode_loop(&mut self.huffman, &mut reader, &mut writer)
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
     }
 }
 
 pub struct DynamicHuffmanDecoder {
-    huffman: DynamicHuffman,
+    model: ContextHuffman,
+    transforms: TransformChain,
 }
 
 impl DynamicHuffmanDecoder {
     pub fn new() -> Self {
+        Self::with_transforms(&[])
+    }
+
+    /// Create an instance for data encoded with the transform `stages`.
+    pub fn with_transforms(stages: &[TransformStage]) -> Self {
+        Self::with_options(stages, 0)
+    }
+
+    /// Create an instance for data encoded with contexts of up to `order` previous bytes.
+    pub fn with_context_order(order: usize) -> Self {
+        Self::with_options(&[], order)
+    }
+
+    fn with_options(stages: &[TransformStage], order: usize) -> Self {
+        assert!(order <= MAX_CONTEXT_ORDER);
+        let transforms = TransformChain::new(stages, NUM_SYMBOLS as usize);
         Self {
-            huffman: DynamicHuffman::new(NUM_SYMBOLS),
+            model: ContextHuffman::new(transforms.num_output_symbols() as u16 + 1, order),
+            transforms,
         }
     }
 
     fn decode_loop(
-        huffman: &mut DynamicHuffman,
+        &mut self,
         reader: &mut dyn io::Read,
         writer: &mut dyn io::Write,
-    ) -> Result<DecodeResult, Box<dyn Error>> {
-        let mut buffer: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+    ) -> Result<DecodeResult, Error> {
+        let end_symbol = self.transforms.num_output_symbols() as u16;
+        let mut symbols: Vec<u16> = Vec::with_capacity(READ_BUFFER_SIZE);
+        let mut decoded: Vec<u16> = Vec::new();
         let mut bit_reader = BitReader::new(reader);
-        let mut buffer_pos = 0;
         let mut bytes_written = 0;
-        loop {
-            let symbol = huffman.decode(&mut bit_reader);
-            if symbol == 256 {
-                break;
+        let mut end = false;
+        self.transforms.reset();
+        while !end {
+            symbols.clear();
+            while symbols.len() < READ_BUFFER_SIZE {
+                let symbol = match self.model.decode(&mut bit_reader) {
+                    Ok(symbol) => symbol,
+                    Err(error) => {
+                        // Invalid data past the end of the input is due to truncation or a read error.
+                        bit_reader.check_status()?;
+                        return Err(error);
+                    }
+                };
+                if symbol == end_symbol {
+                    end = true;
+                    break;
+                }
+                symbols.push(symbol);
             }
-            buffer[buffer_pos] = symbol as u8;
-            buffer_pos += 1;
-            if buffer_pos == READ_BUFFER_SIZE {
-                writer.write_all(buffer.as_ref())?;
-                buffer_pos = 0;
-                bytes_written += READ_BUFFER_SIZE;
+            bit_reader.check_status()?;
+            decoded.clear();
+            self.transforms.decode(&symbols, &mut decoded)?;
+            if end {
+                self.transforms.finish_decode(&mut decoded)?;
             }
+            let bytes: Vec<u8> = decoded.iter().map(|&symbol| symbol as u8).collect();
+            writer.write_all(&bytes)?;
+            bytes_written += bytes.len();
         }
-        let bytes_read = bit_reader.finish();
-        writer.write_all(&buffer[0..buffer_pos])?;
-        bytes_written += buffer_pos;
+        let bytes_read = bit_reader.finish()?;
 
         Ok(DecodeResult::new(bytes_read, bytes_written))
     }
@@ -114,9 +177,78 @@ impl Decoder for DynamicHuffmanDecoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<DecodeResult, Box<dyn Error>> {
-        let mut reader = input.reader();
-        let mut writer = output.writer();
-        Self::decode_loop(&mut self.huffman, &mut reader, &mut writer)
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coding::test_util::check_io_errors;
+    use crate::transform::TransformStage::*;
+
+    // Returns the encoded size.
+    fn encode_decode(stages: &[TransformStage], input: &[u8]) -> usize {
+        let mut output = OutputSink::memory(Vec::new());
+        DynamicHuffmanEncoder::with_transforms(stages)
+            .encode(&mut InputSource::memory(input.to_vec()), &mut output)
+            .unwrap();
+        let encoded = output.take_memory();
+
+        let mut output = OutputSink::memory(Vec::new());
+        DynamicHuffmanDecoder::with_transforms(stages)
+            .decode(&mut InputSource::memory(encoded.clone()), &mut output)
+            .unwrap();
+        assert_eq!(output.take_memory(), input);
+        encoded.len()
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Sparse data, with long runs of zeros.
+        let mut input: Vec<u8> = Vec::new();
+        for i in 0..2000u32 {
+            input.extend(vec![0; (i * 7 % 300) as usize]);
+            input.push((i % 5 + 1) as u8);
+        }
+        let size = encode_decode(&[], &input);
+        assert!(encode_decode(&[ZeroRun], &input) < size / 4);
+        encode_decode(&[MoveToFront, ZeroRun], &input);
+        encode_decode(&[MoveToFront1, ZeroRun], &input);
+        encode_decode(&[MoveToFront], b"abc");
+        encode_decode(&[ZeroRun], b"");
+    }
+
+    #[test]
+    fn test_context_orders() {
+        let input = include_bytes!("../../testdata/deflate/text.txt");
+        let mut sizes = Vec::new();
+        for order in 0..=MAX_CONTEXT_ORDER {
+            let mut output = OutputSink::memory(Vec::new());
+            DynamicHuffmanEncoder::with_context_order(order)
+                .encode(&mut InputSource::slice(input), &mut output)
+                .unwrap();
+            let encoded = output.take_memory();
+            sizes.push(encoded.len());
+
+            let mut output = OutputSink::memory(Vec::new());
+            DynamicHuffmanDecoder::with_context_order(order)
+                .decode(&mut InputSource::memory(encoded), &mut output)
+                .unwrap();
+            assert_eq!(output.take_memory(), input);
+        }
+        assert!(sizes[1] < sizes[0], "{:?}", sizes);
+        assert!(sizes[2] < sizes[0], "{:?}", sizes);
+    }
+
+    #[test]
+    fn test_io_errors() {
+        check_io_errors(
+            || Box::new(DynamicHuffmanEncoder::new()),
+            || Box::new(DynamicHuffmanDecoder::new()),
+        );
     }
 }
@@ -1,8 +1,8 @@
-use std::error::Error;
 use std::fmt;
 
 use crate::coding::input::InputSource;
 use crate::coding::output::OutputSink;
+use crate::error::Error;
 
 pub struct EncodeResult {
     bytes_read: usize,
@@ -16,6 +16,14 @@ impl EncodeResult {
             bytes_written,
         }
     }
+
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
 }
 
 impl fmt::Display for EncodeResult {
@@ -33,5 +41,5 @@ pub trait Encoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<EncodeResult, Box<dyn Error>>;
+    ) -> Result<EncodeResult, Error>;
 }
@@ -0,0 +1,473 @@
+use std::io;
+use std::io::Read;
+
+use crate::checksum::{Checksum, Crc32, XxHash64};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::coding::stream::DecompressReader;
+use crate::coding::CompressionMethod;
+use crate::error::Error;
+
+/// Magic bytes at the start of a frame.
+pub const FRAME_MAGIC: [u8; 4] = *b"CPRS";
+
+/// Version of the frame format written by this implementation. Frames of other versions are
+/// rejected.
+pub const FRAME_VERSION: u8 = 1;
+
+// Header flags.
+const FLAG_ORIGINAL_SIZE: u8 = 0x01;
+const FLAG_CRC32: u8 = 0x02;
+const FLAG_XXHASH64: u8 = 0x04;
+const FLAG_BLOCKS: u8 = 0x08;
+const FLAGS_SUPPORTED: u8 = FLAG_ORIGINAL_SIZE | FLAG_CRC32 | FLAG_XXHASH64 | FLAG_BLOCKS;
+
+// Input buffer size.
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+
+// Size of the fixed part of the header: magic, version, method and flags.
+const FIXED_HEADER_SIZE: usize = FRAME_MAGIC.len() + 3;
+
+/// Checksum of the original data at the end of a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameChecksum {
+    /// CRC-32, stored in 4 bytes.
+    Crc32,
+
+    /// xxHash64 with seed 0, stored in 8 bytes. This is faster on large data.
+    XxHash64,
+}
+
+impl FrameChecksum {
+    fn flag(self) -> u8 {
+        match self {
+            FrameChecksum::Crc32 => FLAG_CRC32,
+            FrameChecksum::XxHash64 => FLAG_XXHASH64,
+        }
+    }
+
+    // Returns the size of the checksum in bytes.
+    pub(crate) fn size(self) -> usize {
+        match self {
+            FrameChecksum::Crc32 => 4,
+            FrameChecksum::XxHash64 => 8,
+        }
+    }
+
+    // Returns the checksum of the data from `reader`, as stored in the frame.
+    fn compute(self, reader: &mut dyn Read) -> io::Result<Vec<u8>> {
+        let mut hasher = FrameHasher::new(self);
+        let mut buf: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        loop {
+            let len = reader.read(buf.as_mut_slice())?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+        }
+        Ok(hasher.value())
+    }
+}
+
+/// Running checksum of the original data, for data that arrives in pieces.
+pub(crate) enum FrameHasher {
+    Crc32(Crc32),
+    XxHash64(XxHash64),
+}
+
+impl FrameHasher {
+    pub fn new(checksum: FrameChecksum) -> Self {
+        match checksum {
+            FrameChecksum::Crc32 => FrameHasher::Crc32(Crc32::new()),
+            FrameChecksum::XxHash64 => FrameHasher::XxHash64(XxHash64::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            FrameHasher::Crc32(crc) => crc.update(data),
+            FrameHasher::XxHash64(hash) => hash.update(data),
+        }
+    }
+
+    /// Returns the checksum as stored in the frame.
+    pub fn value(&self) -> Vec<u8> {
+        match self {
+            FrameHasher::Crc32(crc) => crc.value().to_le_bytes().to_vec(),
+            FrameHasher::XxHash64(hash) => hash.value().to_le_bytes().to_vec(),
+        }
+    }
+}
+
+// Updates a checksum with the data read.
+struct HashingReader<'a> {
+    reader: Box<dyn Read + 'a>,
+    hasher: &'a mut FrameHasher,
+}
+
+impl Read for HashingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Options of the frame header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameOptions {
+    /// Whether to store the size of the original data, which the decoder checks.
+    pub original_size: bool,
+
+    /// Checksum of the original data to append, which the decoder verifies.
+    pub checksum: Option<FrameChecksum>,
+}
+
+impl FrameOptions {
+    pub fn new() -> Self {
+        Self {
+            original_size: true,
+            checksum: None,
+        }
+    }
+}
+
+impl Default for FrameOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encoder of the comprs frame format: a header that identifies the data, followed by the
+/// output of a compression method.
+///
+/// The header has the magic bytes "CPRS", the format version, the method ID, flags, and if the
+/// flag is set, the original size as a 64-bit little-endian value. If a checksum flag is set,
+/// the checksum of the original data follows the output of the method, in little-endian. Frames
+/// with the blocks flag are written by `CompressWriter`.
+pub struct FrameEncoder {
+    method: CompressionMethod,
+    options: FrameOptions,
+}
+
+impl FrameEncoder {
+    pub fn new(method: CompressionMethod) -> Self {
+        Self::with_options(method, FrameOptions::new())
+    }
+
+    pub fn with_options(method: CompressionMethod, options: FrameOptions) -> Self {
+        Self { method, options }
+    }
+
+    fn header(&self, original_size: u64) -> Vec<u8> {
+        FrameHeader {
+            method: self.method,
+            original_size: self.options.original_size.then_some(original_size),
+            checksum: self.options.checksum,
+            blocks: false,
+        }
+        .to_bytes()
+    }
+}
+
+impl Encoder for FrameEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut payload = OutputSink::memory(Vec::new());
+        let (result, trailer) = match self.options.checksum {
+            None => (
+                self.method.encoder().encode(input, &mut payload)?,
+                Vec::new(),
+            ),
+            Some(checksum) if input.len().is_some() => {
+                let result = self.method.encoder().encode(input, &mut payload)?;
+                (result, checksum.compute(&mut input.reader()?)?)
+            }
+            Some(checksum) => {
+                // The input can only be read once, so it's checksummed as it's encoded.
+                let mut hasher = FrameHasher::new(checksum);
+                let reader = HashingReader {
+                    reader: input.reader()?,
+                    hasher: &mut hasher,
+                };
+                let result = self.method.encoder().encode(
+                    &mut InputSource::from_reader(Box::new(reader)),
+                    &mut payload,
+                )?;
+                (result, hasher.value())
+            }
+        };
+        let payload = payload.take_memory();
+
+        let header = self.header(result.bytes_read() as u64);
+        let mut writer = output.writer()?;
+        writer.write_all(&header)?;
+        writer.write_all(&payload)?;
+        writer.write_all(&trailer)?;
+
+        Ok(EncodeResult::new(
+            result.bytes_read(),
+            header.len() + payload.len() + trailer.len(),
+        ))
+    }
+}
+
+/// A decoded frame header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub method: CompressionMethod,
+
+    /// Size of the original data, if stored.
+    pub original_size: Option<u64>,
+
+    /// Checksum at the end of the frame, if any.
+    pub checksum: Option<FrameChecksum>,
+
+    /// Whether the payload is a sequence of blocks, as written by `CompressWriter`.
+    pub blocks: bool,
+}
+
+impl FrameHeader {
+    /// Reads a frame header, and checks that it's supported.
+    pub fn read(reader: &mut dyn Read) -> Result<Self, Error> {
+        let mut fixed = [0; FIXED_HEADER_SIZE];
+        if reader.read_exact(&mut fixed).is_err() || fixed[..4] != FRAME_MAGIC {
+            return Err(Error::CorruptHeader("Not a comprs frame"));
+        }
+        let version = fixed[4];
+        if version != FRAME_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let method = CompressionMethod::from_id(fixed[5])
+            .ok_or(Error::Unsupported("Unknown compression method"))?;
+        let flags = fixed[6];
+        if flags & !FLAGS_SUPPORTED != 0 {
+            return Err(Error::Unsupported("Unsupported frame flags"));
+        }
+        if flags & FLAG_CRC32 != 0 && flags & FLAG_XXHASH64 != 0 {
+            return Err(Error::CorruptHeader("More than one checksum flag"));
+        }
+        let checksum = [FrameChecksum::Crc32, FrameChecksum::XxHash64]
+            .into_iter()
+            .find(|checksum| flags & checksum.flag() != 0);
+
+        let mut original_size = None;
+        if flags & FLAG_ORIGINAL_SIZE != 0 {
+            let mut size = [0; 8];
+            reader.read_exact(&mut size)?;
+            original_size = Some(u64::from_le_bytes(size));
+        }
+        Ok(Self {
+            method,
+            original_size,
+            checksum,
+            blocks: flags & FLAG_BLOCKS != 0,
+        })
+    }
+
+    /// Returns the header as written in the frame.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.original_size.is_some() {
+            flags |= FLAG_ORIGINAL_SIZE;
+        }
+        if let Some(checksum) = self.checksum {
+            flags |= checksum.flag();
+        }
+        if self.blocks {
+            flags |= FLAG_BLOCKS;
+        }
+        let mut header: Vec<u8> = FRAME_MAGIC.to_vec();
+        header.extend([FRAME_VERSION, self.method.id(), flags]);
+        if let Some(original_size) = self.original_size {
+            header.extend(original_size.to_le_bytes());
+        }
+        header
+    }
+
+    // Returns the size of the header in bytes.
+    pub(crate) fn size(&self) -> usize {
+        FIXED_HEADER_SIZE + if self.original_size.is_some() { 8 } else { 0 }
+    }
+}
+
+/// Decoder of frames, with any of the compression methods.
+pub struct FrameDecoder {}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for FrameDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = DecompressReader::new(input.reader()?);
+        let bytes_written = reader.decode_to(&mut output.writer()?)?;
+        Ok(DecodeResult::new(reader.bytes_read(), bytes_written))
+    }
+}
+
+/// Decompresses a frame with any of the compression methods.
+pub fn decompress(input: &mut InputSource, output: &mut OutputSink) -> Result<DecodeResult, Error> {
+    FrameDecoder::new().decode(input, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn encode(input: &[u8], method: CompressionMethod, options: FrameOptions) -> Vec<u8> {
+        let mut output = OutputSink::memory(Vec::new());
+        FrameEncoder::with_options(method, options)
+            .encode(&mut InputSource::memory(input.to_vec()), &mut output)
+            .unwrap();
+        output.take_memory()
+    }
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = OutputSink::memory(Vec::new());
+        decompress(&mut InputSource::memory(encoded.to_vec()), &mut output)?;
+        Ok(output.take_memory())
+    }
+
+    #[test]
+    fn test_all_methods() {
+        for method in CompressionMethod::ALL {
+            let encoded = encode(TEXT, method, FrameOptions::new());
+            assert_eq!(encoded[..4], FRAME_MAGIC);
+            assert_eq!(encoded[5], method.id());
+            assert_eq!(decode(&encoded).unwrap(), TEXT, "{}", method.name());
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let encoded = encode(
+            TEXT,
+            CompressionMethod::DynamicHuffmanCoding,
+            FrameOptions::new(),
+        );
+        let header = FrameHeader::read(&mut &encoded[..]).unwrap();
+        assert_eq!(header.method, CompressionMethod::DynamicHuffmanCoding);
+        assert_eq!(header.original_size, Some(TEXT.len() as u64));
+
+        let options = FrameOptions {
+            original_size: false,
+            checksum: None,
+        };
+        let encoded = encode(TEXT, CompressionMethod::DynamicHuffmanCoding, options);
+        let header = FrameHeader::read(&mut &encoded[..]).unwrap();
+        assert_eq!(header.original_size, None);
+        assert_eq!(decode(&encoded).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let encoded = encode(
+            TEXT,
+            CompressionMethod::DynamicHuffmanCoding,
+            FrameOptions::new(),
+        );
+        let corrupt = |i: usize, value: u8| {
+            let mut corrupt = encoded.clone();
+            corrupt[i] = value;
+            decode(&corrupt)
+        };
+
+        // Foreign data.
+        assert!(decode(b"").is_err());
+        assert!(decode(include_bytes!("../../testdata/gzip/text.txt.gz")).is_err());
+        assert!(corrupt(0, b'X').is_err());
+
+        // Future version, unknown method, and unknown flags.
+        assert!(matches!(
+            corrupt(4, FRAME_VERSION + 1),
+            Err(Error::UnsupportedVersion(version)) if version == FRAME_VERSION + 1
+        ));
+        assert!(corrupt(5, 0xff).is_err());
+        assert!(corrupt(6, 0x80).is_err());
+
+        // Wrong original size.
+        assert!(corrupt(7, TEXT.len() as u8 ^ 1).is_err());
+        assert!(decode(&encoded[..10]).is_err());
+    }
+
+    #[test]
+    fn test_checksum() {
+        for checksum in [FrameChecksum::Crc32, FrameChecksum::XxHash64] {
+            let options = FrameOptions {
+                original_size: true,
+                checksum: Some(checksum),
+            };
+            let encoded = encode(TEXT, CompressionMethod::DynamicHuffmanCoding, options);
+            let header = FrameHeader::read(&mut &encoded[..]).unwrap();
+            assert_eq!(header.checksum, Some(checksum));
+            assert_eq!(decode(&encoded).unwrap(), TEXT);
+
+            // Corrupt checksum.
+            let mut corrupt = encoded.clone();
+            let len = corrupt.len();
+            corrupt[len - 1] ^= 1;
+            assert!(matches!(decode(&corrupt), Err(Error::ChecksumMismatch)));
+        }
+
+        // Corrupt data that decodes to the same size.
+        let options = FrameOptions {
+            original_size: true,
+            checksum: Some(FrameChecksum::Crc32),
+        };
+        let mut encoded = encode(TEXT, CompressionMethod::StaticHuffmanCoding, options);
+        let len = encoded.len();
+        encoded[len - 100] ^= 0x10;
+        assert!(matches!(decode(&encoded), Err(Error::ChecksumMismatch)));
+
+        // Input that can only be read once.
+        for checksum in [FrameChecksum::Crc32, FrameChecksum::XxHash64] {
+            let options = FrameOptions {
+                original_size: true,
+                checksum: Some(checksum),
+            };
+            for method in [
+                CompressionMethod::DynamicHuffmanCoding,
+                CompressionMethod::StaticHuffmanCoding,
+            ] {
+                let mut output = OutputSink::memory(Vec::new());
+                FrameEncoder::with_options(method, options.clone())
+                    .encode(&mut InputSource::from_reader(Box::new(TEXT)), &mut output)
+                    .unwrap();
+                let encoded = output.take_memory();
+                assert_eq!(decode(&encoded).unwrap(), TEXT);
+                if method == CompressionMethod::DynamicHuffmanCoding {
+                    assert_eq!(encoded, encode(TEXT, method, options.clone()));
+                }
+            }
+        }
+
+        // Both checksum flags.
+        let mut encoded = encode(
+            TEXT,
+            CompressionMethod::DynamicHuffmanCoding,
+            FrameOptions::new(),
+        );
+        encoded[6] |= FLAG_CRC32 | FLAG_XXHASH64;
+        assert!(decode(&encoded).is_err());
+    }
+}
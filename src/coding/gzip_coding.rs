@@ -0,0 +1,375 @@
+use std::io;
+
+use crate::bits::{LsbBitReader, LsbBitWriter};
+use crate::checksum::{Checksum, ChecksumWriter, Crc32};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::deflate_coding::deflate_stream;
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::deflate::{Deflater, Inflater};
+use crate::error::Error;
+
+/// The first 2 bytes of a gzip member.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Compression method: DEFLATE.
+const METHOD_DEFLATE: u8 = 8;
+
+// Header flags.
+const FLAG_TEXT: u8 = 0x01;
+const FLAG_HEADER_CRC: u8 = 0x02;
+const FLAG_EXTRA: u8 = 0x04;
+const FLAG_NAME: u8 = 0x08;
+const FLAG_COMMENT: u8 = 0x10;
+const FLAG_RESERVED: u8 = 0xe0;
+
+/// Operating system value for an unknown system.
+pub const OS_UNKNOWN: u8 = 255;
+
+/// Header fields of a gzip member (RFC 1952).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GzipHeader {
+    /// Original file name, in ISO 8859-1, without the terminating zero byte.
+    pub file_name: Option<Vec<u8>>,
+
+    /// Comment, in ISO 8859-1, without the terminating zero byte.
+    pub comment: Option<Vec<u8>>,
+
+    /// Extra field, up to 65535 bytes.
+    pub extra: Option<Vec<u8>>,
+
+    /// Modification time in seconds since the Unix epoch, or 0 if not available.
+    pub mtime: u32,
+
+    /// Operating system on which the data was compressed.
+    pub os: u8,
+
+    /// Whether the data is probably text.
+    pub text: bool,
+}
+
+impl GzipHeader {
+    pub fn new() -> Self {
+        Self {
+            file_name: None,
+            comment: None,
+            extra: None,
+            mtime: 0,
+            os: OS_UNKNOWN,
+            text: false,
+        }
+    }
+
+    // Serializes the header.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut flags = 0;
+        if self.text {
+            flags |= FLAG_TEXT;
+        }
+        if self.extra.is_some() {
+            flags |= FLAG_EXTRA;
+        }
+        if self.file_name.is_some() {
+            flags |= FLAG_NAME;
+        }
+        if self.comment.is_some() {
+            flags |= FLAG_COMMENT;
+        }
+
+        let mut bytes: Vec<u8> = GZIP_MAGIC.to_vec();
+        bytes.extend([METHOD_DEFLATE, flags]);
+        bytes.extend(self.mtime.to_le_bytes());
+        bytes.extend([0, self.os]);
+        if let Some(extra) = &self.extra {
+            assert!(extra.len() <= u16::MAX as usize);
+            bytes.extend((extra.len() as u16).to_le_bytes());
+            bytes.extend(extra);
+        }
+        for field in [&self.file_name, &self.comment].into_iter().flatten() {
+            assert!(!field.contains(&0));
+            bytes.extend(field);
+            bytes.push(0);
+        }
+        bytes
+    }
+}
+
+/// gzip (RFC 1952) encoder, writing a single member with DEFLATE compressed data.
+pub struct GzipEncoder {
+    header: GzipHeader,
+    deflater: Deflater,
+}
+
+impl GzipEncoder {
+    pub fn new() -> Self {
+        Self::with_header(GzipHeader::new())
+    }
+
+    /// Create an instance that writes `header` as the member header.
+    pub fn with_header(header: GzipHeader) -> Self {
+        Self {
+            header,
+            deflater: Deflater::new(),
+        }
+    }
+
+    fn encode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = LsbBitWriter::new(writer);
+        bit_writer.write_bytes(&self.header.to_bytes());
+
+        let mut crc = Crc32::new();
+        let bytes_read =
+            deflate_stream(&mut self.deflater, reader, &mut bit_writer, Some(&mut crc))?;
+
+        // Write the trailer: CRC-32 and size of the data.
+        bit_writer.align_to_byte();
+        bit_writer.write_bytes(&crc.value().to_le_bytes());
+        bit_writer.write_bytes(&(bytes_read as u32).to_le_bytes());
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for GzipEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+/// Decoder for gzip data. All members are decoded, and their data concatenated.
+pub struct GzipDecoder {
+    inflater: Inflater,
+
+    // Headers of the members decoded by the last call.
+    headers: Vec<GzipHeader>,
+}
+
+impl GzipDecoder {
+    pub fn new() -> Self {
+        Self {
+            inflater: Inflater::new(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Returns the headers of the members that were decoded.
+    #[cfg(test)]
+    pub fn headers(&self) -> &[GzipHeader] {
+        &self.headers
+    }
+
+    pub(crate) fn decode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = LsbBitReader::new(reader);
+        let mut bytes_written = 0;
+        self.headers.clear();
+        while let Some(header) = Self::read_header(&mut bit_reader, self.headers.is_empty())? {
+            self.headers.push(header);
+
+            let mut checksum_writer = ChecksumWriter::new(&mut *writer, Crc32::new());
+            self.inflater
+                .inflate(&mut bit_reader, &mut checksum_writer)?;
+
+            // Check the trailer.
+            bit_reader.align_to_byte();
+            let mut trailer = [0; 8];
+            read_exact(&mut bit_reader, &mut trailer)?;
+            if u32::from_le_bytes(trailer[..4].try_into().unwrap()) != checksum_writer.value() {
+                return Err(Error::ChecksumMismatch);
+            }
+            let size = checksum_writer.bytes_written();
+            if u32::from_le_bytes(trailer[4..].try_into().unwrap()) != size as u32 {
+                return Err(Error::CorruptData("Size mismatch"));
+            }
+            bytes_written += size;
+        }
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+
+    // Reads a member header. Returns None at the end of the data, unless `first` is set.
+    fn read_header(
+        bit_reader: &mut LsbBitReader,
+        first: bool,
+    ) -> Result<Option<GzipHeader>, Error> {
+        let mut fixed = [0; 10];
+        let len = bit_reader.read_bytes(&mut fixed);
+        bit_reader.check_status()?;
+        if len == 0 && !first {
+            return Ok(None);
+        }
+        if len < fixed.len() || fixed[..2] != GZIP_MAGIC {
+            return Err(Error::CorruptHeader("Invalid gzip header"));
+        }
+        if fixed[2] != METHOD_DEFLATE {
+            return Err(Error::Unsupported("Unsupported compression method"));
+        }
+        let flags = fixed[3];
+        if flags & FLAG_RESERVED != 0 {
+            return Err(Error::CorruptHeader("Reserved flags are set"));
+        }
+
+        let mut header = GzipHeader::new();
+        header.text = flags & FLAG_TEXT != 0;
+        header.mtime = u32::from_le_bytes(fixed[4..8].try_into().unwrap());
+        header.os = fixed[9];
+
+        // Keep the header bytes for the header CRC.
+        let mut header_bytes = fixed.to_vec();
+        if flags & FLAG_EXTRA != 0 {
+            let mut len = [0; 2];
+            read_exact(bit_reader, &mut len)?;
+            let mut extra = vec![0; u16::from_le_bytes(len) as usize];
+            read_exact(bit_reader, &mut extra)?;
+            header_bytes.extend(len);
+            header_bytes.extend(&extra);
+            header.extra = Some(extra);
+        }
+        if flags & FLAG_NAME != 0 {
+            let file_name = read_zero_terminated(bit_reader)?;
+            header_bytes.extend(&file_name);
+            header_bytes.push(0);
+            header.file_name = Some(file_name);
+        }
+        if flags & FLAG_COMMENT != 0 {
+            let comment = read_zero_terminated(bit_reader)?;
+            header_bytes.extend(&comment);
+            header_bytes.push(0);
+            header.comment = Some(comment);
+        }
+        if flags & FLAG_HEADER_CRC != 0 {
+            let mut crc = [0; 2];
+            read_exact(bit_reader, &mut crc)?;
+            if u16::from_le_bytes(crc) != Crc32::checksum(&header_bytes) as u16 {
+                return Err(Error::ChecksumMismatch);
+            }
+        }
+        Ok(Some(header))
+    }
+}
+
+impl Decoder for GzipDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+// Reads exactly `bytes.len()` bytes.
+fn read_exact(bit_reader: &mut LsbBitReader, bytes: &mut [u8]) -> Result<(), Error> {
+    if bit_reader.read_bytes(bytes) != bytes.len() {
+        bit_reader.check_status()?;
+        return Err(Error::Truncated);
+    }
+    Ok(())
+}
+
+// Reads a zero-terminated string, without the terminating zero.
+fn read_zero_terminated(bit_reader: &mut LsbBitReader) -> Result<Vec<u8>, Error> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut byte = [0; 1];
+    loop {
+        read_exact(bit_reader, &mut byte)?;
+        if byte[0] == 0 {
+            return Ok(bytes);
+        }
+        bytes.push(byte[0]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn decode(encoded: &[u8]) -> Result<(Vec<u8>, Vec<GzipHeader>), Error> {
+        let mut decoder = GzipDecoder::new();
+        let mut decoded = Vec::new();
+        decoder.decode_loop(&mut io::Cursor::new(encoded), &mut decoded)?;
+        Ok((decoded, decoder.headers().to_vec()))
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Written by Python's gzip module.
+        let (decoded, headers) = decode(include_bytes!("../../testdata/gzip/text.txt.gz")).unwrap();
+        assert_eq!(decoded, TEXT);
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers[0].file_name.as_deref(), Some(&b"text.txt"[..]));
+        assert_eq!(headers[0].mtime, 1234567890);
+
+        // Two members.
+        let (decoded, headers) = decode(include_bytes!("../../testdata/gzip/multi.gz")).unwrap();
+        assert_eq!(decoded, b"Hello, world!\nHello again!\n");
+        assert_eq!(headers.len(), 2);
+
+        // All optional header fields, with a header CRC.
+        let (decoded, headers) = decode(include_bytes!("../../testdata/gzip/fields.gz")).unwrap();
+        assert_eq!(decoded, b"Hello, world!\n");
+        let header = &headers[0];
+        assert_eq!(header.extra.as_deref(), Some(&b"AB\x02\x00xy"[..]));
+        assert_eq!(header.file_name.as_deref(), Some(&b"hello.txt"[..]));
+        assert_eq!(header.comment.as_deref(), Some(&b"a comment"[..]));
+        assert!(header.text);
+        assert_eq!(header.os, 3);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let mut header = GzipHeader::new();
+        header.file_name = Some(b"text.txt".to_vec());
+        header.comment = Some(b"comment".to_vec());
+        header.extra = Some(vec![1, 2, 3, 4]);
+        header.mtime = 42;
+
+        for input in [&b""[..], b"a", TEXT] {
+            let mut encoded = Vec::new();
+            GzipEncoder::with_header(header.clone())
+                .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+                .unwrap();
+            let (decoded, headers) = decode(&encoded).unwrap();
+            assert_eq!(decoded, input);
+            assert_eq!(headers, vec![header.clone()]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let encoded = include_bytes!("../../testdata/gzip/text.txt.gz");
+        assert!(decode(&[]).is_err());
+        assert!(decode(&encoded[..encoded.len() - 1]).is_err());
+
+        // Corrupt CRC.
+        let mut corrupt = encoded.to_vec();
+        let len = corrupt.len();
+        corrupt[len - 8] ^= 1;
+        assert!(decode(&corrupt).is_err());
+
+        // Trailing garbage.
+        let mut trailing = encoded.to_vec();
+        trailing.push(0);
+        assert!(decode(&trailing).is_err());
+    }
+}
@@ -24,93 +24,192 @@ impl io::Read for MemReader {
         Ok(to_read)
     }
 }
-enum InputSourceType {
-    File,
-    Memory,
-}
 
-pub struct InputSource {
-    source_type: InputSourceType,
+enum InputSourceType<'a> {
+    File(String),
+    Memory(Rc<Vec<u8>>),
+    Slice(&'a [u8]),
 
-    // Number of bytes,
-    num_bytes: u64,
+    // None once the reader is taken.
+    Reader(Option<Box<dyn io::Read + 'a>>),
+    Stdin,
+}
 
-    // For file input.
-    filename: String,
+/// The input of an encoder or decoder.
+///
+/// Files, memory and slices can be read any number of times, and have a known length. Readers
+/// and stdin can only be read once, and have no known length, so that encoders that need the
+/// length or several passes over the input fall back to a streaming layout.
+pub struct InputSource<'a> {
+    source_type: InputSourceType<'a>,
 
-    // For memory input.
-    data: Rc<Vec<u8>>,
+    // Number of bytes, if known.
+    num_bytes: Option<u64>,
+
+    // Whether a one-time source has been read.
+    consumed: bool,
 }
 
-impl InputSource {
+impl<'a> InputSource<'a> {
+    /// Create a source that reads the file `filename`. If the size of the file isn't known, as
+    /// for pipes, the length is unknown.
     pub fn file(filename: &str) -> Self {
-        let metadata = fs::metadata(filename).unwrap();
-        let file_size = metadata.len();        
-        Self {
-            source_type: InputSourceType::File,
-            num_bytes: file_size,
-            filename: filename.to_string(),
-            data: Default::default(),
-        }
+        let num_bytes = fs::metadata(filename)
+            .ok()
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len());
+        Self::new(InputSourceType::File(filename.to_string()), num_bytes)
     }
 
     pub fn memory(data: Vec<u8>) -> Self {
+        let num_bytes = data.len() as u64;
+        Self::new(InputSourceType::Memory(Rc::new(data)), Some(num_bytes))
+    }
+
+    /// Create a source that reads `data` without copying it.
+    pub fn slice(data: &'a [u8]) -> Self {
+        Self::new(InputSourceType::Slice(data), Some(data.len() as u64))
+    }
+
+    /// Create a source that reads `reader` once.
+    pub fn from_reader(reader: Box<dyn io::Read + 'a>) -> Self {
+        Self::new(InputSourceType::Reader(Some(reader)), None)
+    }
+
+    /// Create a source that reads stdin once.
+    pub fn stdin() -> Self {
+        Self::new(InputSourceType::Stdin, None)
+    }
+
+    fn new(source_type: InputSourceType<'a>, num_bytes: Option<u64>) -> Self {
         Self {
-            source_type: InputSourceType::Memory,
-            num_bytes: data.len() as u64,
-            filename: Default::default(),
-            data: Rc::new(data),
+            source_type,
+            num_bytes,
+            consumed: false,
         }
     }
 
-    pub fn len(&self) -> u64 {
+    /// Returns the number of bytes, if known. Sources of unknown length can only be read once.
+    pub fn len(&self) -> Option<u64> {
         self.num_bytes
     }
 
-    pub fn take_memory(self) -> Vec<u8> {
-        Rc::into_inner(self.data).unwrap()
+    /// Returns true if the source is known to be empty.
+    pub fn is_empty(&self) -> bool {
+        self.num_bytes == Some(0)
+    }
+
+    /// Returns the data of a memory source, or None for other sources.
+    pub fn take_memory(self) -> Option<Vec<u8>> {
+        match self.source_type {
+            InputSourceType::Memory(data) => Rc::into_inner(data),
+            _ => None,
+        }
     }
 
-    pub fn frequencies(&self) -> Vec<u32> {
+    /// Returns the frequencies of the bytes. This reads the whole source, so it fails for
+    /// sources that can only be read once.
+    pub fn frequencies(&self) -> io::Result<Vec<u32>> {
         let mut frequencies: Vec<u32> = vec![0; 256];
+        let mut count = |bytes: &[u8]| {
+            for &byte in bytes.iter() {
+                frequencies[byte as usize] += 1;
+            }
+        };
         match &self.source_type {
-            InputSourceType::File => {
-                let mut file = fs::File::open(&self.filename).unwrap();
+            InputSourceType::File(filename) => {
+                let mut file = fs::File::open(filename)?;
                 let mut buffer = [0; 1024];
-                while let Ok(bytes_read) = file.read(&mut buffer) {
+                loop {
+                    let bytes_read = file.read(&mut buffer)?;
                     if bytes_read == 0 {
                         break;
                     }
-                    for byte in buffer[..bytes_read].iter() {
-                        frequencies[*byte as usize] += 1;
-                    }
-                }
-            }
-            InputSourceType::Memory => {
-                for byte in self.data.iter() {
-                    frequencies[*byte as usize] += 1;
+                    count(&buffer[..bytes_read]);
                 }
             }
+            InputSourceType::Memory(data) => count(data),
+            InputSourceType::Slice(data) => count(data),
+            InputSourceType::Reader(_) | InputSourceType::Stdin => return Err(read_once_error()),
         }
-        frequencies
+        Ok(frequencies)
     }
 
-    pub fn reader(&mut self) -> Box<dyn io::Read> {
-        match &self.source_type {
-            InputSourceType::File => {
-                let file = fs::File::open(&self.filename).unwrap();
-                Box::new(file)
+    /// Returns a reader of the data from the start. Sources of unknown length can only be read
+    /// once.
+    pub fn reader(&mut self) -> io::Result<Box<dyn io::Read + '_>> {
+        match &mut self.source_type {
+            InputSourceType::File(filename) => Ok(Box::new(fs::File::open(&*filename)?)),
+            InputSourceType::Memory(data) => Ok(Box::new(MemReader::new(data.clone()))),
+            InputSourceType::Slice(data) => Ok(Box::new(*data)),
+            InputSourceType::Reader(reader) => match reader.take() {
+                Some(reader) => Ok(reader),
+                None => Err(read_once_error()),
+            },
+            InputSourceType::Stdin => {
+                if self.consumed {
+                    return Err(read_once_error());
+                }
+                self.consumed = true;
+                Ok(Box::new(io::stdin().lock()))
             }
-            InputSourceType::Memory => Box::new(MemReader::new(self.data.clone())),
         }
     }
 }
 
-impl fmt::Display for InputSource {
+fn read_once_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "The input source can only be read once",
+    )
+}
+
+impl fmt::Display for InputSource<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.source_type {
-            InputSourceType::File => write!(f, "Input file: {}", self.filename),
-            InputSourceType::Memory => write!(f, "Input memory: {:?} bytes", self.data.len()),
+            InputSourceType::File(filename) => write!(f, "Input file: {}", filename),
+            InputSourceType::Memory(data) => write!(f, "Input memory: {:?} bytes", data.len()),
+            InputSourceType::Slice(data) => write!(f, "Input slice: {:?} bytes", data.len()),
+            InputSourceType::Reader(_) => write!(f, "Input reader"),
+            InputSourceType::Stdin => write!(f, "Input stdin"),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(input: &mut InputSource) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        input.reader()?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn test_sources() {
+        let data = b"abcab".to_vec();
+        let mut memory = InputSource::memory(data.clone());
+        let mut slice = InputSource::slice(&data);
+        for input in [&mut memory, &mut slice] {
+            assert_eq!(input.len(), Some(5));
+            assert_eq!(input.frequencies().unwrap()[b'a' as usize], 2);
+            assert_eq!(read_all(input).unwrap(), data);
+            assert_eq!(read_all(input).unwrap(), data);
+        }
+        assert_eq!(memory.take_memory(), Some(data.clone()));
+
+        // A reader can only be read once.
+        let mut reader = InputSource::from_reader(Box::new(&data[..]));
+        assert_eq!(reader.len(), None);
+        assert!(reader.frequencies().is_err());
+        assert_eq!(read_all(&mut reader).unwrap(), data);
+        assert!(reader.reader().is_err());
+
+        // A missing file fails when it's read.
+        let mut file = InputSource::file("/nonexistent/file");
+        assert_eq!(file.len(), None);
+        assert!(file.reader().is_err());
+        assert!(file.frequencies().is_err());
+    }
+}
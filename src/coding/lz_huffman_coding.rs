@@ -0,0 +1,357 @@
+use std::io;
+use std::io::Read;
+use std::mem;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+use crate::huffman::{LengthLimitedHuffman, PrefixCode};
+use crate::lz::{
+    bucket_base, bucket_symbol, copy_match, num_bucket_symbols, MatchFinder, Token, MAX_MATCH,
+    MAX_WINDOW_SIZE, MIN_MATCH,
+};
+
+/// Default number of input bytes in each block.
+pub const DEFAULT_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// Largest supported block size.
+pub const MAX_BLOCK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default window size.
+pub const DEFAULT_WINDOW_SIZE: usize = 32 * 1024;
+
+/// Default number of hash chain candidates to compare.
+pub const DEFAULT_SEARCH_DEPTH: usize = 32;
+
+// Number of literal symbols. Length symbols follow the literals.
+const NUM_LITERALS: u16 = 256;
+
+// Maximum code length.
+const MAX_CODE_LENGTH: usize = 15;
+
+// Number of bits to store the block length.
+const BLOCK_LENGTH_BITS: u32 = 32;
+
+// Number of bits to store the minimum match length.
+const MIN_MATCH_BITS: u32 = 9;
+
+/// LZ77 encoder, with the tokens coded by static Huffman codes.
+///
+/// Each block of input is parsed into literals and matches by a hash chain `MatchFinder`. Literals
+/// and match lengths share one prefix code, and match distances have another. Lengths and
+/// distances are mapped to symbols with extra bits by `bucket_symbol`.
+///
+/// Each block has a header with a last-block flag and the block length, followed by the minimum
+/// match length, the coding tables and the coded tokens. Matches do not cross blocks.
+pub struct LzHuffmanEncoder {
+    block_size: usize,
+    min_match: usize,
+    match_finder: MatchFinder,
+}
+
+impl LzHuffmanEncoder {
+    pub fn new() -> Self {
+        Self::with_options(
+            DEFAULT_BLOCK_SIZE,
+            DEFAULT_WINDOW_SIZE,
+            MIN_MATCH,
+            DEFAULT_SEARCH_DEPTH,
+        )
+    }
+
+    /// Create an instance with the given block size and match finder parameters.
+    /// `window_size` must be a power of 2.
+    pub fn with_options(
+        block_size: usize,
+        window_size: usize,
+        min_match: usize,
+        search_depth: usize,
+    ) -> Self {
+        assert!(block_size > 0 && block_size <= MAX_BLOCK_SIZE);
+        Self {
+            block_size,
+            min_match,
+            match_finder: MatchFinder::new(window_size, min_match, search_depth),
+        }
+    }
+
+    fn encode_block(&mut self, block: &[u8], last: bool, bit_writer: &mut BitWriter) {
+        // Write the block header.
+        bit_writer.write_bits(last as u64, 1);
+        bit_writer.write_bits(block.len() as u64, BLOCK_LENGTH_BITS);
+        if block.is_empty() {
+            return;
+        }
+        bit_writer.write_bits(self.min_match as u64, MIN_MATCH_BITS);
+
+        // Count the symbols.
+        let tokens = self.match_finder.parse(block);
+        let min_match = self.min_match as u32;
+        let mut literal_weights: Vec<u32> = vec![0; num_literal_symbols(min_match) as usize];
+        let mut distance_weights: Vec<u32> = vec![0; num_distance_symbols() as usize];
+        for token in tokens.iter() {
+            match *token {
+                Token::Literal(byte) => literal_weights[byte as usize] += 1,
+                Token::Match { length, distance } => {
+                    let length_symbol = bucket_symbol(length - min_match).0;
+                    literal_weights[(NUM_LITERALS + length_symbol) as usize] += 1;
+                    distance_weights[bucket_symbol(distance - 1).0 as usize] += 1;
+                }
+            }
+        }
+
+        // Write the coding tables. The distance table is omitted if there are no matches.
+        let literal_code = build_code(&literal_weights);
+        literal_code.encode_coding_table(bit_writer);
+        let has_matches = distance_weights.iter().any(|&weight| weight > 0);
+        bit_writer.write_bits(has_matches as u64, 1);
+        let distance_code = build_code(&distance_weights);
+        if has_matches {
+            distance_code.encode_coding_table(bit_writer);
+        }
+
+        // Write the tokens.
+        let literal_table = literal_code.generate_encoder_table();
+        let distance_table = distance_code.generate_encoder_table();
+        for token in tokens.iter() {
+            match *token {
+                Token::Literal(byte) => {
+                    let code = literal_table[byte as usize];
+                    bit_writer.write_bits(code.0 as u64, code.1 as u32);
+                }
+                Token::Match { length, distance } => {
+                    let (symbol, extra_bits, extra) = bucket_symbol(length - min_match);
+                    let code = literal_table[(NUM_LITERALS + symbol) as usize];
+                    bit_writer.write_bits(code.0 as u64, code.1 as u32);
+                    bit_writer.write_bits(extra as u64, extra_bits);
+
+                    let (symbol, extra_bits, extra) = bucket_symbol(distance - 1);
+                    let code = distance_table[symbol as usize];
+                    bit_writer.write_bits(code.0 as u64, code.1 as u32);
+                    bit_writer.write_bits(extra as u64, extra_bits);
+                }
+            }
+        }
+    }
+
+    fn encode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        let mut block: Vec<u8> = Vec::with_capacity(self.block_size);
+        let mut next_block: Vec<u8> = Vec::with_capacity(self.block_size);
+        let mut bytes_read = reader
+            .take(self.block_size as u64)
+            .read_to_end(&mut block)?;
+        loop {
+            // Read ahead to find out if this is the last block.
+            next_block.clear();
+            let len = if block.len() < self.block_size {
+                0
+            } else {
+                reader
+                    .take(self.block_size as u64)
+                    .read_to_end(&mut next_block)?
+            };
+            bytes_read += len;
+
+            let last = len == 0;
+            self.encode_block(&block, last, &mut bit_writer);
+            if last {
+                break;
+            }
+            mem::swap(&mut block, &mut next_block);
+        }
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for LzHuffmanEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+// Number of symbols of the literal/length code.
+fn num_literal_symbols(min_match: u32) -> u16 {
+    NUM_LITERALS + num_bucket_symbols(MAX_MATCH as u32 - min_match)
+}
+
+// Number of symbols of the distance code.
+fn num_distance_symbols() -> u16 {
+    num_bucket_symbols(MAX_WINDOW_SIZE as u32 - 1)
+}
+
+// Builds a prefix code for the weights. All symbols may have zero weight.
+fn build_code(weights: &[u32]) -> PrefixCode {
+    let num_symbols = weights.len() as u16;
+    if weights.iter().all(|&weight| weight == 0) {
+        return PrefixCode::new(num_symbols, Vec::new());
+    }
+    LengthLimitedHuffman::new(num_symbols, MAX_CODE_LENGTH).build_from_weights(weights)
+}
+
+/// Decoder for `LzHuffmanEncoder`.
+pub struct LzHuffmanDecoder {}
+
+impl LzHuffmanDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = BitReader::new(reader);
+        let mut block: Vec<u8> = Vec::new();
+        let mut bytes_written = 0;
+        loop {
+            // Read the block header.
+            let last = bit_reader.read_bits(1) == 1;
+            let block_len = bit_reader.read_bits(BLOCK_LENGTH_BITS) as usize;
+            bit_reader.check_status()?;
+            if block_len > MAX_BLOCK_SIZE {
+                return Err(Error::CorruptData("Invalid block length"));
+            }
+
+            if block_len > 0 {
+                block.clear();
+                Self::decode_block(&mut bit_reader, block_len, &mut block)?;
+                writer.write_all(&block)?;
+                bytes_written += block_len;
+            }
+            if last {
+                break;
+            }
+        }
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+
+    fn decode_block(
+        bit_reader: &mut BitReader,
+        block_len: usize,
+        block: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        let min_match = bit_reader.read_bits(MIN_MATCH_BITS) as u32;
+        if !(MIN_MATCH as u32..=MAX_MATCH as u32).contains(&min_match) {
+            return Err(Error::CorruptHeader("Invalid minimum match length"));
+        }
+
+        // Read the coding tables.
+        let literal_code = PrefixCode::decode_coding_table(bit_reader)?;
+        if literal_code.num_symbols != num_literal_symbols(min_match) {
+            return Err(Error::InvalidCodeTable("Invalid coding table"));
+        }
+        let literal_decoder = literal_code.generate_decoder();
+        let has_matches = bit_reader.read_bits(1) == 1;
+        let distance_decoder = if has_matches {
+            let distance_code = PrefixCode::decode_coding_table(bit_reader)?;
+            if distance_code.num_symbols != num_distance_symbols() {
+                return Err(Error::InvalidCodeTable("Invalid coding table"));
+            }
+            Some(distance_code.generate_decoder())
+        } else {
+            None
+        };
+
+        // Read the tokens.
+        while block.len() < block_len {
+            let symbol = literal_decoder.decode(bit_reader)?;
+            if symbol < NUM_LITERALS {
+                block.push(symbol as u8);
+                continue;
+            }
+            let (base, extra_bits) = bucket_base(symbol - NUM_LITERALS);
+            let length = min_match + base + bit_reader.read_bits(extra_bits) as u32;
+
+            let distance_decoder = distance_decoder
+                .as_ref()
+                .ok_or(Error::CorruptData("Unexpected match"))?;
+            let (base, extra_bits) = bucket_base(distance_decoder.decode(bit_reader)?);
+            let distance = base + bit_reader.read_bits(extra_bits) as u32 + 1;
+
+            if block.len() + length as usize > block_len {
+                return Err(Error::CorruptData("Invalid match length"));
+            }
+            copy_match(block, length, distance)?;
+        }
+        bit_reader.check_status()?;
+        Ok(())
+    }
+}
+
+impl Decoder for LzHuffmanDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_decode(input: &[u8], mut encoder: LzHuffmanEncoder) -> usize {
+        let mut encoded = Vec::new();
+        encoder
+            .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+            .unwrap();
+
+        let mut decoded = Vec::new();
+        LzHuffmanDecoder::decode_loop(&mut io::Cursor::new(&encoded), &mut decoded).unwrap();
+        assert_eq!(decoded, input);
+        encoded.len()
+    }
+
+    #[test]
+    fn test_empty() {
+        encode_decode(&[], LzHuffmanEncoder::new());
+    }
+
+    #[test]
+    fn test_literals_only() {
+        encode_decode(b"abcdefg", LzHuffmanEncoder::new());
+        encode_decode(b"a", LzHuffmanEncoder::new());
+    }
+
+    #[test]
+    fn test_repeats() {
+        let text = b"the quick brown fox jumps over the lazy dog. ".repeat(200);
+        let encoded_len = encode_decode(&text, LzHuffmanEncoder::new());
+        assert!(encoded_len < text.len() / 20);
+
+        encode_decode(&text, LzHuffmanEncoder::with_options(1000, 256, 5, 4));
+        encode_decode(
+            &text,
+            LzHuffmanEncoder::with_options(1 << 16, 1 << 20, 258, 64),
+        );
+    }
+
+    #[test]
+    fn test_runs() {
+        let mut input = vec![0; 5000];
+        input.extend((0..5000u32).map(|i| (i / 100) as u8));
+        encode_decode(&input, LzHuffmanEncoder::new());
+    }
+}
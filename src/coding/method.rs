@@ -0,0 +1,286 @@
+use crate::coding::block_huffman_coding::DEFAULT_BLOCK_SIZE;
+use crate::coding::decoder::Decoder;
+use crate::coding::encoder::Encoder;
+use crate::coding::{
+    ArithmeticDecoder, ArithmeticEncoder, BlockHuffmanDecoder, BlockHuffmanEncoder, Bzip2Encoder,
+    ContainerDecoder, ContextDecoder, ContextEncoder, DeflateDecoder, DeflateEncoder,
+    DynamicHuffmanDecoder, DynamicHuffmanEncoder, EntropyCoder, GzipEncoder, LzHuffmanDecoder,
+    LzHuffmanEncoder, MultiTableHuffmanDecoder, MultiTableHuffmanEncoder, PipelineDecoder,
+    PipelineEncoder, RansDecoder, RansEncoder, StaticHuffmanDecoder, StaticHuffmanEncoder,
+    TansDecoder, TansEncoder, ZlibEncoder,
+};
+use crate::transform::{ByteStage, TransformStage};
+
+/// The compression methods, with the IDs that identify them in a frame header.
+///
+/// The IDs must not change, as they are part of the frame format.
+#[allow(clippy::enum_variant_names)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[repr(u8)]
+pub enum CompressionMethod {
+    DynamicHuffmanCoding = 1,
+    StaticHuffmanCoding = 2,
+    ZeroRunHuffmanCoding = 3,
+    RansCoding = 4,
+    TansCoding = 5,
+    ArithmeticCoding = 6,
+    Order1ContextCoding = 7,
+    Order2ContextCoding = 8,
+    MultiTableHuffmanCoding = 9,
+    BlockHuffmanCoding = 10,
+    InterleavedHuffmanCoding = 11,
+    LzHuffmanCoding = 12,
+    DeflateCoding = 13,
+    GzipCoding = 14,
+    ZlibCoding = 15,
+    Bzip2Coding = 16,
+    PipelineCoding = 17,
+    Mtf1ZeroRunHuffmanCoding = 18,
+    Order1DynamicHuffmanCoding = 19,
+    Order2DynamicHuffmanCoding = 20,
+}
+
+impl CompressionMethod {
+    /// All the methods.
+    pub const ALL: [CompressionMethod; 20] = [
+        CompressionMethod::DynamicHuffmanCoding,
+        CompressionMethod::StaticHuffmanCoding,
+        CompressionMethod::ZeroRunHuffmanCoding,
+        CompressionMethod::RansCoding,
+        CompressionMethod::TansCoding,
+        CompressionMethod::ArithmeticCoding,
+        CompressionMethod::Order1ContextCoding,
+        CompressionMethod::Order2ContextCoding,
+        CompressionMethod::MultiTableHuffmanCoding,
+        CompressionMethod::BlockHuffmanCoding,
+        CompressionMethod::InterleavedHuffmanCoding,
+        CompressionMethod::LzHuffmanCoding,
+        CompressionMethod::DeflateCoding,
+        CompressionMethod::GzipCoding,
+        CompressionMethod::ZlibCoding,
+        CompressionMethod::Bzip2Coding,
+        CompressionMethod::PipelineCoding,
+        CompressionMethod::Mtf1ZeroRunHuffmanCoding,
+        CompressionMethod::Order1DynamicHuffmanCoding,
+        CompressionMethod::Order2DynamicHuffmanCoding,
+    ];
+
+    /// Returns the ID of the method.
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Returns the method with `id`, or None if it's unknown.
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|method| method.id() == id)
+    }
+
+    /// Returns the name of the method.
+    pub fn name(self) -> String {
+        CompressionFactories::new().get_method(self).name.clone()
+    }
+
+    /// Create an encoder of the method.
+    pub fn encoder(self) -> Box<dyn Encoder> {
+        (CompressionFactories::new().get_method(self).encoder_factory)()
+    }
+
+    /// Create a decoder of the method.
+    pub fn decoder(self) -> Box<dyn Decoder> {
+        (CompressionFactories::new().get_method(self).decoder_factory)()
+    }
+}
+
+type EncoderFactory = fn() -> Box<dyn Encoder>;
+type DecoderFactory = fn() -> Box<dyn Decoder>;
+
+struct CompressionFactory {
+    name: String,
+    method: CompressionMethod,
+    encoder_factory: EncoderFactory,
+    decoder_factory: DecoderFactory,
+}
+
+struct CompressionFactories {
+    all: Vec<CompressionFactory>,
+}
+
+impl CompressionFactories {
+    fn new() -> Self {
+        let mut instance = Self { all: Vec::new() };
+        instance.all.push(CompressionFactory {
+            name: "DynamicHuffman".to_string(),
+            method: CompressionMethod::DynamicHuffmanCoding,
+            encoder_factory: || Box::new(DynamicHuffmanEncoder::new()),
+            decoder_factory: || Box::new(DynamicHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "StaticHuffman".to_string(),
+            method: CompressionMethod::StaticHuffmanCoding,
+            encoder_factory: || Box::new(StaticHuffmanEncoder::new()),
+            decoder_factory: || Box::new(StaticHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "ZeroRunHuffman".to_string(),
+            method: CompressionMethod::ZeroRunHuffmanCoding,
+            encoder_factory: || {
+                Box::new(StaticHuffmanEncoder::with_transforms(&[
+                    TransformStage::MoveToFront,
+                    TransformStage::ZeroRun,
+                ]))
+            },
+            decoder_factory: || {
+                Box::new(StaticHuffmanDecoder::with_transforms(&[
+                    TransformStage::MoveToFront,
+                    TransformStage::ZeroRun,
+                ]))
+            },
+        });
+        instance.all.push(CompressionFactory {
+            name: "Rans".to_string(),
+            method: CompressionMethod::RansCoding,
+            encoder_factory: || Box::new(RansEncoder::new()),
+            decoder_factory: || Box::new(RansDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Tans".to_string(),
+            method: CompressionMethod::TansCoding,
+            encoder_factory: || Box::new(TansEncoder::new()),
+            decoder_factory: || Box::new(TansDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Arithmetic".to_string(),
+            method: CompressionMethod::ArithmeticCoding,
+            encoder_factory: || Box::new(ArithmeticEncoder::new()),
+            decoder_factory: || Box::new(ArithmeticDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Order1Context".to_string(),
+            method: CompressionMethod::Order1ContextCoding,
+            encoder_factory: || Box::new(ContextEncoder::new(1)),
+            decoder_factory: || Box::new(ContextDecoder::new(1)),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Order2Context".to_string(),
+            method: CompressionMethod::Order2ContextCoding,
+            encoder_factory: || Box::new(ContextEncoder::new(2)),
+            decoder_factory: || Box::new(ContextDecoder::new(2)),
+        });
+        instance.all.push(CompressionFactory {
+            name: "MultiTableHuffman".to_string(),
+            method: CompressionMethod::MultiTableHuffmanCoding,
+            encoder_factory: || Box::new(MultiTableHuffmanEncoder::new()),
+            decoder_factory: || Box::new(MultiTableHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "BlockHuffman".to_string(),
+            method: CompressionMethod::BlockHuffmanCoding,
+            encoder_factory: || Box::new(BlockHuffmanEncoder::new()),
+            decoder_factory: || Box::new(BlockHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "InterleavedHuffman".to_string(),
+            method: CompressionMethod::InterleavedHuffmanCoding,
+            encoder_factory: || {
+                Box::new(BlockHuffmanEncoder::with_interleaved_streams(
+                    DEFAULT_BLOCK_SIZE,
+                ))
+            },
+            decoder_factory: || Box::new(BlockHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "LzHuffman".to_string(),
+            method: CompressionMethod::LzHuffmanCoding,
+            encoder_factory: || Box::new(LzHuffmanEncoder::new()),
+            decoder_factory: || Box::new(LzHuffmanDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Deflate".to_string(),
+            method: CompressionMethod::DeflateCoding,
+            encoder_factory: || Box::new(DeflateEncoder::new()),
+            decoder_factory: || Box::new(DeflateDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Gzip".to_string(),
+            method: CompressionMethod::GzipCoding,
+            encoder_factory: || Box::new(GzipEncoder::new()),
+            decoder_factory: || Box::new(ContainerDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Zlib".to_string(),
+            method: CompressionMethod::ZlibCoding,
+            encoder_factory: || Box::new(ZlibEncoder::new()),
+            decoder_factory: || Box::new(ContainerDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Bzip2".to_string(),
+            method: CompressionMethod::Bzip2Coding,
+            encoder_factory: || Box::new(Bzip2Encoder::new()),
+            decoder_factory: || Box::new(ContainerDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Pipeline".to_string(),
+            method: CompressionMethod::PipelineCoding,
+            encoder_factory: || {
+                Box::new(PipelineEncoder::new(
+                    &[ByteStage::RunLength],
+                    EntropyCoder::StaticHuffman,
+                ))
+            },
+            decoder_factory: || Box::new(PipelineDecoder::new()),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Mtf1ZeroRunHuffman".to_string(),
+            method: CompressionMethod::Mtf1ZeroRunHuffmanCoding,
+            encoder_factory: || {
+                Box::new(StaticHuffmanEncoder::with_transforms(&[
+                    TransformStage::MoveToFront1,
+                    TransformStage::ZeroRun,
+                ]))
+            },
+            decoder_factory: || {
+                Box::new(StaticHuffmanDecoder::with_transforms(&[
+                    TransformStage::MoveToFront1,
+                    TransformStage::ZeroRun,
+                ]))
+            },
+        });
+        instance.all.push(CompressionFactory {
+            name: "Order1DynamicHuffman".to_string(),
+            method: CompressionMethod::Order1DynamicHuffmanCoding,
+            encoder_factory: || Box::new(DynamicHuffmanEncoder::with_context_order(1)),
+            decoder_factory: || Box::new(DynamicHuffmanDecoder::with_context_order(1)),
+        });
+        instance.all.push(CompressionFactory {
+            name: "Order2DynamicHuffman".to_string(),
+            method: CompressionMethod::Order2DynamicHuffmanCoding,
+            encoder_factory: || Box::new(DynamicHuffmanEncoder::with_context_order(2)),
+            decoder_factory: || Box::new(DynamicHuffmanDecoder::with_context_order(2)),
+        });
+        instance
+    }
+
+    fn get_method(&self, method: CompressionMethod) -> &CompressionFactory {
+        for factory in self.all.iter() {
+            if factory.method == method {
+                return factory;
+            }
+        }
+        panic!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        for method in CompressionMethod::ALL {
+            assert_eq!(CompressionMethod::from_id(method.id()), Some(method));
+            assert!(!method.name().is_empty());
+        }
+        assert_eq!(CompressionMethod::from_id(0), None);
+        assert_eq!(CompressionMethod::DynamicHuffmanCoding.id(), 1);
+    }
+}
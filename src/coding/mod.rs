@@ -1,11 +1,50 @@
+mod arithmetic_coding;
+mod block_huffman_coding;
+mod bzip2_coding;
+mod container_decoder;
+mod context_coding;
 mod decoder;
+mod deflate_coding;
 mod dynamic_huffman_coding;
 mod encoder;
+mod frame;
+mod gzip_coding;
 mod input;
+mod lz_huffman_coding;
+mod method;
+mod multi_table_huffman_coding;
 mod output;
+mod pipeline_coding;
+mod rans_coding;
 mod static_huffman_coding;
-mod tester;
+mod stream;
+mod tans_coding;
+#[cfg(test)]
+mod test_util;
+mod zlib_coding;
 
+pub use arithmetic_coding::{ArithmeticDecoder, ArithmeticEncoder};
+pub use block_huffman_coding::{BlockHuffmanDecoder, BlockHuffmanEncoder};
+pub use bzip2_coding::Bzip2Encoder;
+pub use container_decoder::ContainerDecoder;
+pub use context_coding::{ContextDecoder, ContextEncoder};
+pub use decoder::{DecodeResult, Decoder};
+pub use deflate_coding::{DeflateDecoder, DeflateEncoder};
 pub use dynamic_huffman_coding::{DynamicHuffmanDecoder, DynamicHuffmanEncoder};
+pub use encoder::{EncodeResult, Encoder};
+pub use frame::{
+    decompress, FrameChecksum, FrameDecoder, FrameEncoder, FrameHeader, FrameOptions, FRAME_MAGIC,
+    FRAME_VERSION,
+};
+pub use gzip_coding::GzipEncoder;
+pub use input::InputSource;
+pub use lz_huffman_coding::{LzHuffmanDecoder, LzHuffmanEncoder};
+pub use method::CompressionMethod;
+pub use multi_table_huffman_coding::{MultiTableHuffmanDecoder, MultiTableHuffmanEncoder};
+pub use output::OutputSink;
+pub use pipeline_coding::{EntropyCoder, PipelineDecoder, PipelineEncoder};
+pub use rans_coding::{RansDecoder, RansEncoder};
 pub use static_huffman_coding::{StaticHuffmanDecoder, StaticHuffmanEncoder};
-pub use tester::{CompressionMethod, Tester};
+pub use stream::{CompressWriter, DecompressReader, STREAM_BLOCK_SIZE};
+pub use tans_coding::{TansDecoder, TansEncoder};
+pub use zlib_coding::ZlibEncoder;
@@ -0,0 +1,242 @@
+use std::io;
+use std::io::Read;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+use crate::huffman::{MultiTableHuffman, PrefixCode, PrefixDecoder};
+
+const NUM_SYMBOLS: u16 = 256;
+
+// Number of input bytes in each block.
+const BLOCK_SIZE: usize = 256 * 1024;
+
+// Number of symbols coded with the same table.
+const GROUP_SIZE: usize = 50;
+
+// Maximum code length.
+const MAX_CODE_LENGTH: usize = 17;
+
+// Maximum number of tables in a block.
+const MAX_TABLES: usize = 6;
+
+// Number of bits to store the number of tables.
+const NUM_TABLES_BITS: u32 = 3;
+
+// Number of bits to store the block length.
+const BLOCK_LENGTH_BITS: u32 = 32;
+
+// Choose the number of tables for a block. Small blocks can't afford many tables.
+fn choose_num_tables(block_len: usize) -> usize {
+    match block_len {
+        0..=199 => 2,
+        200..=599 => 3,
+        600..=1199 => 4,
+        1200..=2399 => 5,
+        _ => MAX_TABLES,
+    }
+}
+
+// Encode the selectors. Each selector is move-to-front transformed, and the position is written
+// in unary.
+fn encode_selectors(selectors: &[u8], num_tables: usize, bit_writer: &mut BitWriter) {
+    let mut mtf: Vec<u8> = (0..num_tables as u8).collect();
+    for &selector in selectors.iter() {
+        let pos = mtf.iter().position(|&t| t == selector).unwrap();
+        mtf[..=pos].rotate_right(1);
+        bit_writer.write_bits((1 << (pos + 1)) - 2, pos as u32 + 1);
+    }
+}
+
+// Decode the selectors written by `encode_selectors`.
+fn decode_selectors(
+    num_selectors: usize,
+    num_tables: usize,
+    bit_reader: &mut BitReader,
+) -> Result<Vec<u8>, Error> {
+    let mut mtf: Vec<u8> = (0..num_tables as u8).collect();
+    let mut selectors: Vec<u8> = Vec::with_capacity(num_selectors);
+    for _ in 0..num_selectors {
+        let mut pos = 0;
+        while bit_reader.read_bits(1) == 1 {
+            pos += 1;
+            if pos == num_tables {
+                return Err(Error::CorruptData("Invalid selector"));
+            }
+        }
+        mtf[..=pos].rotate_right(1);
+        selectors.push(mtf[0]);
+    }
+    Ok(selectors)
+}
+
+/// Static Huffman encoder that codes each group of symbols in a block with one of several tables.
+pub struct MultiTableHuffmanEncoder {
+    huffman: MultiTableHuffman,
+}
+
+impl MultiTableHuffmanEncoder {
+    pub fn new() -> Self {
+        Self {
+            huffman: MultiTableHuffman::new(NUM_SYMBOLS, MAX_CODE_LENGTH, GROUP_SIZE),
+        }
+    }
+
+    fn encode_block(huffman: &MultiTableHuffman, block: &[u8], bit_writer: &mut BitWriter) {
+        let symbols: Vec<u16> = block.iter().map(|&byte| byte as u16).collect();
+        let num_tables = choose_num_tables(block.len());
+        let code = huffman.build(&symbols, num_tables);
+
+        // Write the block header and the tables.
+        bit_writer.write_bits(block.len() as u64, BLOCK_LENGTH_BITS);
+        bit_writer.write_bits(num_tables as u64, NUM_TABLES_BITS);
+        for table in code.tables.iter() {
+            table.encode_coding_table(bit_writer);
+        }
+        encode_selectors(&code.selectors, num_tables, bit_writer);
+
+        // Write the symbols.
+        let encoder_tables: Vec<Vec<(u32, u8)>> = code
+            .tables
+            .iter()
+            .map(|table| table.generate_encoder_table())
+            .collect();
+        for (group, &selector) in block.chunks(GROUP_SIZE).zip(code.selectors.iter()) {
+            let encoder_table = &encoder_tables[selector as usize];
+            for &symbol in group.iter() {
+                let code = encoder_table[symbol as usize];
+                bit_writer.write_bits(code.0 as u64, code.1 as u32);
+            }
+        }
+    }
+
+    fn encode_loop(
+        huffman: &MultiTableHuffman,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        let mut block: Vec<u8> = Vec::with_capacity(BLOCK_SIZE);
+        let mut bytes_read = 0;
+        loop {
+            block.clear();
+            let len = (&mut *reader)
+                .take(BLOCK_SIZE as u64)
+                .read_to_end(&mut block)?;
+            if len == 0 {
+                break;
+            }
+            bytes_read += len;
+            Self::encode_block(huffman, &block, &mut bit_writer);
+        }
+
+        // Terminator.
+        bit_writer.write_bits(0, BLOCK_LENGTH_BITS);
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for MultiTableHuffmanEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::encode_loop(&self.huffman, &mut reader, &mut writer)
+    }
+}
+
+/// Decoder for `MultiTableHuffmanEncoder`.
+pub struct MultiTableHuffmanDecoder {}
+
+impl MultiTableHuffmanDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = BitReader::new(reader);
+        let mut block: Vec<u8> = Vec::with_capacity(BLOCK_SIZE);
+        let mut bytes_written = 0;
+        loop {
+            let block_len = bit_reader.read_bits(BLOCK_LENGTH_BITS) as usize;
+            if block_len == 0 {
+                break;
+            }
+            if block_len > BLOCK_SIZE {
+                return Err(Error::CorruptData("Invalid block length"));
+            }
+
+            // Read the tables.
+            let num_tables = bit_reader.read_bits(NUM_TABLES_BITS) as usize;
+            if num_tables == 0 || num_tables > MAX_TABLES {
+                return Err(Error::CorruptHeader("Invalid number of tables"));
+            }
+            let mut decoders: Vec<PrefixDecoder> = Vec::with_capacity(num_tables);
+            for _ in 0..num_tables {
+                let prefix_code = PrefixCode::decode_coding_table(&mut bit_reader)?;
+                decoders.push(prefix_code.generate_decoder());
+            }
+            let num_selectors = block_len.div_ceil(GROUP_SIZE);
+            let selectors = decode_selectors(num_selectors, num_tables, &mut bit_reader)?;
+
+            // Read the symbols.
+            block.clear();
+            for (i, &selector) in selectors.iter().enumerate() {
+                let decoder = &decoders[selector as usize];
+                let group_len = GROUP_SIZE.min(block_len - i * GROUP_SIZE);
+                for _ in 0..group_len {
+                    block.push(decoder.decode(&mut bit_reader)? as u8);
+                }
+            }
+            bit_reader.check_status()?;
+            writer.write_all(&block)?;
+            bytes_written += block.len();
+        }
+        bit_reader.check_status()?;
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for MultiTableHuffmanDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_selectors() {
+        let selectors: Vec<u8> = vec![0, 0, 1, 2, 1, 5, 5, 0, 3, 4];
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut writer = BitWriter::new(&mut cursor);
+        encode_selectors(&selectors, MAX_TABLES, &mut writer);
+        writer.finish().unwrap();
+
+        let mut cursor = io::Cursor::new(cursor.into_inner());
+        let mut reader = BitReader::new(&mut cursor);
+        let decoded = decode_selectors(selectors.len(), MAX_TABLES, &mut reader).unwrap();
+        assert_eq!(decoded, selectors);
+    }
+}
@@ -1,9 +1,9 @@
+use std::cell::RefCell;
 use std::fmt;
 use std::fs::File;
 use std::io;
 use std::io::Write;
 use std::rc::Rc;
-use std::cell::RefCell;
 
 struct MemWriter {
     data: Rc<RefCell<Vec<u8>>>,
@@ -26,60 +26,72 @@ impl io::Write for MemWriter {
     }
 }
 
-enum OutputSinkType {
-    File,
-    Memory,
+enum OutputSinkType<'a> {
+    File(String),
+    Memory(Rc<RefCell<Vec<u8>>>),
+    Writer(Box<dyn Write + 'a>),
+    Stdout,
 }
 
-pub struct OutputSink {
-    sink_type: OutputSinkType,
-
-    filename: String,
-
-    // Output bytes for memory sink.
-    data: Rc<RefCell<Vec<u8>>>,
+/// The output of an encoder or decoder.
+///
+/// Each call to `writer()` of a file sink starts a new file. The other sinks append to the
+/// output.
+pub struct OutputSink<'a> {
+    sink_type: OutputSinkType<'a>,
 }
 
-impl OutputSink {
+impl<'a> OutputSink<'a> {
     pub fn file(filename: &str) -> Self {
         Self {
-            sink_type: OutputSinkType::File,
-            filename: filename.to_string(),
-            data: Default::default(),
+            sink_type: OutputSinkType::File(filename.to_string()),
         }
     }
 
     pub fn memory(data: Vec<u8>) -> Self {
         Self {
-            sink_type: OutputSinkType::Memory,
-            filename: String::new(),
-            data: Rc::new(RefCell::new(data)),
+            sink_type: OutputSinkType::Memory(Rc::new(RefCell::new(data))),
         }
     }
 
-    pub fn writer(&mut self) -> Box<dyn Write> {
-        match &self.sink_type {
-            OutputSinkType::File => {
-                let file = File::create(&self.filename).unwrap();
-                Box::new(file)
-            }
-            OutputSinkType::Memory => {
-                let writer = MemWriter::new(self.data.clone());
-                Box::new(writer)
-            }
+    /// Create a sink that writes to `writer`, e.g. a borrowed `Vec<u8>`.
+    pub fn from_writer(writer: Box<dyn Write + 'a>) -> Self {
+        Self {
+            sink_type: OutputSinkType::Writer(writer),
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self {
+            sink_type: OutputSinkType::Stdout,
+        }
+    }
+
+    pub fn writer(&mut self) -> io::Result<Box<dyn Write + '_>> {
+        match &mut self.sink_type {
+            OutputSinkType::File(filename) => Ok(Box::new(File::create(&*filename)?)),
+            OutputSinkType::Memory(data) => Ok(Box::new(MemWriter::new(data.clone()))),
+            OutputSinkType::Writer(writer) => Ok(Box::new(writer.as_mut())),
+            OutputSinkType::Stdout => Ok(Box::new(io::stdout().lock())),
         }
     }
 
+    /// Returns the data of a memory sink, which is empty for other sinks.
     pub fn take_memory(&mut self) -> Vec<u8> {
-        self.data.take()
+        match &self.sink_type {
+            OutputSinkType::Memory(data) => data.take(),
+            _ => Vec::new(),
+        }
     }
 }
 
-impl fmt::Display for OutputSink {
+impl fmt::Display for OutputSink<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.sink_type {
-            OutputSinkType::File => write!(f, "Output file: {}", self.filename),
-            OutputSinkType::Memory => write!(f, "Output memory"),
+            OutputSinkType::File(filename) => write!(f, "Output file: {}", filename),
+            OutputSinkType::Memory(_) => write!(f, "Output memory"),
+            OutputSinkType::Writer(_) => write!(f, "Output writer"),
+            OutputSinkType::Stdout => write!(f, "Output stdout"),
         }
     }
 }
@@ -0,0 +1,261 @@
+use std::io::Read;
+
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::coding::{
+    ArithmeticDecoder, ArithmeticEncoder, DynamicHuffmanDecoder, DynamicHuffmanEncoder,
+    RansDecoder, RansEncoder, StaticHuffmanDecoder, StaticHuffmanEncoder, TansDecoder, TansEncoder,
+};
+use crate::error::Error;
+use crate::transform::ByteStage;
+
+// Largest number of byte stages in a pipeline.
+const MAX_STAGES: usize = 16;
+
+/// The entropy coders that can end a pipeline, with the IDs that identify them in the header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EntropyCoder {
+    StaticHuffman,
+    DynamicHuffman,
+    Arithmetic,
+    Rans,
+    Tans,
+}
+
+impl EntropyCoder {
+    /// Returns the ID of the coder.
+    pub fn id(self) -> u8 {
+        match self {
+            EntropyCoder::StaticHuffman => 1,
+            EntropyCoder::DynamicHuffman => 2,
+            EntropyCoder::Arithmetic => 3,
+            EntropyCoder::Rans => 4,
+            EntropyCoder::Tans => 5,
+        }
+    }
+
+    /// Returns the coder with `id`, or None if it's unknown.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(EntropyCoder::StaticHuffman),
+            2 => Some(EntropyCoder::DynamicHuffman),
+            3 => Some(EntropyCoder::Arithmetic),
+            4 => Some(EntropyCoder::Rans),
+            5 => Some(EntropyCoder::Tans),
+            _ => None,
+        }
+    }
+
+    fn encoder(self) -> Box<dyn Encoder> {
+        match self {
+            EntropyCoder::StaticHuffman => Box::new(StaticHuffmanEncoder::new()),
+            EntropyCoder::DynamicHuffman => Box::new(DynamicHuffmanEncoder::new()),
+            EntropyCoder::Arithmetic => Box::new(ArithmeticEncoder::new()),
+            EntropyCoder::Rans => Box::new(RansEncoder::new()),
+            EntropyCoder::Tans => Box::new(TansEncoder::new()),
+        }
+    }
+
+    fn decoder(self) -> Box<dyn Decoder> {
+        match self {
+            EntropyCoder::StaticHuffman => Box::new(StaticHuffmanDecoder::new()),
+            EntropyCoder::DynamicHuffman => Box::new(DynamicHuffmanDecoder::new()),
+            EntropyCoder::Arithmetic => Box::new(ArithmeticDecoder::new()),
+            EntropyCoder::Rans => Box::new(RansDecoder::new()),
+            EntropyCoder::Tans => Box::new(TansDecoder::new()),
+        }
+    }
+}
+
+/// Encoder of a pipeline: byte transforms applied in order, followed by an entropy coder.
+///
+/// The header lists the stages and the coder, so that `PipelineDecoder` can invert the same
+/// pipeline: the number of stages, the ID of each stage, and the ID of the coder, one byte
+/// each. The output of the coder follows.
+pub struct PipelineEncoder {
+    stages: Vec<ByteStage>,
+    coder: EntropyCoder,
+}
+
+impl PipelineEncoder {
+    pub fn new(stages: &[ByteStage], coder: EntropyCoder) -> Self {
+        assert!(stages.len() <= MAX_STAGES);
+        Self {
+            stages: stages.to_vec(),
+            coder,
+        }
+    }
+
+    fn header(&self) -> Vec<u8> {
+        let mut header: Vec<u8> = Vec::with_capacity(self.stages.len() + 2);
+        header.push(self.stages.len() as u8);
+        header.extend(self.stages.iter().map(|stage| stage.id()));
+        header.push(self.coder.id());
+        header
+    }
+}
+
+impl Encoder for PipelineEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut data: Vec<u8> = Vec::new();
+        let bytes_read = input.reader()?.read_to_end(&mut data)?;
+        for stage in self.stages.iter() {
+            let mut transform = stage.transform();
+            let mut transformed: Vec<u8> = Vec::with_capacity(data.len());
+            transform.encode(&data, &mut transformed);
+            transform.finish_encode(&mut transformed);
+            data = transformed;
+        }
+
+        let mut coded = OutputSink::memory(Vec::new());
+        self.coder
+            .encoder()
+            .encode(&mut InputSource::memory(data), &mut coded)?;
+        let coded = coded.take_memory();
+
+        let header = self.header();
+        let mut writer = output.writer()?;
+        writer.write_all(&header)?;
+        writer.write_all(&coded)?;
+
+        Ok(EncodeResult::new(bytes_read, header.len() + coded.len()))
+    }
+}
+
+/// Decoder of `PipelineEncoder` output, which inverts the pipeline given in the header.
+pub struct PipelineDecoder {}
+
+impl PipelineDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // Reads the header. Returns the stages and the coder.
+    fn read_header(reader: &mut dyn Read) -> Result<(Vec<ByteStage>, EntropyCoder), Error> {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        let num_stages = byte[0] as usize;
+        if num_stages > MAX_STAGES {
+            return Err(Error::CorruptHeader("Too many pipeline stages"));
+        }
+        let mut ids = vec![0; num_stages + 1];
+        reader.read_exact(&mut ids)?;
+        let stages: Vec<ByteStage> = ids[..num_stages]
+            .iter()
+            .map(|&id| ByteStage::from_id(id).ok_or(Error::Unsupported("Unknown pipeline stage")))
+            .collect::<Result<_, _>>()?;
+        let coder = EntropyCoder::from_id(ids[num_stages])
+            .ok_or(Error::Unsupported("Unknown entropy coder"))?;
+        Ok((stages, coder))
+    }
+}
+
+impl Decoder for PipelineDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let (stages, coder) = Self::read_header(&mut reader)?;
+        let mut coded: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut coded)?;
+        let bytes_read = stages.len() + 2 + coded.len();
+
+        let mut decoded = OutputSink::memory(Vec::new());
+        coder
+            .decoder()
+            .decode(&mut InputSource::memory(coded), &mut decoded)?;
+        let mut data = decoded.take_memory();
+        for stage in stages.iter().rev() {
+            let mut transform = stage.transform();
+            let mut transformed: Vec<u8> = Vec::with_capacity(data.len());
+            transform.decode(&data, &mut transformed)?;
+            transform.finish_decode(&mut transformed)?;
+            data = transformed;
+        }
+
+        output.writer()?.write_all(&data)?;
+        Ok(DecodeResult::new(bytes_read, data.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn encode(input: &[u8], stages: &[ByteStage], coder: EntropyCoder) -> Vec<u8> {
+        let mut output = OutputSink::memory(Vec::new());
+        PipelineEncoder::new(stages, coder)
+            .encode(&mut InputSource::memory(input.to_vec()), &mut output)
+            .unwrap();
+        output.take_memory()
+    }
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = OutputSink::memory(Vec::new());
+        PipelineDecoder::new().decode(&mut InputSource::memory(encoded.to_vec()), &mut output)?;
+        Ok(output.take_memory())
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        // Slowly rising values with long runs.
+        let ramp: Vec<u8> = (0..20000).map(|i| (i / 100) as u8).collect();
+        for stages in [
+            &[][..],
+            &[ByteStage::Delta],
+            &[ByteStage::RunLength],
+            &[ByteStage::Delta, ByteStage::RunLength],
+        ] {
+            for coder in [EntropyCoder::StaticHuffman, EntropyCoder::DynamicHuffman] {
+                for input in [&b"a"[..], TEXT, &ramp] {
+                    let encoded = encode(input, stages, coder);
+                    assert_eq!(encoded[0] as usize, stages.len());
+                    assert_eq!(decode(&encoded).unwrap(), input);
+                }
+            }
+        }
+        assert_eq!(
+            decode(&encode(
+                b"",
+                &[ByteStage::Delta],
+                EntropyCoder::DynamicHuffman
+            ))
+            .unwrap(),
+            b""
+        );
+
+        // The stages make the ramp much smaller.
+        let plain = encode(&ramp, &[], EntropyCoder::StaticHuffman);
+        let transformed = encode(
+            &ramp,
+            &[ByteStage::Delta, ByteStage::RunLength],
+            EntropyCoder::StaticHuffman,
+        );
+        assert!(transformed.len() * 10 < plain.len());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let encoded = encode(TEXT, &[ByteStage::Delta], EntropyCoder::DynamicHuffman);
+        assert!(decode(&encoded[..2]).is_err());
+
+        let mut unknown_stage = encoded.clone();
+        unknown_stage[1] = 0xff;
+        assert!(decode(&unknown_stage).is_err());
+
+        let mut unknown_coder = encoded.clone();
+        unknown_coder[2] = 0xff;
+        assert!(decode(&unknown_coder).is_err());
+        assert!(decode(&[200]).is_err());
+    }
+}
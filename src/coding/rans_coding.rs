@@ -0,0 +1,157 @@
+use std::io;
+
+use crate::ans::{normalize_frequencies, Rans, RANS_SCALE_BITS};
+use crate::bits::{ReverseBitReader, ReverseBitWriter};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+
+const NUM_SYMBOLS: usize = 256;
+
+// Output buffer size.
+const WRITE_BUFFER_SIZE: usize = 8 * 1024;
+
+// Encode (i.e. serialize) the normalized frequencies.
+// Each symbol is stored as a present bit, followed by (frequency - 1) if present.
+// Written in reverse order, so that the decoder reads them in symbol order.
+fn encode_frequencies(frequencies: &[u32], bit_writer: &mut ReverseBitWriter) {
+    for &frequency in frequencies.iter().rev() {
+        if frequency > 0 {
+            bit_writer.write_bits((frequency - 1) as u64, RANS_SCALE_BITS);
+            bit_writer.write_bits(1, 1);
+        } else {
+            bit_writer.write_bits(0, 1);
+        }
+    }
+}
+
+// Decode (i.e. deserialize) the normalized frequencies.
+fn decode_frequencies(bit_reader: &mut ReverseBitReader) -> Result<Vec<u32>, Error> {
+    let mut frequencies: Vec<u32> = Vec::with_capacity(NUM_SYMBOLS);
+    let mut total = 0;
+    for _ in 0..NUM_SYMBOLS {
+        let frequency = if bit_reader.read_bits(1) == 1 {
+            bit_reader.read_bits(RANS_SCALE_BITS) as u32 + 1
+        } else {
+            0
+        };
+        total += frequency;
+        frequencies.push(frequency);
+    }
+    if total != 1 << RANS_SCALE_BITS {
+        return Err(Error::InvalidCodeTable("Invalid frequency table"));
+    }
+    Ok(frequencies)
+}
+
+pub struct RansEncoder {}
+
+impl RansEncoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn encode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        // rANS encodes in reverse, so the whole input is needed.
+        let mut input: Vec<u8> = Vec::new();
+        let bytes_read = reader.read_to_end(&mut input)?;
+
+        let mut frequencies = vec![0; NUM_SYMBOLS];
+        for &byte in input.iter() {
+            frequencies[byte as usize] += 1;
+        }
+
+        let mut bit_writer = ReverseBitWriter::new(writer);
+        if !input.is_empty() {
+            let normalized = normalize_frequencies(&frequencies, RANS_SCALE_BITS);
+            let rans = Rans::new(&normalized);
+
+            // Encode the symbols backwards.
+            let mut state = rans.initial_state();
+            for &symbol in input.iter().rev() {
+                rans.encode(&mut state, symbol as u16, &mut bit_writer);
+            }
+            Rans::write_state(state, &mut bit_writer);
+
+            // Write the coding table.
+            encode_frequencies(&normalized, &mut bit_writer);
+        }
+
+        // Write out the input length. This is read first by the decoder.
+        bit_writer.write_bits(bytes_read as u64, 64);
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for RansEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::encode_loop(&mut reader, &mut writer)
+    }
+}
+
+pub struct RansDecoder {}
+
+impl RansDecoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn decode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = ReverseBitReader::new(reader);
+        let input_len = bit_reader.read_bits(64);
+
+        let mut buffer: Box<[u8; WRITE_BUFFER_SIZE]> = Box::new([0; WRITE_BUFFER_SIZE]);
+        let mut bytes_written = 0;
+        let mut buffer_pos = 0;
+        if input_len > 0 {
+            let frequencies = decode_frequencies(&mut bit_reader)?;
+            let rans = Rans::new(&frequencies);
+            let mut state = Rans::read_state(&mut bit_reader)?;
+
+            for _ in 0..input_len {
+                buffer[buffer_pos] = rans.decode(&mut state, &mut bit_reader) as u8;
+                buffer_pos += 1;
+                if buffer_pos == WRITE_BUFFER_SIZE {
+                    bit_reader.check_status()?;
+                    writer.write_all(buffer.as_ref())?;
+                    buffer_pos = 0;
+                    bytes_written += WRITE_BUFFER_SIZE;
+                }
+            }
+        }
+        bit_reader.check_status()?;
+        let bytes_read = bit_reader.finish()?;
+        writer.write_all(&buffer[0..buffer_pos])?;
+        bytes_written += buffer_pos;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for RansDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::decode_loop(&mut reader, &mut writer)
+    }
+}
@@ -1,65 +1,182 @@
-use std::error::Error;
 use std::io;
+use std::io::Read;
 
 use crate::bits::{BitReader, BitWriter};
 use crate::coding::decoder::{DecodeResult, Decoder};
 use crate::coding::encoder::{EncodeResult, Encoder};
 use crate::coding::input::InputSource;
 use crate::coding::output::OutputSink;
+use crate::error::Error;
 use crate::huffman::{PrefixCode, StaticHuffman};
+use crate::transform::{TransformChain, TransformStage};
 
 const NUM_SYMBOLS: u16 = 256;
 
 // Input buffer size.
 const READ_BUFFER_SIZE: usize = 8 * 1024;
 
+// Number of symbols in the header of the streaming layout, in place of the number of symbols.
+const STREAMING_LAYOUT: u64 = u64::MAX;
+
+// Size of the chunks of the streaming layout.
+const STREAMING_CHUNK_SIZE: u64 = 1 << 20;
+
 pub struct StaticHuffmanEncoder {
     huffman: StaticHuffman,
+    transforms: TransformChain,
 }
 
 impl StaticHuffmanEncoder {
     pub fn new() -> Self {
+        Self::with_transforms(&[])
+    }
+
+    /// Create an instance that applies the transform `stages` to the bytes before coding.
+    pub fn with_transforms(stages: &[TransformStage]) -> Self {
+        let transforms = TransformChain::new(stages, NUM_SYMBOLS as usize);
         Self {
-            huffman: StaticHuffman::new(NUM_SYMBOLS),
+            huffman: StaticHuffman::new(transforms.num_output_symbols() as u16),
+            transforms,
         }
     }
 
+    // Returns the number of symbols output by the transforms, and their frequencies.
+    fn transformed_frequencies(
+        &mut self,
+        reader: &mut dyn io::Read,
+    ) -> Result<(u64, Vec<u32>), Error> {
+        let mut frequencies: Vec<u32> = vec![0; self.transforms.num_output_symbols()];
+        let mut input_buf: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut symbols: Vec<u16> = Vec::new();
+        let mut num_symbols = 0;
+        self.transforms.reset();
+        loop {
+            let len = self.read_symbols(reader, input_buf.as_mut_slice(), &mut symbols)?;
+            num_symbols += symbols.len() as u64;
+            for &symbol in symbols.iter() {
+                frequencies[symbol as usize] += 1;
+            }
+            if len == 0 {
+                break;
+            }
+        }
+        Ok((num_symbols, frequencies))
+    }
+
+    // Reads the next bytes into `buf` and transforms them into `symbols`. Returns the number of
+    // bytes read. At the end of the input, this returns 0 with the last symbols of the transforms.
+    fn read_symbols(
+        &mut self,
+        reader: &mut dyn io::Read,
+        buf: &mut [u8],
+        symbols: &mut Vec<u16>,
+    ) -> io::Result<usize> {
+        symbols.clear();
+        let len = reader.read(buf)?;
+        if len == 0 {
+            self.transforms.finish_encode(symbols);
+        } else {
+            let bytes: Vec<u16> = buf[..len].iter().map(|&byte| byte as u16).collect();
+            self.transforms.encode(&bytes, symbols);
+        }
+        Ok(len)
+    }
+
     fn encode_loop(
-        huffman: &mut StaticHuffman,
+        &mut self,
+        num_symbols: u64,
         input_length: u64,
         frequencies: Vec<u32>,
         reader: &mut dyn io::Read,
         writer: &mut dyn io::Write,
-    ) -> Result<EncodeResult, Box<dyn Error>> {
-        let mut prefix_code = huffman.build_from_weights(&frequencies);
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        let bytes_read = self.encode_block(
+            num_symbols,
+            input_length,
+            &frequencies,
+            reader,
+            &mut bit_writer,
+        )?;
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+
+    // Encodes input of unknown length in one pass, in chunks with their own coding table. The
+    // header has `STREAMING_LAYOUT` in place of the number of symbols, and each chunk has the
+    // layout of a whole stream. A chunk of 0 symbols ends the stream.
+    fn encode_streaming(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        let mut bit_writer = BitWriter::new(writer);
+        bit_writer.write_bits(STREAMING_LAYOUT, 64);
+        let mut chunk: Vec<u8> = Vec::new();
+        let mut bytes_read = 0;
+        loop {
+            chunk.clear();
+            reader.take(STREAMING_CHUNK_SIZE).read_to_end(&mut chunk)?;
+            if chunk.is_empty() {
+                break;
+            }
+            let (num_symbols, frequencies) = self.transformed_frequencies(&mut &chunk[..])?;
+            bytes_read += self.encode_block(
+                num_symbols,
+                chunk.len() as u64,
+                &frequencies,
+                &mut &chunk[..],
+                &mut bit_writer,
+            )?;
+        }
+        bit_writer.write_bits(0, 64);
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+
+    // Writes the number of symbols, the coding table and the symbols of the `input_length` bytes
+    // of input from `reader`. With transforms, the number of symbols is followed by the input
+    // length. Returns the number of bytes read.
+    fn encode_block(
+        &mut self,
+        num_symbols: u64,
+        input_length: u64,
+        frequencies: &[u32],
+        reader: &mut dyn io::Read,
+        bit_writer: &mut BitWriter,
+    ) -> Result<usize, Error> {
+        let mut prefix_code = self.huffman.build_from_weights(frequencies);
         prefix_code.apply_max_length_limit(32);
         let encoder_table = prefix_code.generate_encoder_table();
 
-        // Write out the input length.
-        let mut bit_writer = BitWriter::new(writer);
-        bit_writer.write_bits(input_length, 64);
+        // Write out the number of symbols, and the input length if it's different.
+        bit_writer.write_bits(num_symbols, 64);
+        if !self.transforms.is_empty() {
+            bit_writer.write_bits(input_length, 64);
+        }
 
         // Write the coding table.
-        prefix_code.encode_coding_table(&mut bit_writer);
+        prefix_code.encode_coding_table(bit_writer);
 
         let mut input_buf: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut symbols: Vec<u16> = Vec::with_capacity(READ_BUFFER_SIZE);
         let mut bytes_read = 0;
+        self.transforms.reset();
         loop {
-            let len = reader.read(input_buf.as_mut_slice())?;
-            if len == 0 {
-                break;
-            }
+            let len = self.read_symbols(reader, input_buf.as_mut_slice(), &mut symbols)?;
             bytes_read += len;
-            for &symbol in input_buf[0..len].iter() {
+            for &symbol in symbols.iter() {
                 let code = encoder_table[symbol as usize];
                 bit_writer.write_bits(code.0 as u64, code.1 as u32);
             }
+            if len == 0 {
+                break;
+            }
         }
-        let bytes_written = bit_writer.finish();
-
-        Ok(EncodeResult::new(bytes_read, bytes_written))
+        Ok(bytes_read)
     }
-
 }
 
 impl Encoder for StaticHuffmanEncoder {
@@ -67,63 +184,134 @@ impl Encoder for StaticHuffmanEncoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<EncodeResult, Box<dyn Error>> {
-        let mut reader = input.reader();
-        let mut writer = output.writer();
-
-        let input_length = input.len();
-        let frequencies = input.frequencies();
-        Self::encode_loop(
-            &mut self.huffman,
-            input_length,
-            frequencies,
-            &mut reader,
-            &mut writer,
-        )
+    ) -> Result<EncodeResult, Error> {
+        // The input must be read twice, once for the frequencies.
+        let Some(len) = input.len() else {
+            let mut reader = input.reader()?;
+            let mut writer = output.writer()?;
+            return self.encode_streaming(&mut reader, &mut writer);
+        };
+
+        // Without transforms, the frequencies are those of the input bytes.
+        let (num_symbols, frequencies) = if self.transforms.is_empty() {
+            (len, input.frequencies()?)
+        } else {
+            self.transformed_frequencies(&mut input.reader()?)?
+        };
+
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(num_symbols, len, frequencies, &mut reader, &mut writer)
     }
 }
 
 pub struct StaticHuffmanDecoder {
-    huffman: StaticHuffman,
+    transforms: TransformChain,
+
+    // Largest number of bytes to decode.
+    max_output_len: u64,
 }
 
 impl StaticHuffmanDecoder {
     pub fn new() -> Self {
+        Self::with_transforms(&[])
+    }
+
+    /// Create an instance for data encoded with the transform `stages`.
+    pub fn with_transforms(stages: &[TransformStage]) -> Self {
         Self {
-            huffman: StaticHuffman::new(NUM_SYMBOLS),
+            transforms: TransformChain::new(stages, NUM_SYMBOLS as usize),
+            max_output_len: u64::MAX,
         }
     }
 
     fn decode_loop(
-        &self,
+        &mut self,
         reader: &mut dyn io::Read,
         writer: &mut dyn io::Write,
-    ) -> Result<DecodeResult, Box<dyn Error>> {
-
+    ) -> Result<DecodeResult, Error> {
         let mut bit_reader = BitReader::new(reader);
-        let input_len = bit_reader.read_bits(64);
+        let num_symbols = bit_reader.read_bits(64);
+        let mut bytes_written = 0;
+        if num_symbols == STREAMING_LAYOUT {
+            loop {
+                let num_symbols = bit_reader.read_bits(64);
+                if num_symbols == 0 {
+                    break;
+                }
+                let max_len = self.max_output_len - bytes_written as u64;
+                bytes_written +=
+                    self.decode_block(num_symbols, max_len, &mut bit_reader, writer)?;
+            }
+        } else {
+            let max_len = self.max_output_len;
+            bytes_written = self.decode_block(num_symbols, max_len, &mut bit_reader, writer)?;
+        }
+        bit_reader.check_status()?;
+        let bytes_read = bit_reader.finish()?;
 
-        let prefix_code = PrefixCode::decode_coding_table(&mut bit_reader)?;
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+
+    // Reads the coding table and decodes `num_symbols` symbols, to at most `max_len` bytes.
+    // Returns the number of bytes written.
+    fn decode_block(
+        &mut self,
+        num_symbols: u64,
+        max_len: u64,
+        bit_reader: &mut BitReader,
+        writer: &mut dyn io::Write,
+    ) -> Result<usize, Error> {
+        // The transforms may expand the symbols, e.g. a run of zeros, so the output length is
+        // stored to bound them.
+        let output_len = if self.transforms.is_empty() {
+            num_symbols
+        } else {
+            bit_reader.read_bits(64)
+        };
+        bit_reader.check_status()?;
+        if output_len > max_len {
+            return Err(Error::CorruptData("Length exceeds the limit"));
+        }
+
+        let prefix_code = PrefixCode::decode_coding_table(bit_reader)?;
+        if prefix_code.num_symbols as usize != self.transforms.num_output_symbols() {
+            return Err(Error::InvalidCodeTable("Invalid coding table"));
+        }
+        if num_symbols > 0 && prefix_code.lengths.iter().all(Vec::is_empty) {
+            return Err(Error::InvalidCodeTable("Empty coding table"));
+        }
         let decoder = prefix_code.generate_decoder();
 
-        let mut buffer: Box<[u8; READ_BUFFER_SIZE]> = Box::new([0; READ_BUFFER_SIZE]);
+        let mut symbols: Vec<u16> = Vec::with_capacity(READ_BUFFER_SIZE);
+        let mut decoded: Vec<u16> = Vec::new();
         let mut bytes_written = 0;
-        let mut buffer_pos = 0;
-        for _ in 0..input_len as usize {
-            let symbol = decoder.decode(&mut bit_reader);
-            buffer[buffer_pos] = symbol as u8;
-            buffer_pos += 1;
-            if buffer_pos == READ_BUFFER_SIZE {
-                writer.write_all(buffer.as_ref())?;
-                buffer_pos = 0;
-                bytes_written += READ_BUFFER_SIZE;
+        let mut remaining = num_symbols;
+        self.transforms.reset_with_max_len(output_len);
+        loop {
+            let len = remaining.min(READ_BUFFER_SIZE as u64);
+            remaining -= len;
+            symbols.clear();
+            for _ in 0..len {
+                symbols.push(decoder.decode(bit_reader)?);
+            }
+            bit_reader.check_status()?;
+            decoded.clear();
+            self.transforms.decode(&symbols, &mut decoded)?;
+            if len == 0 {
+                self.transforms.finish_decode(&mut decoded)?;
+            }
+            let bytes: Vec<u8> = decoded.iter().map(|&symbol| symbol as u8).collect();
+            writer.write_all(&bytes)?;
+            bytes_written += bytes.len();
+            if len == 0 {
+                break;
             }
         }
-        let bytes_read = bit_reader.finish();
-        writer.write_all(&buffer[0..buffer_pos])?;
-        bytes_written += buffer_pos;
-
-        Ok(DecodeResult::new(bytes_read, bytes_written))
+        if bytes_written as u64 != output_len {
+            return Err(Error::CorruptData("Size mismatch"));
+        }
+        Ok(bytes_written)
     }
 }
 
@@ -132,9 +320,112 @@ impl Decoder for StaticHuffmanDecoder {
         &mut self,
         input: &mut InputSource,
         output: &mut OutputSink,
-    ) -> Result<DecodeResult, Box<dyn Error>> {
-        let mut reader = input.reader();
-        let mut writer = output.writer();
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
         self.decode_loop(&mut reader, &mut writer)
     }
+
+    fn set_max_output_len(&mut self, max_output_len: u64) {
+        self.max_output_len = max_output_len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coding::test_util::check_io_errors;
+    use crate::transform::TransformStage::*;
+
+    // Returns the encoded size.
+    fn encode_decode(stages: &[TransformStage], input: &[u8]) -> usize {
+        let mut output = OutputSink::memory(Vec::new());
+        StaticHuffmanEncoder::with_transforms(stages)
+            .encode(&mut InputSource::memory(input.to_vec()), &mut output)
+            .unwrap();
+        let encoded = output.take_memory();
+
+        let mut output = OutputSink::memory(Vec::new());
+        StaticHuffmanDecoder::with_transforms(stages)
+            .decode(&mut InputSource::memory(encoded.clone()), &mut output)
+            .unwrap();
+        assert_eq!(output.take_memory(), input);
+        encoded.len()
+    }
+
+    #[test]
+    fn test_streaming_layout() {
+        // More than one chunk.
+        let text = include_bytes!("../../testdata/deflate/text.txt");
+        let input = text.repeat(STREAMING_CHUNK_SIZE as usize / text.len() + 1);
+        for stages in [&[][..], &[MoveToFront, ZeroRun]] {
+            for input in [&input[..], b"", b"a"] {
+                let mut output = OutputSink::memory(Vec::new());
+                StaticHuffmanEncoder::with_transforms(stages)
+                    .encode(&mut InputSource::from_reader(Box::new(input)), &mut output)
+                    .unwrap();
+                let encoded = output.take_memory();
+                assert_eq!(encoded[..8], STREAMING_LAYOUT.to_be_bytes());
+
+                let mut output = OutputSink::memory(Vec::new());
+                StaticHuffmanDecoder::with_transforms(stages)
+                    .decode(&mut InputSource::slice(&encoded), &mut output)
+                    .unwrap();
+                assert_eq!(output.take_memory(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Sparse data, with long runs of zeros.
+        let mut input: Vec<u8> = Vec::new();
+        for i in 0..2000u32 {
+            input.extend(vec![0; (i * 7 % 300) as usize]);
+            input.push((i % 5 + 1) as u8);
+        }
+        let size = encode_decode(&[], &input);
+        assert!(encode_decode(&[ZeroRun], &input) < size / 4);
+        encode_decode(&[MoveToFront, ZeroRun], &input);
+        encode_decode(&[MoveToFront1, ZeroRun], &input);
+        encode_decode(&[MoveToFront], b"abc");
+    }
+
+    #[test]
+    fn test_output_length_limit() {
+        // A run of 1000 zeros.
+        let stages = [MoveToFront, ZeroRun];
+        let mut input = vec![0; 1000];
+        input.push(1);
+        let mut output = OutputSink::memory(Vec::new());
+        StaticHuffmanEncoder::with_transforms(&stages)
+            .encode(&mut InputSource::slice(&input), &mut output)
+            .unwrap();
+        let mut encoded = output.take_memory();
+
+        let decode = |encoded: &[u8], max_output_len: u64| {
+            let mut decoder = StaticHuffmanDecoder::with_transforms(&stages);
+            decoder.set_max_output_len(max_output_len);
+            let mut output = OutputSink::memory(Vec::new());
+            decoder
+                .decode(&mut InputSource::slice(encoded), &mut output)
+                .map(|_| output.take_memory())
+        };
+        assert_eq!(decode(&encoded, 1001).unwrap(), input);
+        let result = decode(&encoded, 1000);
+        assert!(matches!(result, Err(Error::CorruptData(_))));
+
+        // The run is longer than the stored output length.
+        encoded[8..16].copy_from_slice(&500u64.to_be_bytes());
+        let result = decode(&encoded, u64::MAX);
+        assert!(matches!(result, Err(Error::CorruptData("Run too long"))));
+    }
+
+    #[test]
+    fn test_io_errors() {
+        check_io_errors(
+            || Box::new(StaticHuffmanEncoder::new()),
+            || Box::new(StaticHuffmanDecoder::new()),
+        );
+    }
 }
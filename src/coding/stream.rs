@@ -0,0 +1,602 @@
+use std::io;
+use std::io::{Read, Write};
+
+use crate::coding::frame::{FrameChecksum, FrameHasher, FrameHeader};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::coding::CompressionMethod;
+use crate::error::Error;
+
+/// Largest number of bytes of original data in a block of a streamed frame.
+pub const STREAM_BLOCK_SIZE: usize = 1 << 20;
+
+// Size of the block size that precedes each block.
+const BLOCK_SIZE_BYTES: usize = 4;
+
+/// Compresses the data written to it into a frame, which is written to `writer`.
+///
+/// As the size isn't known in advance, the frame has the blocks flag: the payload is a sequence
+/// of blocks, each the output of the method on up to `STREAM_BLOCK_SIZE` bytes of original data,
+/// preceded by its size as a 32-bit little-endian value. A block size of 0 ends the sequence, and
+/// the checksum follows if any.
+///
+/// `flush()` writes the data so far as a block, which makes it available to the decoder at some
+/// cost in compression. `finish()` completes the frame. If the instance is dropped without
+/// `finish()`, the frame is completed on drop and errors are ignored, so call `finish()` to
+/// handle them.
+///
+/// After an error of the writer or the encoder, the frame may be partly written, so the instance
+/// is poisoned: later calls fail, and drop doesn't complete the frame.
+pub struct CompressWriter<W: Write> {
+    // None once `finish()` has taken it.
+    writer: Option<W>,
+    header: FrameHeader,
+    header_written: bool,
+    hasher: Option<FrameHasher>,
+
+    // Original data of the next block, kept until it's written.
+    block: Vec<u8>,
+
+    // Set after an error, as the frame can't be completed then.
+    poisoned: bool,
+}
+
+impl<W: Write> CompressWriter<W> {
+    pub fn new(writer: W, method: CompressionMethod) -> Self {
+        Self::with_checksum(writer, method, None)
+    }
+
+    /// Create an instance that appends `checksum` of the original data, if any.
+    pub fn with_checksum(
+        writer: W,
+        method: CompressionMethod,
+        checksum: Option<FrameChecksum>,
+    ) -> Self {
+        Self {
+            writer: Some(writer),
+            header: FrameHeader {
+                method,
+                original_size: None,
+                checksum,
+                blocks: true,
+            },
+            header_written: false,
+            hasher: checksum.map(FrameHasher::new),
+            block: Vec::new(),
+            poisoned: false,
+        }
+    }
+
+    /// Writes the rest of the frame, and returns the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let result = self.finish_frame();
+        let writer = self.writer.take().unwrap();
+        result.map(|()| writer)
+    }
+
+    /// Returns a reference to the writer.
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().unwrap()
+    }
+
+    // Runs `f`, and poisons the instance if it fails.
+    fn run(&mut self, f: impl FnOnce(&mut Self) -> io::Result<()>) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other("CompressWriter failed earlier"));
+        }
+        let result = f(self);
+        self.poisoned = result.is_err();
+        result
+    }
+
+    // Writes the pending data, the end of the blocks and the checksum.
+    fn finish_frame(&mut self) -> io::Result<()> {
+        self.run(|this| {
+            this.write_block()?;
+            let writer = this.writer.as_mut().unwrap();
+            writer.write_all(&0u32.to_le_bytes())?;
+            if let Some(hasher) = &this.hasher {
+                writer.write_all(&hasher.value())?;
+            }
+            writer.flush()
+        })
+    }
+
+    // Writes the header if it's not written yet, and the pending data as a block if any. The
+    // data is cleared only once written.
+    fn write_block(&mut self) -> io::Result<()> {
+        let writer = self.writer.as_mut().unwrap();
+        if !self.header_written {
+            writer.write_all(&self.header.to_bytes())?;
+            self.header_written = true;
+        }
+        if self.block.is_empty() {
+            return Ok(());
+        }
+
+        let mut coded = OutputSink::memory(Vec::new());
+        self.header
+            .method
+            .encoder()
+            .encode(&mut InputSource::slice(&self.block), &mut coded)
+            .map_err(io::Error::from)?;
+        let coded = coded.take_memory();
+        let size = u32::try_from(coded.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Block too large"))?;
+        writer.write_all(&size.to_le_bytes())?;
+        writer.write_all(&coded)?;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A full block is written before taking more data, so an error doesn't take any of `buf`.
+        if self.block.len() == STREAM_BLOCK_SIZE || self.poisoned {
+            self.run(Self::write_block)?;
+        }
+        let len = buf.len().min(STREAM_BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..len]);
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&buf[..len]);
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.run(|this| {
+            this.write_block()?;
+            this.writer.as_mut().unwrap().flush()
+        })
+    }
+}
+
+impl<W: Write> Drop for CompressWriter<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() && !self.poisoned {
+            let _ = self.finish_frame();
+        }
+    }
+}
+
+/// Decompresses a frame read from `reader`, with any of the compression methods.
+///
+/// Frames written by `CompressWriter`, which always have blocks, are decoded a block at a time.
+/// Other frames, such as those of `FrameEncoder`, are buffered: as the end of the payload is only
+/// known at the end of the input, the whole input is read into memory and decoded on the first
+/// read. Write frames with `CompressWriter` to decode them in bounded memory. Decoding fails as
+/// soon as the data exceeds the original size, or the block size. The size and the checksum are
+/// verified at the end of the frame, before the end of data is returned.
+pub struct DecompressReader<R: Read> {
+    reader: R,
+    header: Option<FrameHeader>,
+    hasher: Option<FrameHasher>,
+
+    // Decoded data, of which the bytes from `pos` are not returned yet.
+    decoded: Vec<u8>,
+    pos: usize,
+
+    // Whether the end of the frame is reached.
+    done: bool,
+
+    bytes_read: usize,
+    bytes_written: u64,
+}
+
+impl<R: Read> DecompressReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            header: None,
+            hasher: None,
+            decoded: Vec::new(),
+            pos: 0,
+            done: false,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// Returns the header of the frame, once the first read is done.
+    pub fn header(&self) -> Option<&FrameHeader> {
+        self.header.as_ref()
+    }
+
+    /// Returns the reader.
+    ///
+    /// The payload of a frame without blocks extends to the end of the input, so the whole reader
+    /// is consumed when it's decoded, including any data after the frame.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Returns the number of bytes read from the reader.
+    pub(crate) fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+
+    /// Decodes the rest of the frame to `writer`. Returns the number of bytes written.
+    pub(crate) fn decode_to(&mut self, writer: &mut dyn Write) -> Result<usize, Error> {
+        let mut bytes_written = 0;
+        loop {
+            writer.write_all(&self.decoded[self.pos..])?;
+            bytes_written += self.decoded.len() - self.pos;
+            self.pos = self.decoded.len();
+            if self.done {
+                return Ok(bytes_written);
+            }
+            self.decode_next()?;
+        }
+    }
+
+    // Decodes the next part of the frame into `decoded`.
+    fn decode_next(&mut self) -> Result<(), Error> {
+        self.decoded.clear();
+        self.pos = 0;
+        let Some(header) = &self.header else {
+            let header = FrameHeader::read(&mut self.reader)?;
+            self.bytes_read += header.size();
+            self.hasher = header.checksum.map(FrameHasher::new);
+            let blocks = header.blocks;
+            self.header = Some(header);
+            if !blocks {
+                self.decode_whole()?;
+            }
+            return Ok(());
+        };
+        let method = header.method;
+        let trailer_size = header.checksum.map_or(0, |checksum| checksum.size());
+
+        let mut size = [0; BLOCK_SIZE_BYTES];
+        self.read_frame_bytes(&mut size)?;
+        let size = u32::from_le_bytes(size) as usize;
+        if size == 0 {
+            let mut trailer = vec![0; trailer_size];
+            self.read_frame_bytes(&mut trailer)?;
+            return self.finish_frame(&trailer);
+        }
+
+        let mut coded: Vec<u8> = Vec::new();
+        self.reader
+            .by_ref()
+            .take(size as u64)
+            .read_to_end(&mut coded)?;
+        self.bytes_read += coded.len();
+        if coded.len() < size {
+            return Err(Error::Truncated);
+        }
+        self.decode_payload(method, coded)
+    }
+
+    // Decodes a frame without blocks, whose payload extends to the end of the input, so it's read
+    // into memory first.
+    fn decode_whole(&mut self) -> Result<(), Error> {
+        let header = self.header.as_ref().unwrap();
+        let method = header.method;
+        let trailer_size = header.checksum.map_or(0, |checksum| checksum.size());
+        let mut payload: Vec<u8> = Vec::new();
+        self.reader.read_to_end(&mut payload)?;
+        self.bytes_read += payload.len();
+        if payload.len() < trailer_size {
+            return Err(Error::Truncated);
+        }
+        let trailer = payload.split_off(payload.len() - trailer_size);
+        self.decode_payload(method, payload)?;
+        self.finish_frame(&trailer)
+    }
+
+    // Decodes `coded` with `method` into `decoded`.
+    fn decode_payload(&mut self, method: CompressionMethod, coded: Vec<u8>) -> Result<(), Error> {
+        let mut decoder = method.decoder();
+        let max_output_len = self.max_output_len();
+        if let Some(max_output_len) = max_output_len {
+            decoder.set_max_output_len(max_output_len);
+        }
+        let mut writer = CappedWriter {
+            data: &mut self.decoded,
+            max_len: max_output_len.unwrap_or(u64::MAX),
+            exceeded: false,
+        };
+        let result = decoder.decode(
+            &mut InputSource::memory(coded),
+            &mut OutputSink::from_writer(Box::new(&mut writer)),
+        );
+        if writer.exceeded {
+            return Err(Error::CorruptData("Decoded data too large"));
+        }
+        result?;
+        self.bytes_written += self.decoded.len() as u64;
+        if let Some(hasher) = &mut self.hasher {
+            hasher.update(&self.decoded);
+        }
+        Ok(())
+    }
+
+    // Returns the largest size of the next decoded part, from the original size and the block
+    // size, if known.
+    fn max_output_len(&self) -> Option<u64> {
+        let header = self.header.as_ref().unwrap();
+        let remaining = header
+            .original_size
+            .map(|size| size.saturating_sub(self.bytes_written));
+        if header.blocks {
+            Some(remaining.map_or(STREAM_BLOCK_SIZE as u64, |len| {
+                len.min(STREAM_BLOCK_SIZE as u64)
+            }))
+        } else {
+            remaining
+        }
+    }
+
+    // Checks the size and the checksum at the end of the frame.
+    fn finish_frame(&mut self, trailer: &[u8]) -> Result<(), Error> {
+        let header = self.header.as_ref().unwrap();
+        if header
+            .original_size
+            .is_some_and(|size| size != self.bytes_written)
+        {
+            return Err(Error::CorruptData("Size mismatch"));
+        }
+        if let Some(hasher) = &self.hasher {
+            if hasher.value() != trailer {
+                return Err(Error::ChecksumMismatch);
+            }
+        }
+        self.done = true;
+        Ok(())
+    }
+
+    // Reads exactly `buf.len()` bytes, which must be in the frame.
+    fn read_frame_bytes(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        match self.reader.read_exact(buf) {
+            Ok(()) => {
+                self.bytes_read += buf.len();
+                Ok(())
+            }
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Err(Error::Truncated),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+// Appends to `data`, and fails once more than `max_len` bytes are written, so that invalid data
+// can't expand without bound.
+struct CappedWriter<'a> {
+    data: &'a mut Vec<u8>,
+    max_len: u64,
+
+    // Set once a write has failed for exceeding `max_len`.
+    exceeded: bool,
+}
+
+impl Write for CappedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if (self.data.len() + buf.len()) as u64 > self.max_len {
+            self.exceeded = true;
+            return Err(Error::CorruptData("Decoded data too large").into());
+        }
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecompressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() && !self.done {
+            self.decode_next().map_err(io::Error::from)?;
+        }
+        let len = buf.len().min(self.decoded.len() - self.pos);
+        buf[..len].copy_from_slice(&self.decoded[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::coding::encoder::Encoder;
+    use crate::coding::frame::{decompress, FrameEncoder};
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn compress(
+        input: &[u8],
+        method: CompressionMethod,
+        checksum: Option<FrameChecksum>,
+    ) -> Vec<u8> {
+        let mut writer = CompressWriter::with_checksum(Vec::new(), method, checksum);
+        // Writes of various sizes.
+        for (i, chunk) in input.chunks(1000).enumerate() {
+            writer.write_all(&chunk[..i % 10]).unwrap();
+            writer.write_all(&chunk[i % 10..]).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn decompress_reader(encoded: &[u8]) -> io::Result<Vec<u8>> {
+        let mut decoded = Vec::new();
+        DecompressReader::new(encoded).read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_all_methods() {
+        for method in CompressionMethod::ALL {
+            for input in [&b""[..], b"a", TEXT] {
+                let encoded = compress(input, method, Some(FrameChecksum::Crc32));
+                let header = FrameHeader::read(&mut &encoded[..]).unwrap();
+                assert!(header.blocks);
+                assert_eq!(
+                    decompress_reader(&encoded).unwrap(),
+                    input,
+                    "{}",
+                    method.name()
+                );
+
+                // The frame decoder reads streamed frames too.
+                let mut output = OutputSink::memory(Vec::new());
+                decompress(&mut InputSource::memory(encoded), &mut output).unwrap();
+                assert_eq!(output.take_memory(), input);
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocks_and_flush() {
+        // More than one full block.
+        let input: Vec<u8> = TEXT.repeat(STREAM_BLOCK_SIZE / TEXT.len() + 2);
+        let encoded = compress(&input, CompressionMethod::DynamicHuffmanCoding, None);
+        assert_eq!(decompress_reader(&encoded).unwrap(), input);
+
+        // Flushed data is available to the reader before the end of the frame.
+        let mut writer = CompressWriter::new(Vec::new(), CompressionMethod::Bzip2Coding);
+        writer.write_all(b"Hello, ").unwrap();
+        writer.flush().unwrap();
+        let flushed = writer.get_ref().clone();
+        let mut reader = DecompressReader::new(&flushed[..]);
+        let mut buf = [0; 7];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"Hello, ");
+        assert_eq!(
+            reader.header().unwrap().method,
+            CompressionMethod::Bzip2Coding
+        );
+        writer.write_all(b"world!").unwrap();
+        let encoded = writer.finish().unwrap();
+        assert_eq!(decompress_reader(&encoded).unwrap(), b"Hello, world!");
+    }
+
+    #[test]
+    fn test_finish_on_drop() {
+        let mut encoded = Vec::new();
+        let mut writer = CompressWriter::with_checksum(
+            &mut encoded,
+            CompressionMethod::LzHuffmanCoding,
+            Some(FrameChecksum::Crc32),
+        );
+        writer.write_all(TEXT).unwrap();
+        drop(writer);
+        assert_eq!(decompress_reader(&encoded).unwrap(), TEXT);
+    }
+
+    // Records the data written to it, and fails writes while `fail` is set.
+    struct FlakyWriter {
+        data: Rc<RefCell<Vec<u8>>>,
+        fail: Rc<Cell<bool>>,
+    }
+
+    impl Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fail.get() {
+                return Err(io::Error::other("Write failed"));
+            }
+            self.data.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_errors() {
+        let data = Rc::new(RefCell::new(Vec::new()));
+        let fail = Rc::new(Cell::new(false));
+        let mut writer = CompressWriter::new(
+            FlakyWriter {
+                data: data.clone(),
+                fail: fail.clone(),
+            },
+            CompressionMethod::StaticHuffmanCoding,
+        );
+        writer.write_all(TEXT).unwrap();
+        writer.flush().unwrap();
+        let flushed_len = data.borrow().len();
+
+        // The block is kept after the failed write, and the instance is poisoned.
+        fail.set(true);
+        writer.write_all(TEXT).unwrap();
+        assert!(writer.flush().is_err());
+        assert_eq!(writer.block, TEXT);
+        assert!(writer.poisoned);
+        fail.set(false);
+        assert!(writer.write(b"a").is_err());
+        assert!(writer.flush().is_err());
+
+        // Drop doesn't write the end of the frame.
+        drop(writer);
+        assert_eq!(data.borrow().len(), flushed_len);
+    }
+
+    #[test]
+    fn test_frames_without_blocks() {
+        let mut output = OutputSink::memory(Vec::new());
+        FrameEncoder::new(CompressionMethod::StaticHuffmanCoding)
+            .encode(&mut InputSource::memory(TEXT.to_vec()), &mut output)
+            .unwrap();
+        assert_eq!(decompress_reader(&output.take_memory()).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut data = Vec::new();
+        let mut writer = CappedWriter {
+            data: &mut data,
+            max_len: 10,
+            exceeded: false,
+        };
+        writer.write_all(b"12345").unwrap();
+        assert!(writer.write_all(b"678901").is_err());
+        assert!(writer.exceeded);
+
+        // Data that decodes to more than the original size in the header.
+        for method in [
+            CompressionMethod::DynamicHuffmanCoding,
+            CompressionMethod::TansCoding,
+        ] {
+            let mut output = OutputSink::memory(Vec::new());
+            FrameEncoder::new(method)
+                .encode(&mut InputSource::slice(TEXT), &mut output)
+                .unwrap();
+            let mut encoded = output.take_memory();
+            encoded[7..15].copy_from_slice(&100u64.to_le_bytes());
+            let mut output = OutputSink::memory(Vec::new());
+            let result = decompress(&mut InputSource::memory(encoded), &mut output);
+            assert!(
+                matches!(result, Err(Error::CorruptData(_))),
+                "{:?}",
+                result.err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let encoded = compress(
+            TEXT,
+            CompressionMethod::DynamicHuffmanCoding,
+            Some(FrameChecksum::XxHash64),
+        );
+        let error = decompress_reader(&encoded[..encoded.len() - 3]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(decompress_reader(&encoded[..20]).is_err());
+        assert!(decompress_reader(b"CPRS").is_err());
+
+        let mut corrupt = encoded.clone();
+        let len = corrupt.len();
+        corrupt[len - 1] ^= 1;
+        let error = decompress_reader(&corrupt).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Checksum mismatch");
+    }
+}
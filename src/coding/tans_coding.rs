@@ -0,0 +1,344 @@
+use std::io;
+
+use crate::ans::{normalize_frequencies, Tans, MIN_TABLE_BITS, TANS_TABLE_BITS};
+use crate::bits::{ReverseBitReader, ReverseBitWriter};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+
+const NUM_SYMBOLS: usize = 256;
+
+// Number of bits used to store the table size.
+const TABLE_BITS_BITS: u32 = 4;
+
+// Number of bits used to store a repeat count of zero frequencies.
+const ZERO_REPEAT_BITS: u32 = 2;
+
+// Output buffer size.
+const WRITE_BUFFER_SIZE: usize = 8 * 1024;
+
+// Number of bits needed to store values up to `max_value`.
+fn bits_for(max_value: u32) -> u32 {
+    u32::BITS - max_value.leading_zeros()
+}
+
+// Choose the table size for the input length and the number of distinct symbols.
+fn choose_table_bits(input_len: usize, num_present: usize) -> u32 {
+    let len_bits = bits_for(u32::try_from(input_len - 1).unwrap_or(u32::MAX));
+    let symbol_bits = bits_for(num_present as u32 - 1);
+    len_bits
+        .clamp(MIN_TABLE_BITS, TANS_TABLE_BITS)
+        .max(symbol_bits)
+}
+
+// Serializes the normalized frequencies in symbol order, as (value, bits) pairs.
+//
+// Each frequency is stored with just enough bits for the remaining total, so the frequencies
+// get cheaper as the total is used up, and the trailing zero frequencies are omitted. A zero
+// frequency is followed by repeat counts of further zero frequencies. A repeat count of
+// `MAX_REPEAT` means more repeat counts follow.
+fn encode_frequencies(frequencies: &[u32], table_bits: u32) -> Vec<(u64, u32)> {
+    const MAX_REPEAT: usize = (1 << ZERO_REPEAT_BITS) - 1;
+    let mut fields: Vec<(u64, u32)> = vec![(table_bits as u64, TABLE_BITS_BITS)];
+    let mut remaining = 1u32 << table_bits;
+    let mut symbol = 0;
+    while remaining > 0 {
+        let frequency = frequencies[symbol];
+        fields.push((frequency as u64, bits_for(remaining)));
+        remaining -= frequency;
+        symbol += 1;
+
+        if frequency == 0 {
+            let mut repeat = frequencies[symbol..]
+                .iter()
+                .take_while(|&&f| f == 0)
+                .count();
+            symbol += repeat;
+            while repeat >= MAX_REPEAT {
+                fields.push((MAX_REPEAT as u64, ZERO_REPEAT_BITS));
+                repeat -= MAX_REPEAT;
+            }
+            fields.push((repeat as u64, ZERO_REPEAT_BITS));
+        }
+    }
+    fields
+}
+
+// Decodes the normalized frequencies and the table size.
+fn decode_frequencies(bit_reader: &mut ReverseBitReader) -> Result<(Vec<u32>, u32), Error> {
+    const ERROR: Error = Error::InvalidCodeTable("Invalid frequency table");
+    const MAX_REPEAT: u64 = (1 << ZERO_REPEAT_BITS) - 1;
+    let table_bits = bit_reader.read_bits(TABLE_BITS_BITS) as u32;
+    if !(MIN_TABLE_BITS..=TANS_TABLE_BITS).contains(&table_bits) {
+        return Err(ERROR);
+    }
+
+    let mut frequencies: Vec<u32> = Vec::with_capacity(NUM_SYMBOLS);
+    let mut remaining = 1u32 << table_bits;
+    while remaining > 0 {
+        let frequency = bit_reader.read_bits(bits_for(remaining)) as u32;
+        if frequency > remaining || frequencies.len() == NUM_SYMBOLS {
+            return Err(ERROR);
+        }
+        remaining -= frequency;
+        frequencies.push(frequency);
+
+        if frequency == 0 {
+            loop {
+                let repeat = bit_reader.read_bits(ZERO_REPEAT_BITS);
+                frequencies.resize(frequencies.len() + repeat as usize, 0);
+                if frequencies.len() > NUM_SYMBOLS {
+                    return Err(ERROR);
+                }
+                if repeat < MAX_REPEAT {
+                    break;
+                }
+            }
+        }
+    }
+    frequencies.resize(NUM_SYMBOLS, 0);
+    Ok((frequencies, table_bits))
+}
+
+pub struct TansEncoder {}
+
+impl TansEncoder {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    fn encode_loop(
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        // tANS encodes in reverse, so the whole input is needed.
+        let mut input: Vec<u8> = Vec::new();
+        let bytes_read = reader.read_to_end(&mut input)?;
+
+        let mut bit_writer = ReverseBitWriter::new(writer);
+        if let Some((&last, rest)) = input.split_last() {
+            let mut frequencies = vec![0; NUM_SYMBOLS];
+            for &byte in input.iter() {
+                frequencies[byte as usize] += 1;
+            }
+            let num_present = frequencies.iter().filter(|&&f| f > 0).count();
+            let table_bits = choose_table_bits(input.len(), num_present);
+            let normalized = normalize_frequencies(&frequencies, table_bits);
+            let tans = Tans::new(&normalized, table_bits);
+
+            // Encode the symbols backwards.
+            let mut state = tans.initial_state(last as u16);
+            for &symbol in rest.iter().rev() {
+                tans.encode(&mut state, symbol as u16, &mut bit_writer);
+            }
+            tans.write_state(state, &mut bit_writer);
+
+            // Write the coding table, last field first.
+            for &(value, bits) in encode_frequencies(&normalized, table_bits).iter().rev() {
+                bit_writer.write_bits(value, bits);
+            }
+        }
+
+        // Write out the input length. This is read first by the decoder.
+        bit_writer.write_bits(bytes_read as u64, 64);
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for TansEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        Self::encode_loop(&mut reader, &mut writer)
+    }
+}
+
+pub struct TansDecoder {
+    // Largest number of bytes to decode.
+    max_output_len: u64,
+}
+
+impl TansDecoder {
+    pub fn new() -> Self {
+        Self {
+            max_output_len: u64::MAX,
+        }
+    }
+
+    fn decode_loop(
+        &self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = ReverseBitReader::new(reader);
+        let input_len = bit_reader.read_bits(64);
+        bit_reader.check_status()?;
+
+        // A symbol with all of the table decodes without reading any bits, so the length is
+        // all that bounds the output.
+        if input_len > self.max_output_len {
+            return Err(Error::CorruptData("Input length exceeds the limit"));
+        }
+
+        let mut buffer: Box<[u8; WRITE_BUFFER_SIZE]> = Box::new([0; WRITE_BUFFER_SIZE]);
+        let mut bytes_written = 0;
+        let mut buffer_pos = 0;
+        if input_len > 0 {
+            let (frequencies, table_bits) = decode_frequencies(&mut bit_reader)?;
+            let tans = Tans::new(&frequencies, table_bits);
+            let mut state = tans.read_state(&mut bit_reader);
+
+            for i in 0..input_len {
+                // The last symbol has no next state.
+                let symbol = if i + 1 < input_len {
+                    tans.decode(&mut state, &mut bit_reader)
+                } else {
+                    tans.peek_symbol(state)
+                };
+                buffer[buffer_pos] = symbol as u8;
+                buffer_pos += 1;
+                if buffer_pos == WRITE_BUFFER_SIZE {
+                    bit_reader.check_status()?;
+                    writer.write_all(buffer.as_ref())?;
+                    buffer_pos = 0;
+                    bytes_written += WRITE_BUFFER_SIZE;
+                }
+            }
+        }
+        bit_reader.check_status()?;
+        let bytes_read = bit_reader.finish()?;
+        writer.write_all(&buffer[0..buffer_pos])?;
+        bytes_written += buffer_pos;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for TansDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+
+    fn set_max_output_len(&mut self, max_output_len: u64) {
+        self.max_output_len = max_output_len;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coding::test_util::check_io_errors;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_encode_decode_frequencies() {
+        fn test(frequencies: &[u32], table_bits: u32) {
+            let mut cursor = Cursor::new(Vec::new());
+            let mut writer = ReverseBitWriter::new(&mut cursor);
+            for &(value, bits) in encode_frequencies(frequencies, table_bits).iter().rev() {
+                writer.write_bits(value, bits);
+            }
+            writer.finish().unwrap();
+
+            let mut cursor = Cursor::new(cursor.into_inner());
+            let mut reader = ReverseBitReader::new(&mut cursor);
+            let (decoded, decoded_table_bits) = decode_frequencies(&mut reader).unwrap();
+            assert_eq!(decoded_table_bits, table_bits);
+            assert_eq!(decoded, frequencies);
+        }
+
+        let mut frequencies = vec![0; NUM_SYMBOLS];
+        frequencies[255] = 32;
+        test(&frequencies, 5);
+
+        frequencies[0] = 16;
+        frequencies[255] = 16;
+        test(&frequencies, 5);
+
+        let frequencies: Vec<u32> = (0..NUM_SYMBOLS).map(|i| (i % 2 == 0) as u32 * 2).collect();
+        test(&frequencies, 8);
+    }
+
+    #[test]
+    fn test_beats_whole_bit_codes() {
+        // 1 in 100 symbols is different. Huffman needs at least 1 bit per symbol.
+        let input: Vec<u8> = (0..10000)
+            .map(|i| if i % 100 == 0 { b'b' } else { b'a' })
+            .collect();
+        let mut encoded = Vec::new();
+        let result = TansEncoder::encode_loop(&mut Cursor::new(input.clone()), &mut encoded);
+        assert!(result.is_ok());
+        assert!(encoded.len() < 10000 / 8 / 2);
+
+        let mut decoded = Vec::new();
+        let result = TansDecoder::new().decode_loop(&mut Cursor::new(encoded), &mut decoded);
+        assert!(result.is_ok());
+        assert_eq!(decoded, input);
+    }
+
+    #[test]
+    fn test_choose_table_bits() {
+        assert_eq!(choose_table_bits(1, 1), MIN_TABLE_BITS);
+        assert_eq!(choose_table_bits(100, 200), 8);
+        assert_eq!(choose_table_bits(usize::MAX, 1), TANS_TABLE_BITS);
+    }
+
+    #[test]
+    fn test_input_length_limit() {
+        // A single symbol decodes without reading any bits.
+        let input = vec![b'a'; 1000];
+        let mut output = OutputSink::memory(Vec::new());
+        TansEncoder::new()
+            .encode(&mut InputSource::slice(&input), &mut output)
+            .unwrap();
+        let encoded = output.take_memory();
+
+        let decode = |encoded: &[u8], max_output_len: u64| {
+            let mut decoder = TansDecoder::new();
+            decoder.set_max_output_len(max_output_len);
+            let mut output = OutputSink::memory(Vec::new());
+            decoder
+                .decode(&mut InputSource::slice(encoded), &mut output)
+                .map(|_| output.take_memory())
+        };
+        assert_eq!(decode(&encoded, 1000).unwrap(), input);
+        let result = decode(&encoded, 999);
+        assert!(matches!(result, Err(Error::CorruptData(_))));
+
+        // Truncated data.
+        let input = include_bytes!("../../testdata/deflate/text.txt");
+        let mut output = OutputSink::memory(Vec::new());
+        TansEncoder::new()
+            .encode(&mut InputSource::slice(input), &mut output)
+            .unwrap();
+        let encoded = output.take_memory();
+        let result = decode(&encoded[..encoded.len() / 2], u64::MAX);
+        assert!(
+            matches!(result, Err(Error::Truncated)),
+            "{:?}",
+            result.err()
+        );
+    }
+
+    #[test]
+    fn test_io_errors() {
+        check_io_errors(
+            || Box::new(TansEncoder::new()),
+            || Box::new(TansDecoder::new()),
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::io;
+
+use crate::coding::decoder::Decoder;
+use crate::coding::encoder::Encoder;
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::error::Error;
+
+// Fails every read and write.
+pub struct FailingIo;
+
+impl io::Read for FailingIo {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("Read failed"))
+    }
+}
+
+impl io::Write for FailingIo {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::other("Write failed"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Checks that the encoder fails with the error of a failing writer, and the decoder with the
+/// error of a reader that fails halfway through the encoded data.
+pub fn check_io_errors(
+    new_encoder: impl Fn() -> Box<dyn Encoder>,
+    new_decoder: impl Fn() -> Box<dyn Decoder>,
+) {
+    let input = include_bytes!("../../testdata/deflate/text.txt");
+    let mut output = OutputSink::from_writer(Box::new(FailingIo));
+    let result = new_encoder().encode(&mut InputSource::slice(input), &mut output);
+    assert!(matches!(result, Err(Error::Io(_))), "{:?}", result.err());
+
+    let mut output = OutputSink::memory(Vec::new());
+    new_encoder()
+        .encode(&mut InputSource::slice(input), &mut output)
+        .unwrap();
+    let encoded = output.take_memory();
+
+    let reader = io::Read::chain(&encoded[..encoded.len() / 2], FailingIo);
+    let mut output = OutputSink::memory(Vec::new());
+    let result = new_decoder().decode(&mut InputSource::from_reader(Box::new(reader)), &mut output);
+    assert!(matches!(result, Err(Error::Io(_))), "{:?}", result.err());
+}
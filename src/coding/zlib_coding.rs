@@ -0,0 +1,184 @@
+use std::io;
+
+use crate::bits::{LsbBitReader, LsbBitWriter};
+use crate::checksum::{Adler32, Checksum, ChecksumWriter};
+use crate::coding::decoder::{DecodeResult, Decoder};
+use crate::coding::deflate_coding::deflate_stream;
+use crate::coding::encoder::{EncodeResult, Encoder};
+use crate::coding::input::InputSource;
+use crate::coding::output::OutputSink;
+use crate::deflate::{Deflater, Inflater};
+use crate::error::Error;
+
+// Compression method: DEFLATE.
+const METHOD_DEFLATE: u8 = 8;
+
+// Largest window size, as log2(window size) - 8.
+const MAX_WINDOW_INFO: u8 = 7;
+
+// Flag for a preset dictionary.
+const FLAG_DICTIONARY: u8 = 0x20;
+
+// Compression level of the header: default.
+const DEFAULT_LEVEL: u8 = 2;
+
+/// Returns true if `header` is a valid zlib header.
+pub fn is_zlib_header(header: [u8; 2]) -> bool {
+    header[0] & 0x0f == METHOD_DEFLATE
+        && header[0] >> 4 <= MAX_WINDOW_INFO
+        && u16::from_be_bytes(header).is_multiple_of(31)
+}
+
+/// zlib (RFC 1950) encoder, with DEFLATE compressed data.
+pub struct ZlibEncoder {
+    deflater: Deflater,
+}
+
+impl ZlibEncoder {
+    pub fn new() -> Self {
+        Self {
+            deflater: Deflater::new(),
+        }
+    }
+
+    fn encode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<EncodeResult, Error> {
+        // The header check bits make the header a multiple of 31.
+        let cmf = MAX_WINDOW_INFO << 4 | METHOD_DEFLATE;
+        let flags = DEFAULT_LEVEL << 6;
+        let check = (31 - u16::from_be_bytes([cmf, flags]) % 31) % 31;
+        let mut bit_writer = LsbBitWriter::new(writer);
+        bit_writer.write_bytes(&[cmf, flags | check as u8]);
+
+        let mut adler = Adler32::new();
+        let bytes_read = deflate_stream(
+            &mut self.deflater,
+            reader,
+            &mut bit_writer,
+            Some(&mut adler),
+        )?;
+
+        // Write the trailer: Adler-32 of the data.
+        bit_writer.align_to_byte();
+        bit_writer.write_bytes(&adler.value().to_be_bytes());
+        let bytes_written = bit_writer.finish()?;
+
+        Ok(EncodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Encoder for ZlibEncoder {
+    fn encode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<EncodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.encode_loop(&mut reader, &mut writer)
+    }
+}
+
+/// Decoder for zlib data.
+pub struct ZlibDecoder {
+    inflater: Inflater,
+}
+
+impl ZlibDecoder {
+    pub fn new() -> Self {
+        Self {
+            inflater: Inflater::new(),
+        }
+    }
+
+    pub(crate) fn decode_loop(
+        &mut self,
+        reader: &mut dyn io::Read,
+        writer: &mut dyn io::Write,
+    ) -> Result<DecodeResult, Error> {
+        let mut bit_reader = LsbBitReader::new(reader);
+        let mut header = [0; 2];
+        let len = bit_reader.read_bytes(&mut header);
+        bit_reader.check_status()?;
+        if len != 2 || !is_zlib_header(header) {
+            return Err(Error::CorruptHeader("Invalid zlib header"));
+        }
+        if header[1] & FLAG_DICTIONARY != 0 {
+            return Err(Error::Unsupported("Preset dictionaries are not supported"));
+        }
+
+        let mut checksum_writer = ChecksumWriter::new(writer, Adler32::new());
+        let bytes_written = self
+            .inflater
+            .inflate(&mut bit_reader, &mut checksum_writer)?;
+
+        // Check the trailer.
+        bit_reader.align_to_byte();
+        let mut trailer = [0; 4];
+        if bit_reader.read_bytes(&mut trailer) != 4 {
+            bit_reader.check_status()?;
+            return Err(Error::Truncated);
+        }
+        if u32::from_be_bytes(trailer) != checksum_writer.value() {
+            return Err(Error::ChecksumMismatch);
+        }
+        let bytes_read = bit_reader.finish()?;
+
+        Ok(DecodeResult::new(bytes_read, bytes_written))
+    }
+}
+
+impl Decoder for ZlibDecoder {
+    fn decode(
+        &mut self,
+        input: &mut InputSource,
+        output: &mut OutputSink,
+    ) -> Result<DecodeResult, Error> {
+        let mut reader = input.reader()?;
+        let mut writer = output.writer()?;
+        self.decode_loop(&mut reader, &mut writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../../testdata/deflate/text.txt");
+
+    fn decode(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut decoded = Vec::new();
+        ZlibDecoder::new().decode_loop(&mut io::Cursor::new(encoded), &mut decoded)?;
+        Ok(decoded)
+    }
+
+    #[test]
+    fn test_vectors() {
+        // Written by Python's zlib module.
+        let encoded = include_bytes!("../../testdata/gzip/text.zlib");
+        assert_eq!(decode(encoded).unwrap(), TEXT);
+
+        // Corrupt checksum.
+        let mut corrupt = encoded.to_vec();
+        let len = corrupt.len();
+        corrupt[len - 1] ^= 1;
+        assert!(decode(&corrupt).is_err());
+        assert!(decode(&encoded[..2]).is_err());
+        assert!(decode(&[0x78, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for input in [&b""[..], b"a", TEXT] {
+            let mut encoded = Vec::new();
+            ZlibEncoder::new()
+                .encode_loop(&mut io::Cursor::new(input), &mut encoded)
+                .unwrap();
+            assert!(is_zlib_header([encoded[0], encoded[1]]));
+            assert_eq!(decode(&encoded).unwrap(), input);
+        }
+    }
+}
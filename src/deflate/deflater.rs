@@ -0,0 +1,379 @@
+use crate::bits::LsbBitWriter;
+use crate::deflate::tables::*;
+use crate::huffman::{LengthLimitedHuffman, PrefixCode};
+use crate::lz::{bucket_symbol, MatchFinder, Token, MAX_MATCH, MIN_MATCH};
+
+/// Default number of hash chain candidates to compare.
+pub const DEFAULT_SEARCH_DEPTH: usize = 32;
+
+/// Type of a DEFLATE block.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockType {
+    Stored,
+    Fixed,
+    Dynamic,
+}
+
+// Huffman codes of a block, as the (code, length) of each symbol.
+struct BlockCodes {
+    literal_codes: Vec<(u32, u8)>,
+    distance_codes: Vec<(u32, u8)>,
+}
+
+/// DEFLATE (RFC 1951) encoder.
+///
+/// Each call to `write_block` compresses the data into one block, choosing the block type that
+/// codes the data in the fewest bits. Matches may refer to the data of the previous blocks.
+pub struct Deflater {
+    match_finder: MatchFinder,
+
+    // If set, all blocks are of this type.
+    block_type: Option<BlockType>,
+
+    // The end of the previous data, up to the window size.
+    history: Vec<u8>,
+
+    // Length symbol of each match length.
+    length_symbols: Vec<u16>,
+
+    // Codes of the fixed Huffman block.
+    fixed_codes: BlockCodes,
+}
+
+impl Deflater {
+    pub fn new() -> Self {
+        Self::with_options(DEFAULT_SEARCH_DEPTH, None)
+    }
+
+    /// Create an instance that compares up to `search_depth` candidates for each match, and
+    /// writes blocks of `block_type` only if it is set.
+    pub fn with_options(search_depth: usize, block_type: Option<BlockType>) -> Self {
+        let mut length_symbols: Vec<u16> = vec![0; MAX_MATCH + 1];
+        for (i, &base) in LENGTH_BASE.iter().enumerate() {
+            length_symbols[base as usize..].fill(END_OF_BLOCK + 1 + i as u16);
+        }
+        Self {
+            match_finder: MatchFinder::new(WINDOW_SIZE, MIN_MATCH, search_depth),
+            block_type,
+            history: Vec::new(),
+            length_symbols,
+            fixed_codes: BlockCodes {
+                literal_codes: canonical_codes(&fixed_literal_lengths()),
+                distance_codes: canonical_codes(&fixed_distance_lengths()),
+            },
+        }
+    }
+
+    /// Starts a new stream, forgetting the data of the previous blocks.
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// Compresses `data` into a block. `last` sets the final block flag.
+    pub fn write_block(&mut self, data: &[u8], last: bool, bit_writer: &mut LsbBitWriter) {
+        let history_len = self.history.len();
+        self.history.extend_from_slice(data);
+        let tokens = self
+            .match_finder
+            .parse_with_dictionary(&self.history, history_len);
+
+        // Count the symbols, and the extra bits that are the same for all Huffman blocks.
+        let mut literal_weights: Vec<u32> = vec![0; NUM_USED_LITERAL_SYMBOLS];
+        let mut distance_weights: Vec<u32> = vec![0; NUM_USED_DISTANCE_SYMBOLS];
+        let mut extra_bits: u64 = 0;
+        literal_weights[END_OF_BLOCK as usize] = 1;
+        for token in tokens.iter() {
+            match *token {
+                Token::Literal(byte) => literal_weights[byte as usize] += 1,
+                Token::Match { length, distance } => {
+                    let symbol = self.length_symbols[length as usize];
+                    literal_weights[symbol as usize] += 1;
+                    extra_bits += LENGTH_EXTRA_BITS[(symbol - END_OF_BLOCK - 1) as usize] as u64;
+                    let (symbol, bits, _) = bucket_symbol(distance - 1);
+                    distance_weights[symbol as usize] += 1;
+                    extra_bits += bits as u64;
+                }
+            }
+        }
+
+        // Find the block type with the fewest bits.
+        let dynamic_header = DynamicHeader::new(&literal_weights, &distance_weights);
+        let dynamic_bits = dynamic_header.num_bits()
+            + cost(&literal_weights, &dynamic_header.literal_lengths)
+            + cost(&distance_weights, &dynamic_header.distance_lengths)
+            + extra_bits;
+        let fixed_bits = cost(&literal_weights, &fixed_literal_lengths())
+            + cost(&distance_weights, &fixed_distance_lengths())
+            + extra_bits;
+        let num_stored_blocks = data.len().div_ceil(MAX_STORED_BLOCK_LENGTH).max(1);
+        let stored_bits = (num_stored_blocks * 40 + data.len() * 8) as u64;
+        let block_type = self
+            .block_type
+            .unwrap_or(if stored_bits < fixed_bits.min(dynamic_bits) {
+                BlockType::Stored
+            } else if fixed_bits <= dynamic_bits {
+                BlockType::Fixed
+            } else {
+                BlockType::Dynamic
+            });
+
+        match block_type {
+            BlockType::Stored => Self::write_stored_blocks(data, last, bit_writer),
+            BlockType::Fixed => {
+                bit_writer.write_bits(last as u64, 1);
+                bit_writer.write_bits(1, 2);
+                self.write_tokens(&tokens, &self.fixed_codes, bit_writer);
+            }
+            BlockType::Dynamic => {
+                bit_writer.write_bits(last as u64, 1);
+                bit_writer.write_bits(2, 2);
+                dynamic_header.write(bit_writer);
+                let codes = BlockCodes {
+                    literal_codes: canonical_codes(&dynamic_header.literal_lengths),
+                    distance_codes: canonical_codes(&dynamic_header.distance_lengths),
+                };
+                self.write_tokens(&tokens, &codes, bit_writer);
+            }
+        }
+
+        // Keep the end of the data for the matches of the next block.
+        let excess = self.history.len().saturating_sub(WINDOW_SIZE);
+        self.history.drain(..excess);
+    }
+
+    fn write_stored_blocks(data: &[u8], last: bool, bit_writer: &mut LsbBitWriter) {
+        let mut chunks: Vec<&[u8]> = data.chunks(MAX_STORED_BLOCK_LENGTH).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        let num_chunks = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            bit_writer.write_bits((last && i + 1 == num_chunks) as u64, 1);
+            bit_writer.write_bits(0, 2);
+            bit_writer.align_to_byte();
+            bit_writer.write_bits(chunk.len() as u64, 16);
+            bit_writer.write_bits(!chunk.len() as u64 & 0xffff, 16);
+            bit_writer.write_bytes(chunk);
+        }
+    }
+
+    fn write_tokens(&self, tokens: &[Token], codes: &BlockCodes, bit_writer: &mut LsbBitWriter) {
+        for token in tokens.iter() {
+            match *token {
+                Token::Literal(byte) => {
+                    let code = codes.literal_codes[byte as usize];
+                    bit_writer.write_code(code.0 as u64, code.1 as u32);
+                }
+                Token::Match { length, distance } => {
+                    let symbol = self.length_symbols[length as usize];
+                    let code = codes.literal_codes[symbol as usize];
+                    bit_writer.write_code(code.0 as u64, code.1 as u32);
+                    let index = (symbol - END_OF_BLOCK - 1) as usize;
+                    bit_writer.write_bits(
+                        (length - LENGTH_BASE[index] as u32) as u64,
+                        LENGTH_EXTRA_BITS[index] as u32,
+                    );
+
+                    let (symbol, extra_bits, extra) = bucket_symbol(distance - 1);
+                    let code = codes.distance_codes[symbol as usize];
+                    bit_writer.write_code(code.0 as u64, code.1 as u32);
+                    bit_writer.write_bits(extra as u64, extra_bits);
+                }
+            }
+        }
+        let code = codes.literal_codes[END_OF_BLOCK as usize];
+        bit_writer.write_code(code.0 as u64, code.1 as u32);
+    }
+}
+
+// The code lengths of a dynamic Huffman block, and the header that stores them.
+struct DynamicHeader {
+    literal_lengths: Vec<u8>,
+    distance_lengths: Vec<u8>,
+
+    // Run-length encoded code lengths, as (code length symbol, extra bits value).
+    code_length_symbols: Vec<(u8, u8)>,
+
+    // Code lengths of the code length code.
+    code_length_lengths: Vec<u8>,
+
+    // Number of code length code lengths that are stored.
+    num_code_length_lengths: usize,
+}
+
+impl DynamicHeader {
+    fn new(literal_weights: &[u32], distance_weights: &[u32]) -> Self {
+        let literal_lengths = code_lengths(literal_weights, MAX_CODE_LENGTH);
+        let distance_lengths = code_lengths(distance_weights, MAX_CODE_LENGTH);
+
+        // Trailing unused symbols are not stored.
+        let num_literals = num_used(&literal_lengths, END_OF_BLOCK as usize + 1);
+        let num_distances = num_used(&distance_lengths, 1);
+        let mut lengths = literal_lengths[..num_literals].to_vec();
+        lengths.extend_from_slice(&distance_lengths[..num_distances]);
+        let code_length_symbols = run_length_encode(&lengths);
+
+        let mut weights: Vec<u32> = vec![0; NUM_CODE_LENGTH_SYMBOLS];
+        for &(symbol, _) in code_length_symbols.iter() {
+            weights[symbol as usize] += 1;
+        }
+        let code_length_lengths = code_lengths(&weights, MAX_CODE_LENGTH_CODE_LENGTH);
+        let num_code_length_lengths = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code_length_lengths[symbol] > 0)
+            .map_or(0, |pos| pos + 1)
+            .max(4);
+
+        Self {
+            literal_lengths: literal_lengths[..num_literals].to_vec(),
+            distance_lengths: distance_lengths[..num_distances].to_vec(),
+            code_length_symbols,
+            code_length_lengths,
+            num_code_length_lengths,
+        }
+    }
+
+    // Number of bits of the header.
+    fn num_bits(&self) -> u64 {
+        let mut bits = 5 + 5 + 4 + 3 * self.num_code_length_lengths as u64;
+        for &(symbol, _) in self.code_length_symbols.iter() {
+            bits +=
+                self.code_length_lengths[symbol as usize] as u64 + repeat_extra_bits(symbol) as u64;
+        }
+        bits
+    }
+
+    fn write(&self, bit_writer: &mut LsbBitWriter) {
+        bit_writer.write_bits((self.literal_lengths.len() - 257) as u64, 5);
+        bit_writer.write_bits((self.distance_lengths.len() - 1) as u64, 5);
+        bit_writer.write_bits((self.num_code_length_lengths - 4) as u64, 4);
+        for &symbol in CODE_LENGTH_ORDER[..self.num_code_length_lengths].iter() {
+            bit_writer.write_bits(self.code_length_lengths[symbol] as u64, 3);
+        }
+
+        let codes = canonical_codes(&self.code_length_lengths);
+        for &(symbol, extra) in self.code_length_symbols.iter() {
+            let code = codes[symbol as usize];
+            bit_writer.write_code(code.0 as u64, code.1 as u32);
+            bit_writer.write_bits(extra as u64, repeat_extra_bits(symbol));
+        }
+    }
+}
+
+// Number of extra bits of a code length symbol.
+fn repeat_extra_bits(symbol: u8) -> u32 {
+    match symbol {
+        REPEAT_PREVIOUS => 2,
+        REPEAT_ZERO_SHORT => 3,
+        REPEAT_ZERO_LONG => 7,
+        _ => 0,
+    }
+}
+
+// Run-length encodes code lengths with the repeat symbols of the code length code.
+fn run_length_encode(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut symbols: Vec<(u8, u8)> = Vec::new();
+    let mut pos = 0;
+    while pos < lengths.len() {
+        let length = lengths[pos];
+        let mut run = lengths[pos..].iter().take_while(|&&l| l == length).count();
+        pos += run;
+        if length == 0 {
+            while run >= 11 {
+                let n = run.min(138);
+                symbols.push((REPEAT_ZERO_LONG, (n - 11) as u8));
+                run -= n;
+            }
+            if run >= 3 {
+                symbols.push((REPEAT_ZERO_SHORT, (run - 3) as u8));
+                run = 0;
+            }
+        } else {
+            symbols.push((length, 0));
+            run -= 1;
+            while run >= 3 {
+                let n = run.min(6);
+                symbols.push((REPEAT_PREVIOUS, (n - 3) as u8));
+                run -= n;
+            }
+        }
+        for _ in 0..run {
+            symbols.push((length, 0));
+        }
+    }
+    symbols
+}
+
+// Builds length-limited code lengths from the weights.
+//
+// At least 2 symbols are given codes, since some decoders reject a code with a single symbol.
+fn code_lengths(weights: &[u32], max_length: usize) -> Vec<u8> {
+    let mut weights = weights.to_vec();
+    for i in 0..2 {
+        if weights.iter().filter(|&&weight| weight > 0).count() < 2 && weights[i] == 0 {
+            weights[i] = 1;
+        }
+    }
+    LengthLimitedHuffman::new(weights.len() as u16, max_length)
+        .build_from_weights(&weights)
+        .code_lengths()
+}
+
+// Number of symbols up to the last one with a code, at least `min`.
+fn num_used(lengths: &[u8], min: usize) -> usize {
+    lengths
+        .iter()
+        .rposition(|&length| length > 0)
+        .map_or(0, |pos| pos + 1)
+        .max(min)
+}
+
+// Number of bits to code symbols of the weights with the code lengths.
+fn cost(weights: &[u32], lengths: &[u8]) -> u64 {
+    weights
+        .iter()
+        .zip(lengths.iter())
+        .map(|(&weight, &length)| weight as u64 * length as u64)
+        .sum()
+}
+
+// Returns the canonical code of each symbol, assigned in order of code length, then symbol.
+fn canonical_codes(lengths: &[u8]) -> Vec<(u32, u8)> {
+    PrefixCode::from_code_lengths(lengths).generate_encoder_table()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_length_encode() {
+        let mut lengths = vec![3, 3, 3, 3, 3, 0, 0, 8, 0, 0, 0, 0];
+        lengths.extend([0; 150]);
+        lengths.extend([5; 8]);
+        assert_eq!(
+            run_length_encode(&lengths),
+            vec![
+                (3, 0),
+                (REPEAT_PREVIOUS, 1),
+                (0, 0),
+                (0, 0),
+                (8, 0),
+                (REPEAT_ZERO_LONG, 127),
+                (REPEAT_ZERO_LONG, 5),
+                (5, 0),
+                (REPEAT_PREVIOUS, 3),
+                (5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_canonical_codes() {
+        // Codes: symbol 1 = 0, symbol 0 = 10, symbol 2 = 110, symbol 3 = 111.
+        assert_eq!(
+            canonical_codes(&[2, 1, 3, 3, 0]),
+            vec![(0b10, 2), (0, 1), (0b110, 3), (0b111, 3), (0, 0)]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::io;
+
+use crate::bits::LsbBitReader;
+use crate::deflate::tables::*;
+use crate::error::Error;
+use crate::huffman::{PrefixCode, PrefixDecoder};
+use crate::lz::copy_match;
+
+// Write out the output when this much is buffered, keeping the window for the matches.
+const OUTPUT_BUFFER_SIZE: usize = 4 * WINDOW_SIZE;
+
+// Creates a decoder of a canonical Huffman code from the code length of each symbol, where 0
+// means that the symbol is unused.
+//
+// An over-subscribed or incomplete code is an error, except that with `allow_incomplete`, a
+// single code of one bit or no code at all is allowed, as DEFLATE allows for distance codes.
+fn create_decoder(lengths: &[u8], allow_incomplete: bool) -> Result<PrefixDecoder, Error> {
+    let prefix_code = PrefixCode::from_code_lengths(lengths);
+    let num_codes = lengths.iter().filter(|&&length| length > 0).count();
+    if !prefix_code.is_decodable() || (num_codes <= 1 && !allow_incomplete) {
+        return Err(Error::InvalidCodeTable("Invalid code lengths"));
+    }
+    Ok(prefix_code.generate_decoder())
+}
+
+/// DEFLATE (RFC 1951) decoder.
+pub struct Inflater {
+    // Decoded data, of which the first `written` bytes have been written out.
+    output: Vec<u8>,
+    written: usize,
+
+    // Total number of bytes written out.
+    bytes_written: usize,
+}
+
+impl Inflater {
+    pub fn new() -> Self {
+        Self {
+            output: Vec::with_capacity(OUTPUT_BUFFER_SIZE + MAX_STORED_BLOCK_LENGTH),
+            written: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// Decodes a DEFLATE stream up to the end of its final block, writing the data to `writer`.
+    /// Returns the number of bytes written.
+    ///
+    /// The bits after the final block are not read, so that a container format can read its
+    /// trailer from `bit_reader`.
+    pub fn inflate(
+        &mut self,
+        bit_reader: &mut LsbBitReader,
+        writer: &mut dyn io::Write,
+    ) -> Result<usize, Error> {
+        self.output.clear();
+        self.written = 0;
+        self.bytes_written = 0;
+        loop {
+            let last = bit_reader.read_bits(1) == 1;
+            let result = match bit_reader.read_bits(2) {
+                0 => self.read_stored_block(bit_reader),
+                1 => {
+                    let literal_decoder = create_decoder(&fixed_literal_lengths(), false)?;
+                    let distance_decoder = create_decoder(&fixed_distance_lengths(), false)?;
+                    self.read_symbols(bit_reader, &literal_decoder, &distance_decoder, writer)
+                }
+                2 => Self::read_dynamic_header(bit_reader).and_then(
+                    |(literal_decoder, distance_decoder)| {
+                        self.read_symbols(bit_reader, &literal_decoder, &distance_decoder, writer)
+                    },
+                ),
+                _ => Err(Error::CorruptHeader("Invalid block type")),
+            };
+
+            // Errors past the end of the input, e.g. an invalid code of zero bits, are due to
+            // truncation, or to an error of the reader.
+            bit_reader.check_status()?;
+            result?;
+            self.flush(writer, 0)?;
+            if last {
+                break;
+            }
+        }
+        self.flush(writer, 0)?;
+        Ok(self.bytes_written)
+    }
+
+    fn read_stored_block(&mut self, bit_reader: &mut LsbBitReader) -> Result<(), Error> {
+        bit_reader.align_to_byte();
+        let len = bit_reader.read_bits(16) as usize;
+        let nlen = bit_reader.read_bits(16) as usize;
+        if len != !nlen & 0xffff {
+            return Err(Error::CorruptData("Invalid stored block length"));
+        }
+        let start = self.output.len();
+        self.output.resize(start + len, 0);
+        if bit_reader.read_bytes(&mut self.output[start..]) != len {
+            return Err(Error::Truncated);
+        }
+        Ok(())
+    }
+
+    // Reads the code lengths of a dynamic block, and creates the literal/length and distance
+    // decoders.
+    fn read_dynamic_header(
+        bit_reader: &mut LsbBitReader,
+    ) -> Result<(PrefixDecoder, PrefixDecoder), Error> {
+        let num_literals = bit_reader.read_bits(5) as usize + 257;
+        let num_distances = bit_reader.read_bits(5) as usize + 1;
+        let num_code_length_lengths = bit_reader.read_bits(4) as usize + 4;
+        if num_literals > NUM_USED_LITERAL_SYMBOLS || num_distances > NUM_USED_DISTANCE_SYMBOLS {
+            return Err(Error::InvalidCodeTable("Invalid number of codes"));
+        }
+
+        let mut code_length_lengths = [0u8; NUM_CODE_LENGTH_SYMBOLS];
+        for &symbol in CODE_LENGTH_ORDER[..num_code_length_lengths].iter() {
+            code_length_lengths[symbol] = bit_reader.read_bits(3) as u8;
+        }
+        let code_length_decoder = create_decoder(&code_length_lengths, false)?;
+
+        // The literal/length and distance code lengths are run-length encoded together.
+        let mut lengths: Vec<u8> = Vec::with_capacity(num_literals + num_distances);
+        while lengths.len() < num_literals + num_distances {
+            let symbol = code_length_decoder.decode(bit_reader)? as u8;
+            let (length, repeat) = match symbol {
+                REPEAT_PREVIOUS => {
+                    let &previous = lengths
+                        .last()
+                        .ok_or(Error::InvalidCodeTable("No previous code length"))?;
+                    (previous, 3 + bit_reader.read_bits(2) as usize)
+                }
+                REPEAT_ZERO_SHORT => (0, 3 + bit_reader.read_bits(3) as usize),
+                REPEAT_ZERO_LONG => (0, 11 + bit_reader.read_bits(7) as usize),
+                _ => (symbol, 1),
+            };
+            if lengths.len() + repeat > num_literals + num_distances {
+                return Err(Error::InvalidCodeTable("Too many code lengths"));
+            }
+            lengths.resize(lengths.len() + repeat, length);
+        }
+        if lengths[END_OF_BLOCK as usize] == 0 {
+            return Err(Error::CorruptData("Missing end of block code"));
+        }
+
+        let literal_decoder = create_decoder(&lengths[..num_literals], true)?;
+        let distance_decoder = create_decoder(&lengths[num_literals..], true)?;
+        Ok((literal_decoder, distance_decoder))
+    }
+
+    // Reads the symbols of a Huffman block, up to the end of block symbol.
+    fn read_symbols(
+        &mut self,
+        bit_reader: &mut LsbBitReader,
+        literal_decoder: &PrefixDecoder,
+        distance_decoder: &PrefixDecoder,
+        writer: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        loop {
+            let symbol = literal_decoder.decode(bit_reader)?;
+            if symbol < END_OF_BLOCK {
+                self.output.push(symbol as u8);
+            } else if symbol == END_OF_BLOCK {
+                return Ok(());
+            } else {
+                let index = (symbol - END_OF_BLOCK - 1) as usize;
+                if index >= LENGTH_BASE.len() {
+                    return Err(Error::CorruptData("Invalid length symbol"));
+                }
+                let length = LENGTH_BASE[index] as u32
+                    + bit_reader.read_bits(LENGTH_EXTRA_BITS[index] as u32) as u32;
+
+                let symbol = distance_decoder.decode(bit_reader)? as usize;
+                if symbol >= DISTANCE_BASE.len() {
+                    return Err(Error::CorruptData("Invalid distance symbol"));
+                }
+                let distance = DISTANCE_BASE[symbol] as u32
+                    + bit_reader.read_bits(DISTANCE_EXTRA_BITS[symbol] as u32) as u32;
+                copy_match(&mut self.output, length, distance)?;
+            }
+
+            // Stop on truncated input, which would be read as zero bits indefinitely.
+            if bit_reader.is_past_end() {
+                return Err(Error::Truncated);
+            }
+            self.flush(writer, OUTPUT_BUFFER_SIZE)?;
+        }
+    }
+
+    // Writes out the output if more than `threshold` bytes are buffered, keeping the window.
+    fn flush(&mut self, writer: &mut dyn io::Write, threshold: usize) -> io::Result<()> {
+        if self.output.len() < threshold.max(1) {
+            return Ok(());
+        }
+        writer.write_all(&self.output[self.written..])?;
+        self.bytes_written += self.output.len() - self.written;
+        let excess = self.output.len().saturating_sub(WINDOW_SIZE);
+        self.output.drain(..excess);
+        self.written = self.output.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_decoder() {
+        // Codes: symbol 1 = 0, symbol 0 = 10, symbol 2 = 110, symbol 3 = 111.
+        let decoder = create_decoder(&[2, 1, 3, 3], false).unwrap();
+
+        // The codes are packed starting from their msb, from the lsb of each byte.
+        // 0, 10, 111, 110 -> bits 0 1 0 1 1 1 1 1 0.
+        let mut data: &[u8] = &[0b1111_1010, 0b0000_0000];
+        let mut bit_reader = LsbBitReader::new(&mut data);
+        assert_eq!(decoder.decode(&mut bit_reader).unwrap(), 1);
+        assert_eq!(decoder.decode(&mut bit_reader).unwrap(), 0);
+        assert_eq!(decoder.decode(&mut bit_reader).unwrap(), 3);
+        assert_eq!(decoder.decode(&mut bit_reader).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_invalid_codes() {
+        assert!(create_decoder(&[1, 1, 1], false).is_err());
+        assert!(create_decoder(&[1, 2], false).is_err());
+        assert!(create_decoder(&[0, 1], false).is_err());
+        assert!(create_decoder(&[0, 1], true).is_ok());
+        assert!(create_decoder(&[0, 0], true).is_ok());
+        assert!(create_decoder(&[1, 2], true).is_err());
+        assert!(create_decoder(&[0, 2], true).is_err());
+
+        // An unused code cannot be decoded.
+        let decoder = create_decoder(&[0, 1], true).unwrap();
+        let mut data: &[u8] = &[0xff, 0xff];
+        let mut bit_reader = LsbBitReader::new(&mut data);
+        assert!(decoder.decode(&mut bit_reader).is_err());
+    }
+}
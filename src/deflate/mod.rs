@@ -0,0 +1,8 @@
+mod deflater;
+mod inflater;
+mod tables;
+
+pub use deflater::Deflater;
+#[cfg(test)]
+pub use deflater::{BlockType, DEFAULT_SEARCH_DEPTH};
+pub use inflater::Inflater;
@@ -0,0 +1,81 @@
+// Constants and tables of the DEFLATE format (RFC 1951).
+
+/// Maximum distance of a match.
+pub const WINDOW_SIZE: usize = 32 * 1024;
+
+/// End of block symbol of the literal/length code.
+pub const END_OF_BLOCK: u16 = 256;
+
+/// Number of symbols of the literal/length code, including the 2 unused symbols.
+pub const NUM_LITERAL_SYMBOLS: usize = 288;
+
+/// Number of symbols of the distance code, including the 2 unused symbols.
+pub const NUM_DISTANCE_SYMBOLS: usize = 32;
+
+/// Number of literal/length symbols that can occur in the data.
+pub const NUM_USED_LITERAL_SYMBOLS: usize = 286;
+
+/// Number of distance symbols that can occur in the data.
+pub const NUM_USED_DISTANCE_SYMBOLS: usize = 30;
+
+/// Number of symbols of the code length code.
+pub const NUM_CODE_LENGTH_SYMBOLS: usize = 19;
+
+/// Maximum code length of the literal/length and distance codes.
+pub const MAX_CODE_LENGTH: usize = 15;
+
+/// Maximum code length of the code length code.
+pub const MAX_CODE_LENGTH_CODE_LENGTH: usize = 7;
+
+/// Largest length of a stored block.
+pub const MAX_STORED_BLOCK_LENGTH: usize = 65535;
+
+/// Base match length of each length symbol, starting from symbol 257.
+pub const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Number of extra bits of each length symbol, starting from symbol 257.
+pub const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distance of each distance symbol.
+pub const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Number of extra bits of each distance symbol.
+pub const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Order in which the code lengths of the code length code are stored.
+pub const CODE_LENGTH_ORDER: [usize; NUM_CODE_LENGTH_SYMBOLS] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Code length symbol to repeat the previous length 3-6 times.
+pub const REPEAT_PREVIOUS: u8 = 16;
+
+/// Code length symbol for 3-10 zero lengths.
+pub const REPEAT_ZERO_SHORT: u8 = 17;
+
+/// Code length symbol for 11-138 zero lengths.
+pub const REPEAT_ZERO_LONG: u8 = 18;
+
+/// Code lengths of the fixed literal/length code.
+pub fn fixed_literal_lengths() -> Vec<u8> {
+    let mut lengths = vec![8; NUM_LITERAL_SYMBOLS];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths
+}
+
+/// Code lengths of the fixed distance code.
+pub fn fixed_distance_lengths() -> Vec<u8> {
+    vec![5; NUM_DISTANCE_SYMBOLS]
+}
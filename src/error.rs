@@ -0,0 +1,100 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Errors of encoding and decoding.
+#[derive(Debug)]
+pub enum Error {
+    /// An error of the underlying reader or writer.
+    Io(io::Error),
+
+    /// The input ended before the end of the coded data.
+    Truncated,
+
+    /// A header is invalid, e.g. the input isn't in the expected format.
+    CorruptHeader(&'static str),
+
+    /// A coding table is invalid, e.g. the code lengths don't form a prefix code.
+    InvalidCodeTable(&'static str),
+
+    /// The coded data is invalid.
+    CorruptData(&'static str),
+
+    /// The checksum of the decoded data doesn't match the stored one.
+    ChecksumMismatch,
+
+    /// The format version isn't supported.
+    UnsupportedVersion(u8),
+
+    /// The data uses a feature that isn't supported, e.g. an unknown method.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "I/O error: {}", error),
+            Error::Truncated => write!(f, "Truncated input"),
+            Error::CorruptHeader(message) => write!(f, "Corrupt header: {}", message),
+            Error::InvalidCodeTable(message) => write!(f, "Invalid code table: {}", message),
+            Error::CorruptData(message) => write!(f, "Corrupt data: {}", message),
+            Error::ChecksumMismatch => write!(f, "Checksum mismatch"),
+            Error::UnsupportedVersion(version) => write!(f, "Unsupported version {}", version),
+            Error::Unsupported(message) => write!(f, "Unsupported: {}", message),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// The end of the input in the middle of the data is `Truncated`.
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            Error::Truncated
+        } else {
+            Error::Io(error)
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    /// For `io::Read` and `io::Write` adapters. I/O errors are kept as they are.
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            Error::Truncated => io::Error::new(io::ErrorKind::UnexpectedEof, error),
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_conversions() {
+        let error = Error::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(matches!(error, Error::Truncated));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::UnexpectedEof);
+
+        let error = Error::from(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(error, Error::Io(_)));
+        assert_eq!(
+            io::Error::from(error).kind(),
+            io::ErrorKind::PermissionDenied
+        );
+
+        let error = io::Error::from(Error::ChecksumMismatch);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "Checksum mismatch");
+    }
+}
@@ -0,0 +1,227 @@
+use std::ops::RangeInclusive;
+
+use crate::bits::{BitReader, BitWriter};
+use crate::error::Error;
+use crate::huffman::DynamicHuffman;
+
+/// Maximum supported context order.
+pub const MAX_CONTEXT_ORDER: usize = 2;
+
+// Number of bits of the hash table for order-2 contexts.
+const ORDER2_HASH_BITS: u32 = 12;
+
+/// Adaptive Huffman coding with a context model.
+///
+/// A separate `DynamicHuffman` tree is kept for each context of the previous symbol (order-1)
+/// and hashed previous two symbols (order-2), with an extra escape symbol. A symbol that is not
+/// in the tree of the current context is coded as an escape, followed by the symbol in the next
+/// lower order context, down to an order-0 tree of all symbols. The symbol is then added to the
+/// trees that escaped. With order 0, this is a single `DynamicHuffman` tree.
+pub struct ContextHuffman {
+    // Number of symbols, excluding the escape symbol.
+    num_symbols: u16,
+
+    // Highest context order used.
+    order: usize,
+
+    // Trees for each context, for orders 1 up to `order`. Created when first used.
+    contexts: Vec<Vec<Option<DynamicHuffman>>>,
+
+    // The order-0 tree.
+    order0: DynamicHuffman,
+
+    // The previous symbols, most recent in the lowest 16 bits.
+    history: u32,
+}
+
+impl ContextHuffman {
+    /// Create a new instance for `num_symbols` symbols using contexts of up to `order` symbols.
+    pub fn new(num_symbols: u16, order: usize) -> Self {
+        assert!(order <= MAX_CONTEXT_ORDER && num_symbols < u16::MAX);
+        let contexts = (1..=order)
+            .map(|o| {
+                let size = if o == 1 {
+                    num_symbols as usize
+                } else {
+                    1 << ORDER2_HASH_BITS
+                };
+                let mut trees = Vec::with_capacity(size);
+                trees.resize_with(size, || None);
+                trees
+            })
+            .collect();
+        Self {
+            num_symbols,
+            order,
+            contexts,
+            order0: DynamicHuffman::new(num_symbols),
+            history: 0,
+        }
+    }
+
+    // The escape symbol in the context trees.
+    fn escape_symbol(&self) -> u16 {
+        self.num_symbols
+    }
+
+    // Index of the current context for the given order.
+    fn context_index(&self, order: usize) -> usize {
+        match order {
+            1 => (self.history & 0xFFFF) as usize,
+            _ => (self.history.wrapping_mul(0x9E37_79B1) >> (32 - ORDER2_HASH_BITS)) as usize,
+        }
+    }
+
+    // Returns the tree of the current context for the given order, creating it if necessary.
+    // A new tree only has the escape symbol.
+    fn context_tree(&mut self, order: usize) -> &mut DynamicHuffman {
+        let index = self.context_index(order);
+        let escape = self.escape_symbol();
+        self.contexts[order - 1][index].get_or_insert_with(|| {
+            let mut tree = DynamicHuffman::new(escape + 1);
+            tree.add_symbol(escape);
+            tree
+        })
+    }
+
+    // Adds a symbol to the trees of the contexts that escaped, and updates the history.
+    fn update(&mut self, symbol: u16, escaped_orders: RangeInclusive<usize>) {
+        for order in escaped_orders {
+            self.context_tree(order).add_symbol(symbol);
+        }
+        self.history = (self.history << 16) | symbol as u32;
+    }
+
+    /// Encodes a symbol.
+    pub fn encode(&mut self, symbol: u16, bit_writer: &mut BitWriter) {
+        assert!(symbol < self.num_symbols);
+        let escape = self.escape_symbol();
+        for order in (1..=self.order).rev() {
+            let tree = self.context_tree(order);
+            if tree.contains(symbol) {
+                tree.encode(symbol, bit_writer);
+                self.update(symbol, order + 1..=self.order);
+                return;
+            }
+            tree.encode(escape, bit_writer);
+        }
+
+        self.order0.encode(symbol, bit_writer);
+        self.update(symbol, 1..=self.order);
+    }
+
+    /// Decodes a symbol. Fails if the symbol is invalid, or in a context that escaped.
+    pub fn decode(&mut self, bit_reader: &mut BitReader) -> Result<u16, Error> {
+        let escape = self.escape_symbol();
+        let mut escaped_orders = self.order + 1..=self.order;
+        let mut symbol = escape;
+        for order in (1..=self.order).rev() {
+            symbol = self.context_tree(order).decode(bit_reader)?;
+            if symbol != escape {
+                break;
+            }
+            escaped_orders = order..=self.order;
+        }
+        if symbol == escape {
+            symbol = self.order0.decode(bit_reader)?;
+        }
+
+        // The encoder only escapes for symbols that are new to the context.
+        for order in escaped_orders.clone() {
+            if self.context_tree(order).contains(symbol) {
+                return Err(Error::CorruptData("Invalid escape"));
+            }
+        }
+        self.update(symbol, escaped_orders);
+        Ok(symbol)
+    }
+
+    /// Validate the structure of all the trees.
+    pub fn validate(&self) {
+        self.order0.validate();
+        for tree in self.contexts.iter().flatten().flatten() {
+            tree.validate();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs, Rng, SeedableRng};
+    use std::io;
+
+    // Returns the encoded size.
+    fn encode_decode(order: usize, symbols: &[u16]) -> usize {
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut writer = BitWriter::new(&mut cursor);
+        let mut model = ContextHuffman::new(300, order);
+        for &symbol in symbols.iter() {
+            model.encode(symbol, &mut writer);
+        }
+        model.validate();
+        writer.finish().unwrap();
+        let encoded = cursor.into_inner();
+
+        let mut cursor = io::Cursor::new(encoded.clone());
+        let mut reader = BitReader::new(&mut cursor);
+        let mut model = ContextHuffman::new(300, order);
+        for &symbol in symbols.iter() {
+            assert_eq!(model.decode(&mut reader).unwrap(), symbol);
+        }
+        model.validate();
+        encoded.len()
+    }
+
+    #[test]
+    fn test_orders() {
+        // Each symbol mostly follows from the previous one.
+        let mut rng = rngs::SmallRng::seed_from_u64(1);
+        let mut symbols: Vec<u16> = vec![0];
+        for _ in 0..20000 {
+            let previous = *symbols.last().unwrap();
+            let symbol = if rng.gen_ratio(9, 10) {
+                (previous * 7 + 3) % 300
+            } else {
+                rng.gen_range(0..300)
+            };
+            symbols.push(symbol);
+        }
+        let size0 = encode_decode(0, &symbols);
+        let size1 = encode_decode(1, &symbols);
+        let size2 = encode_decode(2, &symbols);
+        assert!(size1 < size0 / 2, "{} {}", size1, size0);
+        assert!(size2 < size0 / 2, "{} {}", size2, size0);
+
+        for order in 0..=MAX_CONTEXT_ORDER {
+            encode_decode(order, &[]);
+            encode_decode(order, &[299, 0, 299, 0, 5]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_escape() {
+        // Code symbol 0, then an escape in its order-1 context followed by symbol 0 again, with
+        // the trees that the model uses.
+        let mut cursor = io::Cursor::new(Vec::new());
+        let mut writer = BitWriter::new(&mut cursor);
+        let mut context0 = DynamicHuffman::new(5);
+        context0.add_symbol(4);
+        let mut order0 = DynamicHuffman::new(4);
+        context0.encode(4, &mut writer);
+        order0.encode(0, &mut writer);
+        context0.add_symbol(0);
+        context0.encode(4, &mut writer);
+        order0.encode(0, &mut writer);
+        writer.finish().unwrap();
+
+        let mut cursor = io::Cursor::new(cursor.into_inner());
+        let mut reader = BitReader::new(&mut cursor);
+        let mut model = ContextHuffman::new(4, 1);
+        assert_eq!(model.decode(&mut reader).unwrap(), 0);
+        assert!(matches!(
+            model.decode(&mut reader),
+            Err(Error::CorruptData("Invalid escape"))
+        ));
+    }
+}
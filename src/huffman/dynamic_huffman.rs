@@ -1,10 +1,12 @@
 use crate::base::DebugLog;
 use crate::bits::{BitReader, BitWriter};
+use crate::error::Error;
 
 // If true, print debug information.
 const DEBUG: bool = false;
 
 // If true, print tree details for debugging.
+#[cfg(test)]
 const DEBUG_TREE: bool = false;
 
 // If true, print nodes in linear order.
@@ -142,8 +144,8 @@ impl DynamicHuffman {
         self.reset_if_necessary();
     }
 
-    // Decode a symbol.
-    pub fn decode(&mut self, bit_reader: &mut BitReader) -> u16 {
+    // Decode a symbol. Fails if a new symbol is invalid or already in the tree.
+    pub fn decode(&mut self, bit_reader: &mut BitReader) -> Result<u16, Error> {
         if bit_reader.bits_avail() < 16 {
             bit_reader.fill_data();
         }
@@ -188,6 +190,9 @@ impl DynamicHuffman {
         // If the decoded symbol is the NYT symbol, then read the raw symbol.
         let decoded_symbol = if child_id == NYT_SYMBOL {
             let new_symbol = bit_reader.read_bits(self.symbol_bits) as u16;
+            if new_symbol >= self.num_symbols || self.nodes[(new_symbol + 1) as usize].parent != 0 {
+                return Err(Error::CorruptData("Invalid new symbol"));
+            }
             self.add_new_symbol(new_symbol);
             new_symbol
         } else {
@@ -199,7 +204,20 @@ impl DynamicHuffman {
         // Don't let the total weight of the tree overflow.
         self.reset_if_necessary();
 
-        decoded_symbol
+        Ok(decoded_symbol)
+    }
+
+    /// Returns true if `symbol` is in the tree.
+    pub fn contains(&self, symbol: u16) -> bool {
+        self.nodes[(symbol + 1) as usize].parent != 0
+    }
+
+    /// Add a symbol that is not in the tree yet, without coding it. This is for symbols that the
+    /// decoder learns in another way, e.g. from another tree.
+    pub fn add_symbol(&mut self, symbol: u16) {
+        assert!(symbol < self.num_symbols && !self.contains(symbol));
+        self.add_new_symbol(symbol);
+        self.reset_if_necessary();
     }
 
     // Symbol does not exist, add it to the tree.
@@ -493,12 +511,8 @@ mod tests {
     }
 
     // Decode a symbol and validate the tree.
-    fn decode(
-        huffman: &mut DynamicHuffman,
-        expected_symbol: u16,
-        reader: &mut BitReader,
-    ) {
-        let symbol = huffman.decode(reader);
+    fn decode(huffman: &mut DynamicHuffman, expected_symbol: u16, reader: &mut BitReader) {
+        let symbol = huffman.decode(reader).unwrap();
         if DEBUG_TREE {
             huffman.print("After decode");
         }
@@ -520,7 +534,7 @@ mod tests {
         for _ in 0..10 {
             encode(&mut huffman, 0, &mut writer);
         }
-        writer.finish();
+        writer.finish().unwrap();
     }
 
     #[test]
@@ -535,7 +549,7 @@ mod tests {
                 let symbol = rng.gen::<u8>() as u16;
                 encode(&mut huffman, symbol, &mut writer);
             }
-            writer.finish();
+            writer.finish().unwrap();
         }
     }
 
@@ -548,11 +562,10 @@ mod tests {
         for i in 0..20 {
             encode(&mut huffman, i, &mut writer);
         }
-        for i in 0..20 {    
+        for i in 0..20 {
             encode(&mut huffman, i, &mut writer);
         }
-        writer.finish();
-        assert_eq!(writer.num_write_errors(), 0);
+        writer.finish().unwrap();
 
         // Decode
         let mut decode_cursor = io::Cursor::new(encode_cursor.into_inner());
@@ -0,0 +1,200 @@
+use crate::huffman::prefix_code::PrefixCode;
+
+// Type of the symbols used in the Huffman tree.
+type SymbolType = u16;
+
+// Type of the weights used in the Huffman tree.
+type WeightType = u32;
+
+// An item in a package-merge list: either a leaf (a symbol) or a package of two items from the
+// previous list.
+#[derive(Clone, Copy)]
+struct Item {
+    weight: u64,
+    is_leaf: bool,
+}
+
+/// Builds optimal length-limited Huffman codes, using the package-merge algorithm.
+///
+/// Unlike `PrefixCode::apply_max_length_limit`, which rebalances an existing tree, this finds
+/// the code lengths with the minimal total cost for the given maximum length.
+pub struct LengthLimitedHuffman {
+    num_symbols: SymbolType,
+    max_length: usize,
+}
+
+impl LengthLimitedHuffman {
+    pub fn new(num_symbols: SymbolType, max_length: usize) -> Self {
+        assert!(max_length > 0);
+        Self {
+            num_symbols,
+            max_length,
+        }
+    }
+
+    /// Builds the code table from the weights of the symbols.
+    /// Symbols with zero weight are not assigned a code.
+    pub fn build_from_weights(&self, weights: &[WeightType]) -> PrefixCode {
+        assert!(weights.len() == self.num_symbols as usize);
+
+        // Non-zero-weight symbols, sorted by weight.
+        let mut symbols: Vec<SymbolType> = (0..self.num_symbols)
+            .filter(|&symbol| weights[symbol as usize] > 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| weights[symbol as usize]);
+        let n = symbols.len();
+        assert!(n > 0);
+        assert!(
+            self.max_length >= usize::BITS as usize || n <= 1 << self.max_length,
+            "Not possible to apply specified length limit"
+        );
+
+        // A single symbol gets a 1-bit code.
+        let mut code_lengths: Vec<usize> = vec![1; n];
+        if n > 1 {
+            let leaves: Vec<Item> = symbols
+                .iter()
+                .map(|&symbol| Item {
+                    weight: weights[symbol as usize] as u64,
+                    is_leaf: true,
+                })
+                .collect();
+
+            // Each list merges the leaves with the packages made from pairs of the previous list.
+            // No code can be longer than n - 1, so more lists are not needed.
+            let num_lists = self.max_length.min(n - 1);
+            let mut lists: Vec<Vec<Item>> = vec![leaves.clone()];
+            for _ in 1..num_lists {
+                let packages = lists.last().unwrap().chunks_exact(2).map(|pair| Item {
+                    weight: pair[0].weight + pair[1].weight,
+                    is_leaf: false,
+                });
+                lists.push(Self::merge(&leaves, packages));
+            }
+
+            // Select the first 2n - 2 items of the last list. Each time a leaf is selected in a
+            // list, its code length is incremented. Each selected package selects two items in
+            // the previous list.
+            code_lengths.fill(0);
+            let mut num_selected = 2 * n - 2;
+            for list in lists.iter().rev() {
+                let mut num_packages = 0;
+                let mut num_leaves = 0;
+                for item in list[..num_selected].iter() {
+                    if item.is_leaf {
+                        num_leaves += 1;
+                    } else {
+                        num_packages += 1;
+                    }
+                }
+                for length in code_lengths[..num_leaves].iter_mut() {
+                    *length += 1;
+                }
+                num_selected = num_packages * 2;
+            }
+        }
+
+        // Output the code lengths.
+        let longest = *code_lengths.iter().max().unwrap();
+        let mut lengths: Vec<Vec<SymbolType>> = vec![Vec::new(); longest + 1];
+        for (&symbol, &length) in symbols.iter().zip(code_lengths.iter()) {
+            lengths[length].push(symbol);
+        }
+        for symbols in lengths.iter_mut() {
+            symbols.sort_unstable();
+        }
+        PrefixCode::new(self.num_symbols, lengths)
+    }
+
+    // Merge the sorted leaves and packages into a sorted list.
+    // Leaves come before packages of equal weight.
+    fn merge(leaves: &[Item], packages: impl Iterator<Item = Item>) -> Vec<Item> {
+        let mut merged: Vec<Item> = Vec::with_capacity(leaves.len() * 2);
+        let mut leaves = leaves.iter().peekable();
+        for package in packages {
+            while let Some(&&leaf) = leaves.peek() {
+                if leaf.weight > package.weight {
+                    break;
+                }
+                merged.push(leaf);
+                leaves.next();
+            }
+            merged.push(package);
+        }
+        merged.extend(leaves);
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::prefix_code::tests::validate_prefix_code;
+    use crate::huffman::StaticHuffman;
+    use rand::{rngs, Rng, SeedableRng};
+
+    fn build(weights: &[WeightType], max_length: usize) -> PrefixCode {
+        let huffman = LengthLimitedHuffman::new(weights.len() as SymbolType, max_length);
+        let prefix_code = huffman.build_from_weights(weights);
+        validate_prefix_code(&prefix_code);
+        assert!(prefix_code.lengths.len() <= max_length + 1);
+        prefix_code
+    }
+
+    #[test]
+    fn test_simple() {
+        let prefix_code = build(&[1, 1, 2, 4], 3);
+        assert_eq!(prefix_code.code_lengths(), vec![3, 3, 2, 1]);
+
+        let prefix_code = build(&[1, 1, 2, 4], 2);
+        assert_eq!(prefix_code.code_lengths(), vec![2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let prefix_code = build(&[0, 7, 0], 4);
+        assert_eq!(prefix_code.code_lengths(), vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn test_fibonacci_weights() {
+        // Fibonacci weights give the deepest possible Huffman tree.
+        let mut weights: Vec<WeightType> = vec![1, 1];
+        while weights.len() < 30 {
+            weights.push(weights[weights.len() - 1] + weights[weights.len() - 2]);
+        }
+        for max_length in [5, 8, 11, 15, 29] {
+            build(&weights, max_length);
+        }
+    }
+
+    #[test]
+    fn test_compare_with_heuristic() {
+        let huffman = StaticHuffman::new(256);
+        for s in 0..50 {
+            let mut rng = rngs::SmallRng::seed_from_u64(s);
+            let weights: Vec<WeightType> = (0..256)
+                .map(|_| {
+                    let bits = rng.gen_range(0..24);
+                    rng.gen_range(0..1 << bits)
+                })
+                .collect();
+
+            // Without an effective limit, the cost is the same as an optimal Huffman code.
+            let unlimited = huffman.build_from_weights(&weights);
+            let optimal = build(&weights, 32);
+            assert_eq!(
+                optimal.encoded_bits(&weights),
+                unlimited.encoded_bits(&weights)
+            );
+
+            // With a limit, the cost is no more than the heuristic.
+            for max_length in [9, 11, 15] {
+                let mut heuristic = unlimited.clone();
+                heuristic.apply_max_length_limit(max_length);
+                let optimal = build(&weights, max_length);
+                assert!(optimal.encoded_bits(&weights) <= heuristic.encoded_bits(&weights));
+            }
+        }
+    }
+}
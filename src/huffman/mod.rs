@@ -1,7 +1,15 @@
+mod context_huffman;
 mod dynamic_huffman;
+mod length_limited_huffman;
+mod multi_symbol_decoder;
+mod multi_table_huffman;
 mod prefix_code;
 mod static_huffman;
 
+pub use context_huffman::{ContextHuffman, MAX_CONTEXT_ORDER};
 pub use dynamic_huffman::DynamicHuffman;
+pub use length_limited_huffman::LengthLimitedHuffman;
+pub use multi_symbol_decoder::{MultiSymbolDecoder, MAX_SYMBOLS_PER_ENTRY};
+pub use multi_table_huffman::{MultiTableCode, MultiTableHuffman};
 pub use prefix_code::{PrefixCode, PrefixDecoder};
 pub use static_huffman::StaticHuffman;
@@ -0,0 +1,161 @@
+use crate::bits::PeekBits;
+use crate::error::Error;
+use crate::huffman::prefix_code::{PrefixCode, PrefixDecoder};
+
+// Type of the symbols used in the prefix tree.
+type SymbolType = u16;
+
+/// Size of the multi-symbol decode lookup table.
+pub const MULTI_DECODE_TABLE_BITS: u32 = 11;
+
+/// Maximum number of symbols in each lookup table entry.
+pub const MAX_SYMBOLS_PER_ENTRY: usize = 3;
+
+// An entry of the lookup table: the symbols whose codes fit in the looked up bits, and the
+// total number of bits of their codes. An entry without symbols means that the next code is
+// longer than the lookup bits.
+#[derive(Clone, Copy, Default)]
+struct Entry {
+    symbols: [SymbolType; MAX_SYMBOLS_PER_ENTRY],
+    num_symbols: u8,
+    num_bits: u8,
+}
+
+/// Decoder for PrefixCode that decodes several symbols per table lookup.
+///
+/// Each entry of the lookup table holds as many short codes as fit in `MULTI_DECODE_TABLE_BITS`
+/// bits, up to `MAX_SYMBOLS_PER_ENTRY`. This speeds up decoding of highly compressible data,
+/// where the common symbols have short codes. Longer codes go through a `PrefixDecoder`.
+pub struct MultiSymbolDecoder {
+    table: Vec<Entry>,
+    decoder: PrefixDecoder,
+}
+
+impl MultiSymbolDecoder {
+    pub fn new(prefix_code: &PrefixCode) -> Self {
+        let table_size = 1 << MULTI_DECODE_TABLE_BITS;
+
+        // Build a single-symbol table of (symbol, code length) first.
+        let mut single_table: Vec<(SymbolType, u8)> = vec![(0, 0); table_size];
+        for (symbol, &(code, length)) in prefix_code.generate_encoder_table().iter().enumerate() {
+            if length == 0 || length as u32 > MULTI_DECODE_TABLE_BITS {
+                continue;
+            }
+            let shift = MULTI_DECODE_TABLE_BITS - length as u32;
+            let start = (code as usize) << shift;
+            single_table[start..start + (1 << shift)].fill((symbol as SymbolType, length));
+        }
+
+        // Chain as many codes as fit in each entry.
+        let mask = table_size - 1;
+        let mut table: Vec<Entry> = vec![Entry::default(); table_size];
+        for (index, entry) in table.iter_mut().enumerate() {
+            let mut bits = 0;
+            while (entry.num_symbols as usize) < MAX_SYMBOLS_PER_ENTRY {
+                let (symbol, length) = single_table[(index << bits) & mask];
+                if length == 0 || bits + length as u32 > MULTI_DECODE_TABLE_BITS {
+                    break;
+                }
+                entry.symbols[entry.num_symbols as usize] = symbol;
+                entry.num_symbols += 1;
+                bits += length as u32;
+            }
+            entry.num_bits = bits as u8;
+        }
+
+        Self {
+            table,
+            decoder: prefix_code.generate_decoder(),
+        }
+    }
+
+    /// Decodes one or more symbols.
+    /// Returns the symbols, with the number of valid symbols.
+    pub fn decode<R: PeekBits>(
+        &self,
+        bit_reader: &mut R,
+    ) -> Result<([SymbolType; MAX_SYMBOLS_PER_ENTRY], usize), Error> {
+        if bit_reader.bits_avail() < MULTI_DECODE_TABLE_BITS {
+            bit_reader.fill_data();
+        }
+        let entry = self.table[(bit_reader.peek() >> (64 - MULTI_DECODE_TABLE_BITS)) as usize];
+        if entry.num_symbols > 0 {
+            bit_reader.consume(entry.num_bits as u32);
+            return Ok((entry.symbols, entry.num_symbols as usize));
+        }
+
+        // The code is too long for the table.
+        let mut symbols = [0; MAX_SYMBOLS_PER_ENTRY];
+        symbols[0] = self.decoder.decode(bit_reader)?;
+        Ok((symbols, 1))
+    }
+
+    /// Decodes exactly one symbol.
+    pub fn decode_one<R: PeekBits>(&self, bit_reader: &mut R) -> Result<SymbolType, Error> {
+        self.decoder.decode(bit_reader)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bits::{BitReader, BitWriter};
+    use crate::huffman::LengthLimitedHuffman;
+    use std::io;
+
+    fn encode_decode(weights: &[u32], symbols: &[SymbolType]) {
+        let prefix_code =
+            LengthLimitedHuffman::new(weights.len() as SymbolType, 24).build_from_weights(weights);
+        let encoder_table = prefix_code.generate_encoder_table();
+
+        let mut writer = io::Cursor::new(Vec::new());
+        let mut bit_writer = BitWriter::new(&mut writer);
+        for &symbol in symbols.iter() {
+            let code = encoder_table[symbol as usize];
+            bit_writer.write_bits(code.0 as u64, code.1 as u32);
+        }
+        bit_writer.finish().unwrap();
+
+        let decoder = MultiSymbolDecoder::new(&prefix_code);
+        let data = writer.into_inner();
+        let mut reader = io::Cursor::new(data);
+        let mut bit_reader = BitReader::new(&mut reader);
+        let mut decoded: Vec<SymbolType> = Vec::new();
+        while decoded.len() + MAX_SYMBOLS_PER_ENTRY <= symbols.len() {
+            let (output, count) = decoder.decode(&mut bit_reader).unwrap();
+            decoded.extend_from_slice(&output[..count]);
+        }
+        while decoded.len() < symbols.len() {
+            decoded.push(decoder.decode_one(&mut bit_reader).unwrap());
+        }
+        assert_eq!(decoded, symbols);
+    }
+
+    #[test]
+    fn test_skewed() {
+        // Mostly short codes, so that entries hold several symbols.
+        let weights: Vec<u32> = (0..16).map(|i| 1 << (15 - i)).collect();
+        let symbols: Vec<SymbolType> = (0..5000u32)
+            .map(|i| (i * i * 7 % 31).trailing_zeros().min(15) as SymbolType)
+            .collect();
+        encode_decode(&weights, &symbols);
+    }
+
+    #[test]
+    fn test_long_codes() {
+        // Fibonacci weights give codes longer than the lookup table bits.
+        let mut weights: Vec<u32> = vec![1, 1];
+        for i in 2..20 {
+            weights.push(weights[i - 1] + weights[i - 2]);
+        }
+        let symbols: Vec<SymbolType> = (0..5000u32).map(|i| (i * 13 % 20) as SymbolType).collect();
+        encode_decode(&weights, &symbols);
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let mut weights: Vec<u32> = vec![0; 10];
+        weights[3] = 5;
+        encode_decode(&weights, &[3; 100]);
+    }
+}
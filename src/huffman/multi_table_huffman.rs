@@ -0,0 +1,174 @@
+use crate::huffman::length_limited_huffman::LengthLimitedHuffman;
+use crate::huffman::prefix_code::PrefixCode;
+
+// Type of the symbols used in the Huffman tree.
+type SymbolType = u16;
+
+// Type of the weights used in the Huffman tree.
+type WeightType = u32;
+
+// Number of times to refine the tables.
+const NUM_ITERATIONS: usize = 4;
+
+// Initial cost of symbols that are not in the frequency range of a table.
+const GREATER_COST: u8 = 15;
+
+/// Huffman tables for a block, with the table selected for each group of symbols.
+pub struct MultiTableCode {
+    pub tables: Vec<PrefixCode>,
+
+    // Index of the table used by each group of symbols.
+    pub selectors: Vec<u8>,
+}
+
+/// Builds multiple Huffman tables for a block of symbols, in the style of bzip2.
+///
+/// The block is split into fixed-size groups of symbols, and each group is coded with the table
+/// that costs the least for it. Starting from tables that cover disjoint frequency ranges, the
+/// groups are repeatedly assigned to their cheapest table, and the tables are rebuilt from the
+/// symbols of their groups.
+pub struct MultiTableHuffman {
+    num_symbols: SymbolType,
+    max_length: usize,
+    group_size: usize,
+}
+
+impl MultiTableHuffman {
+    pub fn new(num_symbols: SymbolType, max_length: usize, group_size: usize) -> Self {
+        Self {
+            num_symbols,
+            max_length,
+            group_size,
+        }
+    }
+
+    /// Builds `num_tables` tables for the (non-empty) block of `symbols`.
+    ///
+    /// Every symbol that appears in the block has a code in every table.
+    pub fn build(&self, symbols: &[SymbolType], num_tables: usize) -> MultiTableCode {
+        assert!(!symbols.is_empty());
+        assert!(num_tables > 0 && num_tables <= u8::MAX as usize);
+        let num_symbols = self.num_symbols as usize;
+
+        let mut frequencies: Vec<WeightType> = vec![0; num_symbols];
+        for &symbol in symbols.iter() {
+            frequencies[symbol as usize] += 1;
+        }
+
+        // Each initial table is cheap for a range of symbols with about the same total frequency.
+        let mut code_lengths: Vec<Vec<u8>> = Vec::with_capacity(num_tables);
+        let mut remaining = symbols.len() as u64;
+        let mut start = 0;
+        for t in 0..num_tables {
+            let target = remaining / (num_tables - t) as u64;
+            let mut end = start;
+            let mut sum = 0;
+            while end < num_symbols && (sum < target || end == start) {
+                sum += frequencies[end] as u64;
+                end += 1;
+            }
+            let mut lengths = vec![GREATER_COST; num_symbols];
+            lengths[start..end].fill(0);
+            code_lengths.push(lengths);
+            remaining -= sum;
+            start = end;
+        }
+
+        let huffman = LengthLimitedHuffman::new(self.num_symbols, self.max_length);
+        let mut selectors: Vec<u8> = Vec::new();
+        let mut tables: Vec<PrefixCode> = Vec::new();
+        for _ in 0..NUM_ITERATIONS {
+            // Assign each group to the cheapest table.
+            let mut table_frequencies: Vec<Vec<WeightType>> =
+                vec![vec![0; num_symbols]; num_tables];
+            selectors.clear();
+            for group in symbols.chunks(self.group_size) {
+                let mut best = 0;
+                let mut best_cost = u64::MAX;
+                for (t, lengths) in code_lengths.iter().enumerate() {
+                    let cost: u64 = group.iter().map(|&s| lengths[s as usize] as u64).sum();
+                    if cost < best_cost {
+                        best = t;
+                        best_cost = cost;
+                    }
+                }
+                selectors.push(best as u8);
+                for &symbol in group.iter() {
+                    table_frequencies[best][symbol as usize] += 1;
+                }
+            }
+
+            // Rebuild the tables. Add 1 to the weights of all symbols in the block, so that any
+            // group can be coded with any table.
+            tables.clear();
+            code_lengths.clear();
+            for mut weights in table_frequencies {
+                for (weight, &frequency) in weights.iter_mut().zip(frequencies.iter()) {
+                    if frequency > 0 {
+                        *weight += 1;
+                    }
+                }
+                let table = huffman.build_from_weights(&weights);
+                code_lengths.push(table.code_lengths());
+                tables.push(table);
+            }
+        }
+
+        MultiTableCode { tables, selectors }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::huffman::prefix_code::tests::validate_prefix_code;
+    use rand::{rngs, Rng, SeedableRng};
+
+    // Returns the number of bits to encode the symbols with the multi-table code.
+    fn encoded_bits(code: &MultiTableCode, symbols: &[SymbolType], group_size: usize) -> u64 {
+        let code_lengths: Vec<Vec<u8>> = code.tables.iter().map(|t| t.code_lengths()).collect();
+        let mut bits = 0;
+        for (group, &selector) in symbols.chunks(group_size).zip(code.selectors.iter()) {
+            for &symbol in group.iter() {
+                let length = code_lengths[selector as usize][symbol as usize];
+                assert!(length > 0);
+                bits += length as u64;
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn test_single_symbol() {
+        let huffman = MultiTableHuffman::new(4, 15, 50);
+        let code = huffman.build(&[2; 120], 3);
+        assert_eq!(code.tables.len(), 3);
+        assert_eq!(code.selectors.len(), 3);
+        assert_eq!(encoded_bits(&code, &[2; 120], 50), 120);
+    }
+
+    #[test]
+    fn test_mixed_segments() {
+        // Alternate between segments of text-like and binary-like symbols.
+        let mut rng = rngs::SmallRng::seed_from_u64(1);
+        let mut symbols: Vec<SymbolType> = Vec::new();
+        for segment in 0..40 {
+            for _ in 0..500 {
+                let symbol = if segment % 2 == 0 {
+                    rng.gen_range(b'a'..=b'z') as SymbolType
+                } else {
+                    rng.gen_range(0..8)
+                };
+                symbols.push(symbol);
+            }
+        }
+
+        let huffman = MultiTableHuffman::new(256, 15, 50);
+        let single = huffman.build(&symbols, 1);
+        let multi = huffman.build(&symbols, 4);
+        for table in multi.tables.iter() {
+            validate_prefix_code(table);
+        }
+        assert!(encoded_bits(&multi, &symbols, 50) < encoded_bits(&single, &symbols, 50) * 9 / 10);
+    }
+}
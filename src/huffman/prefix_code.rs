@@ -1,7 +1,9 @@
 use std::fmt;
 use std::mem;
 
-use crate::bits::{BitReader, BitWriter};
+use crate::bits::{BitReader, BitWriter, PeekBits};
+use crate::error::Error;
+use crate::huffman::multi_symbol_decoder::MultiSymbolDecoder;
 
 // Type of the symbols used in the prefix tree.
 type SymbolType = u16;
@@ -31,6 +33,19 @@ impl PrefixCode {
         }
     }
 
+    /// Creates canonical codes from the code length of each symbol, where 0 means no code.
+    /// The symbols of each length are in symbol order, as in DEFLATE and bzip2.
+    pub fn from_code_lengths(code_lengths: &[u8]) -> Self {
+        let max_length = code_lengths.iter().copied().max().unwrap_or(0) as usize;
+        let mut lengths: Vec<Vec<SymbolType>> = vec![Vec::new(); max_length + 1];
+        for (symbol, &length) in code_lengths.iter().enumerate() {
+            if length > 0 {
+                lengths[length as usize].push(symbol as SymbolType);
+            }
+        }
+        Self::new(code_lengths.len() as SymbolType, lengths)
+    }
+
     /// Sets the maximum code length to `max_length`.
     /// This adjusts the code lengths of some leaves to ensure a full huffman tree.
     pub fn apply_max_length_limit(&mut self, max_length: usize) {
@@ -112,25 +127,36 @@ impl PrefixCode {
     }
 
     /// Encode (i.e. serialize) the code lengths table.
-    /// This is a simple implementation, not optimized for minimizing compression size.
+    ///
+    /// For each code length in use, this writes the increase from the previous length in
+    /// Exp-Golomb code and the number of symbols in Elias gamma code, followed by the symbols in
+    /// fixed width. A zero increase terminates the table.
     pub fn encode_coding_table(&self, bit_writer: &mut BitWriter) {
         bit_writer.write_bits(self.num_symbols as u64, SymbolType::BITS);
+        let symbol_bits = self.symbol_bits();
 
+        let mut prev_length = 0;
         for i in 1..self.lengths.len() {
             let symbols = &self.lengths[i];
             if !symbols.is_empty() {
                 // Code length.
-                bit_writer.write_bits(i as u64, 32);
+                bit_writer.write_exp_golomb((i - prev_length) as u64, 0);
+                prev_length = i;
 
                 // The symbols.
-                bit_writer.write_bits(symbols.len() as u64, SymbolType::BITS);
+                bit_writer.write_elias_gamma(symbols.len() as u64);
                 for &symbol in symbols.iter() {
-                    bit_writer.write_bits(symbol as u64, SymbolType::BITS);
+                    bit_writer.write_bits(symbol as u64, symbol_bits);
                 }
             }
         }
         // Terminator.
-        bit_writer.write_bits(0, 32);
+        bit_writer.write_exp_golomb(0, 0);
+    }
+
+    // Number of bits to store a symbol.
+    fn symbol_bits(&self) -> u32 {
+        SymbolType::BITS - self.num_symbols.saturating_sub(1).leading_zeros()
     }
 }
 
@@ -161,6 +187,12 @@ impl PrefixCode {
             }
         }
 
+        // Only an empty or single-symbol code leaves the table partly empty. The unused codes
+        // fail to decode.
+        if self.lengths.len() as u32 <= DECODE_TABLE_BITS {
+            code_table.resize(1 << DECODE_TABLE_BITS, SLOW_DECODE_SYMBOL);
+        }
+
         let mut secondary_table_bits = 0;
         let mut slow_decode_table: Vec<SlowDecode> = Vec::new();
 
@@ -245,38 +277,79 @@ impl PrefixCode {
         )
     }
 
+    /// Create a decoder that decodes several short codes per table lookup.
+    pub fn generate_multi_symbol_decoder(&self) -> MultiSymbolDecoder {
+        MultiSymbolDecoder::new(self)
+    }
+
     // Decode (i.e. deserialize) the code lengths table and create a PrefixCode instance.
-    pub fn decode_coding_table(bit_reader: &mut BitReader) -> Result<Self, &'static str> {
-        const ERROR_STR: &str = "Decode error";
+    pub fn decode_coding_table(bit_reader: &mut BitReader) -> Result<Self, Error> {
+        const ERROR: Error = Error::InvalidCodeTable("Invalid prefix code");
         let mut lengths: Vec<Vec<SymbolType>> = vec![Vec::new()];
         let num_symbols = bit_reader.read_bits(SymbolType::BITS) as SymbolType;
+        let mut prefix_code = Self {
+            num_symbols,
+            lengths: Vec::new(),
+        };
+        let symbol_bits = prefix_code.symbol_bits();
+        let mut total_symbols = 0;
         loop {
-            let len = bit_reader.read_bits(32) as usize;
-            if len == 0 {
+            let delta = bit_reader.read_exp_golomb(0) as usize;
+            if delta == 0 || bit_reader.is_past_end() {
                 break;
             }
-            if len < lengths.len() || len > PREFIX_CODE_MAX_BITS {
-                return Err(ERROR_STR);
+            if delta > PREFIX_CODE_MAX_BITS {
+                return Err(ERROR);
+            }
+            let len = lengths.len() - 1 + delta;
+            if len >= PREFIX_CODE_MAX_BITS {
+                return Err(ERROR);
             }
             while len > lengths.len() {
                 lengths.push(Vec::new());
             }
 
             // Read the number of symbols and then the symbols.
-            let num = bit_reader.read_bits(SymbolType::BITS) as SymbolType;
-            let mut symbols: Vec<SymbolType> = Vec::new();
+            let num = bit_reader.read_elias_gamma() as usize;
+            total_symbols += num;
+            if total_symbols > num_symbols as usize {
+                return Err(ERROR);
+            }
+            let mut symbols: Vec<SymbolType> = Vec::with_capacity(num);
             for _ in 0..num {
-                symbols.push(bit_reader.read_bits(SymbolType::BITS) as SymbolType);
+                let symbol = bit_reader.read_bits(symbol_bits) as SymbolType;
+                if symbol >= num_symbols {
+                    return Err(ERROR);
+                }
+                symbols.push(symbol);
             }
             lengths.push(symbols);
         }
-        if bit_reader.num_read_errors() > 0 {
-            return Err(ERROR_STR);
+        bit_reader.check_status()?;
+        prefix_code.lengths = lengths;
+        if !prefix_code.is_decodable() {
+            return Err(ERROR);
         }
-        Ok(Self {
-            num_symbols,
-            lengths,
-        })
+        Ok(prefix_code)
+    }
+
+    /// Returns true if a decoder can be generated for the code, i.e. the code is complete, or
+    /// it has no symbols, or a single symbol with a 1-bit code.
+    pub fn is_decodable(&self) -> bool {
+        if self.num_symbols == 0 || self.lengths.len() > PREFIX_CODE_MAX_BITS {
+            return false;
+        }
+        let num_codes: usize = self.lengths.iter().map(Vec::len).sum();
+        if num_codes <= 1 {
+            return self.lengths.len() <= 2 && self.lengths.first().is_none_or(Vec::is_empty);
+        }
+        let kraft_sum: u64 = self
+            .lengths
+            .iter()
+            .enumerate()
+            .map(|(length, symbols)| (symbols.len() as u64) << (PREFIX_CODE_MAX_BITS - length))
+            .sum();
+        self.lengths[0].is_empty() && kraft_sum == 1 << PREFIX_CODE_MAX_BITS
     }
 
     /// Creates a table of code length of each symbol.
@@ -289,6 +362,17 @@ impl PrefixCode {
         }
         code_lengths
     }
+
+    /// Returns the total number of bits to encode symbols with the given `weights`.
+    pub fn encoded_bits(&self, weights: &[u32]) -> u64 {
+        let mut bits: u64 = 0;
+        for (length, symbols) in self.lengths.iter().enumerate() {
+            for &symbol in symbols.iter() {
+                bits += weights[symbol as usize] as u64 * length as u64;
+            }
+        }
+        bits
+    }
 }
 
 impl fmt::Display for PrefixCode {
@@ -329,8 +413,10 @@ impl PrefixDecoder {
         }
     }
 
-    /// Decodes a symbol.
-    pub fn decode(&self, bit_reader: &mut BitReader) -> SymbolType {
+    /// Decodes a symbol, from a bit reader of either bit order.
+    ///
+    /// Bits that are not the code of a symbol, which only an incomplete code has, are an error.
+    pub fn decode<R: PeekBits>(&self, bit_reader: &mut R) -> Result<SymbolType, Error> {
         // Must have this number of bits available to decode.
         if bit_reader.bits_avail() < PREFIX_CODE_MAX_BITS as u32 {
             bit_reader.fill_data();
@@ -341,40 +427,43 @@ impl PrefixDecoder {
         let mut symbol = self.code_table[(peek_data >> (64 - DECODE_TABLE_BITS)) as usize];
         if symbol < self.num_symbols {
             bit_reader.consume(self.code_lengths[symbol as usize] as u32);
-            return symbol;
+            return Ok(symbol);
         }
 
         // Look up secondary table.
-        let secondary_index =
-            ((peek_data << DECODE_TABLE_BITS) >> (64 - self.secondary_table_bits)) as usize;
-        symbol = self.code_table[(symbol - self.num_symbols) as usize + secondary_index];
-        if symbol < self.num_symbols {
-            bit_reader.consume(self.code_lengths[symbol as usize] as u32);
-            return symbol;
+        if symbol != SLOW_DECODE_SYMBOL {
+            let secondary_index =
+                ((peek_data << DECODE_TABLE_BITS) >> (64 - self.secondary_table_bits)) as usize;
+            symbol = self.code_table[(symbol - self.num_symbols) as usize + secondary_index];
+            if symbol < self.num_symbols {
+                bit_reader.consume(self.code_lengths[symbol as usize] as u32);
+                return Ok(symbol);
+            }
         }
 
         // Slow path.
         for decode in self.slow_decode_table.iter() {
             let shifted_data = peek_data >> (64 - decode.length);
-            let delta = (shifted_data - decode.base) as usize;
+            let delta = shifted_data.wrapping_sub(decode.base) as usize;
             if delta < decode.symbols.len() {
                 symbol = decode.symbols[delta];
                 bit_reader.consume(decode.length);
-                return symbol;
+                return Ok(symbol);
             }
         }
-        panic!("This shouldn't happen");
+        Err(Error::CorruptData("Invalid prefix code"))
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::bits::{BitReader, BitWriter};
+    use crate::bits::{BitReader, BitWriter, LsbBitReader, LsbBitWriter};
     use std::collections::HashSet;
     use std::io;
 
     // Check that the code lengths are properly assigned such that the huffman tree is full.
+    #[allow(clippy::needless_range_loop, clippy::assign_op_pattern)]
     pub fn validate_prefix_code(code_lengths: &PrefixCode) {
         assert_eq!(code_lengths.lengths[0].len(), 0);
         let lengths = &code_lengths.lengths;
@@ -485,6 +574,7 @@ pub mod tests {
 
     #[test]
     fn test_generate_encoder_table() {
+        #[allow(clippy::ptr_arg, clippy::needless_borrow)]
         fn test(prefix_code: &PrefixCode, expected: &Vec<(CodeType, u8)>) {
             validate_prefix_code(&prefix_code);
             let codes = prefix_code.generate_encoder_table();
@@ -518,6 +608,7 @@ pub mod tests {
         );
     }
 
+    #[allow(clippy::useless_conversion)]
     fn create_prefix_table(data: &[SymbolType]) -> PrefixCode {
         let mut symbol: SymbolType = 0;
         let mut lengths: Vec<Vec<SymbolType>> = Vec::new();
@@ -530,6 +621,7 @@ pub mod tests {
 
     #[test]
     fn test_generate_decoder() {
+        #[allow(clippy::needless_borrow)]
         fn test(prefix_code: &PrefixCode) {
             validate_prefix_code(&prefix_code);
             prefix_code.generate_decoder();
@@ -547,12 +639,13 @@ pub mod tests {
 
     #[test]
     fn test_encode_decode_prefix_code() {
+        #[allow(clippy::needless_borrow)]
         fn test(prefix_code: &PrefixCode) {
             validate_prefix_code(&prefix_code);
             let mut encode_cursor = io::Cursor::new(Vec::new());
             let mut writer = BitWriter::new(&mut encode_cursor);
             prefix_code.encode_coding_table(&mut writer);
-            writer.finish();
+            writer.finish().unwrap();
 
             let mut decode_cursor = io::Cursor::new(encode_cursor.into_inner());
             let mut reader = BitReader::new(&mut decode_cursor);
@@ -567,8 +660,88 @@ pub mod tests {
         ]));
     }
 
+    #[test]
+    fn test_decode_invalid_coding_table() {
+        fn decode(prefix_code: &PrefixCode) -> Result<PrefixCode, Error> {
+            let mut encode_cursor = io::Cursor::new(Vec::new());
+            let mut writer = BitWriter::new(&mut encode_cursor);
+            prefix_code.encode_coding_table(&mut writer);
+            writer.finish().unwrap();
+
+            let mut decode_cursor = io::Cursor::new(encode_cursor.into_inner());
+            let mut reader = BitReader::new(&mut decode_cursor);
+            PrefixCode::decode_coding_table(&mut reader)
+        }
+
+        // Incomplete and over-subscribed codes.
+        for lengths in [
+            vec![vec![], vec![0], vec![], vec![1]],
+            vec![vec![], vec![0, 1], vec![2]],
+            vec![vec![], vec![], vec![0]],
+        ] {
+            let result = decode(&PrefixCode::new(3, lengths));
+            assert!(matches!(result, Err(Error::InvalidCodeTable(_))));
+        }
+
+        // An empty code, and a single symbol with a 1-bit code.
+        assert!(decode(&PrefixCode::new(3, vec![vec![]])).is_ok());
+        assert!(decode(&PrefixCode::new(3, vec![vec![], vec![2]])).is_ok());
+
+        // A truncated table.
+        let mut data: &[u8] = &[0, 3];
+        let mut reader = BitReader::new(&mut data);
+        let result = PrefixCode::decode_coding_table(&mut reader);
+        assert!(matches!(result, Err(Error::Truncated)));
+
+        // A huge length delta after a length of 1, and an invalid delta code.
+        for write_delta in [
+            |writer: &mut BitWriter| writer.write_exp_golomb(u64::MAX - 1, 0),
+            |writer: &mut BitWriter| writer.write_bits(0, 64),
+        ] {
+            let mut cursor = io::Cursor::new(Vec::new());
+            let mut writer = BitWriter::new(&mut cursor);
+            writer.write_bits(3, SymbolType::BITS);
+            writer.write_exp_golomb(1, 0);
+            writer.write_elias_gamma(1);
+            writer.write_bits(0, PrefixCode::new(3, vec![]).symbol_bits());
+            write_delta(&mut writer);
+            writer.write_bits(u64::MAX, 64);
+            writer.finish().unwrap();
+
+            let mut data: &[u8] = &cursor.into_inner();
+            let mut reader = BitReader::new(&mut data);
+            let result = PrefixCode::decode_coding_table(&mut reader);
+            assert!(matches!(result, Err(Error::InvalidCodeTable(_))));
+        }
+    }
+
+    #[test]
+    fn test_decode_incomplete_code() {
+        // The 1-bit code of a single symbol decodes, and the unused code fails.
+        let decoder = PrefixCode::new(3, vec![vec![], vec![2]]).generate_decoder();
+        let mut data: &[u8] = &[0x55];
+        let mut reader = BitReader::new(&mut data);
+        for _ in 0..4 {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), 2);
+            assert!(matches!(
+                decoder.decode(&mut reader),
+                Err(Error::CorruptData(_))
+            ));
+            reader.consume(1);
+        }
+
+        // No bits decode with an empty code.
+        let decoder = PrefixCode::new(3, vec![vec![]]).generate_decoder();
+        for data in [0u8, 0xff] {
+            let mut data: &[u8] = &[data];
+            let mut reader = BitReader::new(&mut data);
+            assert!(decoder.decode(&mut reader).is_err());
+        }
+    }
+
     #[test]
     fn test_encode_decode() {
+        #[allow(clippy::needless_range_loop)]
         fn test(prefix_code: &PrefixCode, input: Vec<SymbolType>) {
             // Encode
             let mut encode_cursor = io::Cursor::new(Vec::new());
@@ -579,8 +752,7 @@ pub mod tests {
                 let (code, len) = encoder_table[symbol as usize];
                 writer.write_bits(code as u64, len as u32);
             }
-            writer.finish();
-            assert_eq!(writer.num_write_errors(), 0);
+            writer.finish().unwrap();
 
             // Decode
             let mut decode_cursor = io::Cursor::new(encode_cursor.into_inner());
@@ -588,7 +760,7 @@ pub mod tests {
 
             let decoder = prefix_code.generate_decoder();
             for i in 0..input.len() {
-                let symbol = decoder.decode(&mut reader);
+                let symbol = decoder.decode(&mut reader).unwrap();
                 assert_eq!(symbol, input[i]);
             }
         }
@@ -612,4 +784,47 @@ pub mod tests {
             vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
         );
     }
+
+    #[test]
+    fn test_from_code_lengths() {
+        let prefix_code = PrefixCode::from_code_lengths(&[3, 3, 3, 3, 3, 2, 4, 4]);
+        validate_prefix_code(&prefix_code);
+        assert_eq!(prefix_code.code_lengths(), vec![3, 3, 3, 3, 3, 2, 4, 4]);
+        let codes: Vec<u32> = prefix_code
+            .generate_encoder_table()
+            .iter()
+            .map(|&(code, _)| code)
+            .collect();
+        assert_eq!(codes, vec![2, 3, 4, 5, 6, 0, 14, 15]);
+    }
+
+    #[test]
+    fn test_encode_decode_lsb() {
+        // Codes up to 23 bits, which go through all the decode tables.
+        let prefix_code = create_prefix_table(&[
+            0, 0, 0, 2, 6, 4, 12, 4, 1, 5, 10, 11, 7, 2, 4, 4, 5, 3, 2, 5, 4, 1, 4, 4,
+        ]);
+        let input: Vec<SymbolType> = (0..1000)
+            .map(|i| (i * 37 % prefix_code.num_symbols as u32) as SymbolType)
+            .collect();
+
+        // Encode
+        let mut encode_cursor = io::Cursor::new(Vec::new());
+        let mut writer = LsbBitWriter::new(&mut encode_cursor);
+        let encoder_table = prefix_code.generate_encoder_table();
+        for &symbol in input.iter() {
+            let (code, len) = encoder_table[symbol as usize];
+            writer.write_code(code as u64, len as u32);
+        }
+        writer.finish().unwrap();
+
+        // Decode
+        let mut decode_cursor = io::Cursor::new(encode_cursor.into_inner());
+        let mut reader = LsbBitReader::new(&mut decode_cursor);
+        let decoder = prefix_code.generate_decoder();
+        for &expected in input.iter() {
+            assert_eq!(decoder.decode(&mut reader).unwrap(), expected);
+        }
+        assert!(!reader.is_past_end());
+    }
 }
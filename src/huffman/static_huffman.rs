@@ -112,9 +112,11 @@ impl StaticHuffman {
             }
         }
 
-        // Number of non-zero-weight symbols.
+        // Number of non-zero-weight symbols. With none, e.g. for empty input, the code is empty.
         let symbol_size = table.len();
-        assert!(symbol_size > 0);
+        if symbol_size == 0 {
+            return PrefixCode::new(self.num_symbols, vec![Vec::new()]);
+        }
 
         // Heapify the table.
         for i in 1..symbol_size {
@@ -207,6 +209,14 @@ mod tests {
         validate_prefix_code(&prefix_code);
     }
 
+    #[test]
+    fn test_no_symbols() {
+        let huffman = StaticHuffman::new(4);
+        let prefix_code = huffman.build_from_weights(&[0, 0, 0, 0]);
+        assert!(prefix_code.lengths.iter().all(Vec::is_empty));
+        assert_eq!(prefix_code.code_lengths(), vec![0; 4]);
+    }
+
     #[test]
     fn test_merge_leaf_nodes_first() {
         let huffman = StaticHuffman::new(6);
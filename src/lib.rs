@@ -0,0 +1,54 @@
+//! A basic data compression library.
+//!
+//! `compress()` and `decompress()` code data in memory, in a frame that records the method. The
+//! `Encoder` and `Decoder` of each method work on an `InputSource` and an `OutputSink`, and the
+//! `bits` and `huffman` modules are the building blocks of the coders.
+//!
+//! Decoding never panics on malformed input: truncated or corrupt data, and unknown formats, are
+//! reported as an `Error`.
+
+mod ans;
+mod arithmetic;
+mod base;
+pub mod bits;
+mod bwt;
+mod bzip2;
+mod checksum;
+mod coding;
+mod deflate;
+mod error;
+pub mod huffman;
+mod lz;
+mod transform;
+
+use crate::coding::ContainerDecoder;
+
+pub use crate::coding::CompressionMethod as Method;
+pub use crate::coding::{
+    CompressWriter, DecodeResult, Decoder, DecompressReader, EncodeResult, Encoder, FrameChecksum,
+    FrameDecoder, FrameEncoder, FrameHeader, FrameOptions, InputSource, OutputSink, FRAME_MAGIC,
+    FRAME_VERSION, STREAM_BLOCK_SIZE,
+};
+pub use crate::error::Error;
+
+/// Compresses `data` with `method`, in a frame that `decompress()` reads.
+pub fn compress(data: &[u8], method: Method) -> Vec<u8> {
+    let mut output = OutputSink::memory(Vec::new());
+    FrameEncoder::new(method)
+        .encode(&mut InputSource::slice(data), &mut output)
+        .expect("Encoding from memory to memory should not fail");
+    output.take_memory()
+}
+
+/// Decompresses a frame written by `compress()` or `FrameEncoder`, with any method.
+///
+/// Data without a frame is decoded if it's gzip, zlib or bzip2, detected from the first bytes.
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = OutputSink::memory(Vec::new());
+    if data.starts_with(&FRAME_MAGIC) {
+        coding::decompress(&mut InputSource::slice(data), &mut output)?;
+    } else {
+        ContainerDecoder::new().decode(&mut InputSource::slice(data), &mut output)?;
+    }
+    Ok(output.take_memory())
+}
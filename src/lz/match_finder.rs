@@ -0,0 +1,249 @@
+use crate::lz::token::Token;
+
+/// Shortest supported match length.
+pub const MIN_MATCH: usize = 3;
+
+/// Longest match length.
+pub const MAX_MATCH: usize = 258;
+
+/// Largest supported window size.
+pub const MAX_WINDOW_SIZE: usize = 1 << 24;
+
+// Number of bits of the hash of the next `MIN_MATCH` bytes.
+const HASH_BITS: u32 = 15;
+
+// Marks the end of a hash chain.
+const NIL: u32 = u32::MAX;
+
+/// LZ77 match finder using hash chains.
+///
+/// The positions that start with the same `MIN_MATCH` bytes are linked in a chain, newest first.
+/// At each position, up to `search_depth` candidates of the chain within the window are compared
+/// to find the longest match. The input is parsed greedily: the longest match is taken if it is at
+/// least `min_match` bytes long, otherwise a literal.
+pub struct MatchFinder {
+    window_size: usize,
+    min_match: usize,
+    search_depth: usize,
+
+    // Most recent position for each hash value.
+    head: Vec<u32>,
+
+    // Previous position with the same hash, for each position in the window.
+    prev: Vec<u32>,
+}
+
+impl MatchFinder {
+    /// Create an instance. `window_size` must be a power of 2.
+    pub fn new(window_size: usize, min_match: usize, search_depth: usize) -> Self {
+        assert!(window_size.is_power_of_two() && window_size <= MAX_WINDOW_SIZE);
+        assert!((MIN_MATCH..=MAX_MATCH).contains(&min_match));
+        assert!(search_depth > 0);
+        Self {
+            window_size,
+            min_match,
+            search_depth,
+            head: vec![NIL; 1 << HASH_BITS],
+            prev: vec![NIL; window_size],
+        }
+    }
+
+    /// Parses `data` into a token stream.
+    pub fn parse(&mut self, data: &[u8]) -> Vec<Token> {
+        self.parse_with_dictionary(data, 0)
+    }
+
+    /// Parses `data[start..]` into a token stream. Matches may refer back to `data[..start]`,
+    /// which is typically the end of the previous block.
+    pub fn parse_with_dictionary(&mut self, data: &[u8], start: usize) -> Vec<Token> {
+        self.head.fill(NIL);
+        self.prev.fill(NIL);
+        for pos in start.saturating_sub(self.window_size)..start {
+            self.insert(data, pos);
+        }
+
+        let mut tokens: Vec<Token> = Vec::new();
+        let mut pos = start;
+        while pos < data.len() {
+            let (length, distance) = self.find_match(data, pos);
+            if length >= self.min_match {
+                tokens.push(Token::Match {
+                    length: length as u32,
+                    distance: distance as u32,
+                });
+                for i in pos..pos + length {
+                    self.insert(data, i);
+                }
+                pos += length;
+            } else {
+                tokens.push(Token::Literal(data[pos]));
+                self.insert(data, pos);
+                pos += 1;
+            }
+        }
+        tokens
+    }
+
+    // Returns (length, distance) of the longest match at `pos`, or length 0 if none.
+    fn find_match(&self, data: &[u8], pos: usize) -> (usize, usize) {
+        let max_length = (data.len() - pos).min(MAX_MATCH);
+        if max_length < MIN_MATCH {
+            return (0, 0);
+        }
+
+        let mut best_length = 0;
+        let mut best_distance = 0;
+        let mut candidate = self.head[hash(data, pos)];
+        for _ in 0..self.search_depth {
+            if candidate == NIL {
+                break;
+            }
+            let distance = pos - candidate as usize;
+            if distance > self.window_size {
+                break;
+            }
+
+            // Check the byte that would make this the longest match first.
+            let start = candidate as usize;
+            if data[start + best_length] == data[pos + best_length] {
+                let length = match_length(&data[start..], &data[pos..pos + max_length]);
+                if length > best_length {
+                    best_length = length;
+                    best_distance = distance;
+                    if length == max_length {
+                        break;
+                    }
+                }
+            }
+            candidate = self.prev[start & (self.window_size - 1)];
+        }
+        (best_length, best_distance)
+    }
+
+    // Adds `pos` to its hash chain.
+    fn insert(&mut self, data: &[u8], pos: usize) {
+        if pos + MIN_MATCH > data.len() {
+            return;
+        }
+        let hash = hash(data, pos);
+        self.prev[pos & (self.window_size - 1)] = self.head[hash];
+        self.head[hash] = pos as u32;
+    }
+}
+
+// Hash of the `MIN_MATCH` bytes at `pos`.
+fn hash(data: &[u8], pos: usize) -> usize {
+    let value = (data[pos] as u32) | (data[pos + 1] as u32) << 8 | (data[pos + 2] as u32) << 16;
+    (value.wrapping_mul(0x9E3779B1) >> (32 - HASH_BITS)) as usize
+}
+
+// Length of the common prefix of `a` and `b`.
+fn match_length(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lz::token::expand;
+    use rand::{rngs, Rng, SeedableRng};
+
+    fn parse_expand(match_finder: &mut MatchFinder, data: &[u8]) -> Vec<Token> {
+        let tokens = match_finder.parse(data);
+        let mut output = Vec::new();
+        expand(&tokens, &mut output).unwrap();
+        assert_eq!(output, data);
+        tokens
+    }
+
+    #[test]
+    fn test_repeats() {
+        let mut match_finder = MatchFinder::new(1 << 15, 3, 16);
+        assert!(parse_expand(&mut match_finder, &[]).is_empty());
+        assert_eq!(
+            parse_expand(&mut match_finder, b"abcabcabcabcx"),
+            vec![
+                Token::Literal(b'a'),
+                Token::Literal(b'b'),
+                Token::Literal(b'c'),
+                Token::Match {
+                    length: 9,
+                    distance: 3
+                },
+                Token::Literal(b'x'),
+            ]
+        );
+
+        // Long runs are split into matches of at most MAX_MATCH bytes.
+        let tokens = parse_expand(&mut match_finder, &[7; 1000]);
+        assert_eq!(tokens.len(), 1 + 999usize.div_ceil(MAX_MATCH));
+    }
+
+    #[test]
+    fn test_window_and_min_match() {
+        // The repeat is beyond a 256-byte window.
+        let mut data: Vec<u8> = b"0123456789".to_vec();
+        data.extend((0..300u32).map(|i| (i * 7 % 251) as u8 | 0x80));
+        data.extend(b"0123456789");
+
+        let mut match_finder = MatchFinder::new(256, 3, 16);
+        let tokens = parse_expand(&mut match_finder, &data);
+        assert!(tokens.iter().all(|token| match token {
+            Token::Literal(_) => true,
+            Token::Match { distance, .. } => *distance <= 256,
+        }));
+        assert_eq!(tokens.last(), Some(&Token::Literal(b'9')));
+
+        let mut match_finder = MatchFinder::new(512, 11, 16);
+        let tokens = parse_expand(&mut match_finder, &data);
+        assert!(tokens.iter().all(|token| match token {
+            Token::Literal(_) => true,
+            Token::Match { length, .. } => *length >= 11,
+        }));
+        assert_eq!(tokens.last(), Some(&Token::Literal(b'9')));
+
+        let mut match_finder = MatchFinder::new(512, 10, 16);
+        let tokens = parse_expand(&mut match_finder, &data);
+        assert_eq!(
+            tokens.last(),
+            Some(&Token::Match {
+                length: 10,
+                distance: 310
+            })
+        );
+    }
+
+    #[test]
+    fn test_dictionary() {
+        let data = b"abcdefgh abcdefgh";
+        let mut match_finder = MatchFinder::new(1 << 15, 3, 16);
+        assert_eq!(
+            match_finder.parse_with_dictionary(data, 9),
+            vec![Token::Match {
+                length: 8,
+                distance: 9
+            }]
+        );
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rng = rngs::SmallRng::seed_from_u64(0);
+        let mut data: Vec<u8> = Vec::new();
+        while data.len() < 100000 {
+            if data.len() > 100 && rng.gen_bool(0.5) {
+                // Copy an earlier string.
+                let length = rng.gen_range(1..300);
+                let start = rng.gen_range(0..data.len() - 1);
+                for i in 0..length {
+                    data.push(data[start + i]);
+                }
+            } else {
+                data.push(rng.gen_range(0..16));
+            }
+        }
+        let mut match_finder = MatchFinder::new(1 << 12, 4, 8);
+        let tokens = parse_expand(&mut match_finder, &data);
+        assert!(tokens.len() < data.len() / 4);
+    }
+}
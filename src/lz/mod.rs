@@ -0,0 +1,5 @@
+mod match_finder;
+mod token;
+
+pub use match_finder::{MatchFinder, MAX_MATCH, MAX_WINDOW_SIZE, MIN_MATCH};
+pub use token::{bucket_base, bucket_symbol, copy_match, num_bucket_symbols, Token};
@@ -0,0 +1,130 @@
+use std::fmt;
+
+use crate::error::Error;
+
+/// An item of an LZ77 token stream.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Token {
+    /// A byte that is output as is.
+    Literal(u8),
+
+    /// A copy of `length` bytes starting `distance` bytes back in the output.
+    Match { length: u32, distance: u32 },
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Literal(byte) => write!(f, "L({:#x})", byte),
+            Token::Match { length, distance } => write!(f, "M({}, {})", length, distance),
+        }
+    }
+}
+
+/// Appends `length` bytes from `distance` bytes back in `output` to `output`.
+///
+/// The source may overlap the bytes being copied, e.g. a distance of 1 repeats the last byte.
+pub fn copy_match(output: &mut Vec<u8>, length: u32, distance: u32) -> Result<(), Error> {
+    let distance = distance as usize;
+    if distance == 0 || distance > output.len() {
+        return Err(Error::CorruptData("Invalid match distance"));
+    }
+    let start = output.len() - distance;
+    if distance >= length as usize {
+        output.extend_from_within(start..start + length as usize);
+    } else {
+        for i in 0..length as usize {
+            output.push(output[start + i]);
+        }
+    }
+    Ok(())
+}
+
+/// Expands a token stream, appending the bytes to `output`.
+#[cfg(test)]
+pub fn expand(tokens: &[Token], output: &mut Vec<u8>) -> Result<(), Error> {
+    for token in tokens.iter() {
+        match *token {
+            Token::Literal(byte) => output.push(byte),
+            Token::Match { length, distance } => copy_match(output, length, distance)?,
+        }
+    }
+    Ok(())
+}
+
+// Values below this are coded without extra bits.
+const DIRECT_VALUES: u32 = 4;
+
+/// Maps `value` to a symbol with extra bits, for entropy coding of lengths and distances.
+///
+/// Values 0-3 have their own symbols. Larger values are split into buckets of 2 symbols per power
+/// of 2, as with the distance codes of DEFLATE. Returns (symbol, number of extra bits, extra bits).
+pub fn bucket_symbol(value: u32) -> (u16, u32, u32) {
+    if value < DIRECT_VALUES {
+        return (value as u16, 0, 0);
+    }
+    let high_bit = 31 - value.leading_zeros();
+    let extra_bits = high_bit - 1;
+    let symbol = 2 * high_bit + ((value >> extra_bits) & 1);
+    (symbol as u16, extra_bits, value & ((1 << extra_bits) - 1))
+}
+
+/// Returns (smallest value, number of extra bits) of a symbol from `bucket_symbol`.
+pub fn bucket_base(symbol: u16) -> (u32, u32) {
+    let symbol = symbol as u32;
+    if symbol < DIRECT_VALUES {
+        return (symbol, 0);
+    }
+    let high_bit = symbol / 2;
+    let extra_bits = high_bit - 1;
+    ((2 | (symbol & 1)) << extra_bits, extra_bits)
+}
+
+/// Returns the number of symbols needed to code values up to `max_value`.
+pub fn num_bucket_symbols(max_value: u32) -> u16 {
+    bucket_symbol(max_value).0 + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_symbol() {
+        assert_eq!(bucket_symbol(0), (0, 0, 0));
+        assert_eq!(bucket_symbol(3), (3, 0, 0));
+        assert_eq!(bucket_symbol(4), (4, 1, 0));
+        assert_eq!(bucket_symbol(5), (4, 1, 1));
+        assert_eq!(bucket_symbol(6), (5, 1, 0));
+        assert_eq!(bucket_symbol(8), (6, 2, 0));
+        assert_eq!(bucket_symbol(32767), (29, 13, 8191));
+
+        for value in (0..100000).chain([u32::MAX - 1, u32::MAX]) {
+            let (symbol, extra_bits, extra) = bucket_symbol(value);
+            assert_eq!(bucket_base(symbol), (value - extra, extra_bits));
+        }
+        assert_eq!(num_bucket_symbols(u32::MAX), 64);
+    }
+
+    #[test]
+    fn test_expand() {
+        let tokens = vec![
+            Token::Literal(1),
+            Token::Literal(2),
+            Token::Match {
+                length: 5,
+                distance: 2,
+            },
+            Token::Match {
+                length: 2,
+                distance: 7,
+            },
+        ];
+        let mut output = Vec::new();
+        expand(&tokens, &mut output).unwrap();
+        assert_eq!(output, vec![1, 2, 1, 2, 1, 2, 1, 1, 2]);
+
+        assert!(copy_match(&mut output, 1, 10).is_err());
+        assert!(copy_match(&mut output, 1, 0).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::bzip2::RunLengthEncoder;
+use crate::error::Error;
+use crate::transform::ByteTransform;
+
+// Length of a run that is followed by a count of further repeats.
+const RUN_THRESHOLD: usize = 4;
+
+/// Run-length coding of bytes, in the format of the initial run-length coding of bzip2: a run
+/// of 4 to 255 equal bytes is coded as 4 bytes, followed by a byte with the number of further
+/// repeats.
+pub struct ByteRunLength {
+    encoder: RunLengthEncoder,
+
+    // The last byte decoded, and the length of its run up to `RUN_THRESHOLD`.
+    byte: u8,
+    run: usize,
+}
+
+impl ByteRunLength {
+    pub fn new() -> Self {
+        Self {
+            encoder: RunLengthEncoder::new(),
+            byte: 0,
+            run: 0,
+        }
+    }
+}
+
+impl ByteTransform for ByteRunLength {
+    fn encode(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        for &byte in data.iter() {
+            self.encoder.push(byte, output);
+        }
+    }
+
+    fn finish_encode(&mut self, output: &mut Vec<u8>) {
+        self.encoder.flush(output);
+    }
+
+    fn decode(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        for &byte in data.iter() {
+            if self.run == RUN_THRESHOLD {
+                // The count of further repeats, after which a new run starts.
+                output.resize(output.len() + byte as usize, self.byte);
+                self.run = 0;
+                continue;
+            }
+            if self.run > 0 && byte == self.byte {
+                self.run += 1;
+            } else {
+                self.byte = byte;
+                self.run = 1;
+            }
+            output.push(byte);
+        }
+        Ok(())
+    }
+
+    fn finish_decode(&mut self, _output: &mut Vec<u8>) -> Result<(), Error> {
+        let run = self.run;
+        self.run = 0;
+        // The data ends before the length of the run.
+        if run == RUN_THRESHOLD {
+            return Err(Error::Truncated);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let data = [b"ab".as_slice(), &[b'c'; 300], b"dddd", b"eeeeef"].concat();
+        let mut encoded = Vec::new();
+        let mut encoder = ByteRunLength::new();
+        encoder.encode(&data[..100], &mut encoded);
+        encoder.encode(&data[100..], &mut encoded);
+        encoder.finish_encode(&mut encoded);
+        assert_eq!(encoded, b"abcccc\xffcccc\x25dddd\x00eeee\x01f");
+
+        // Decode in pieces that split the runs.
+        for split in 0..encoded.len() {
+            let mut decoder = ByteRunLength::new();
+            let mut decoded = Vec::new();
+            decoder.decode(&encoded[..split], &mut decoded).unwrap();
+            decoder.decode(&encoded[split..], &mut decoded).unwrap();
+            decoder.finish_decode(&mut decoded).unwrap();
+            assert_eq!(decoded, data);
+        }
+
+        let mut decoder = ByteRunLength::new();
+        decoder.decode(b"xxxx", &mut Vec::new()).unwrap();
+        assert!(matches!(
+            decoder.finish_decode(&mut Vec::new()),
+            Err(Error::Truncated)
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::error::Error;
+use crate::transform::{ByteRunLength, Delta};
+
+/// A reversible transform of a byte stream, applied before an entropy coder.
+///
+/// An instance keeps its state across calls, so that a stream can be transformed in pieces,
+/// followed by a call to `finish_encode()` or `finish_decode()`. An instance is used either for
+/// encoding or for decoding.
+pub trait ByteTransform {
+    /// Transform `data`, appending the output to `output`.
+    fn encode(&mut self, data: &[u8], output: &mut Vec<u8>);
+
+    /// Finish encoding the stream, writing out the pending output.
+    fn finish_encode(&mut self, output: &mut Vec<u8>);
+
+    /// Inverse transform of `data`, appending the output to `output`.
+    fn decode(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Finish decoding the stream, writing out the pending output.
+    fn finish_decode(&mut self, output: &mut Vec<u8>) -> Result<(), Error>;
+}
+
+/// The byte transforms, with the IDs that identify them in a stream header.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ByteStage {
+    /// Difference of each byte from the previous byte.
+    Delta,
+
+    /// Run-length coding of runs of 4 or more equal bytes.
+    RunLength,
+}
+
+impl ByteStage {
+    /// Returns the ID of the stage.
+    pub fn id(self) -> u8 {
+        match self {
+            ByteStage::Delta => 1,
+            ByteStage::RunLength => 2,
+        }
+    }
+
+    /// Returns the stage with `id`, or None if it's unknown.
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(ByteStage::Delta),
+            2 => Some(ByteStage::RunLength),
+            _ => None,
+        }
+    }
+
+    /// Create a new instance of the transform.
+    pub fn transform(self) -> Box<dyn ByteTransform> {
+        match self {
+            ByteStage::Delta => Box::new(Delta::new()),
+            ByteStage::RunLength => Box::new(ByteRunLength::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ids() {
+        for stage in [ByteStage::Delta, ByteStage::RunLength] {
+            assert_eq!(ByteStage::from_id(stage.id()), Some(stage));
+        }
+        assert_eq!(ByteStage::from_id(0), None);
+    }
+}
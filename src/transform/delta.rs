@@ -0,0 +1,64 @@
+use crate::error::Error;
+use crate::transform::ByteTransform;
+
+/// Delta coding: each byte is coded as its difference from the previous byte, modulo 256.
+/// This turns slowly changing data, such as samples or sorted numbers, into small values.
+pub struct Delta {
+    // The previous byte of the original stream.
+    prev: u8,
+}
+
+impl Delta {
+    pub fn new() -> Self {
+        Self { prev: 0 }
+    }
+}
+
+impl ByteTransform for Delta {
+    fn encode(&mut self, data: &[u8], output: &mut Vec<u8>) {
+        output.reserve(data.len());
+        for &byte in data.iter() {
+            output.push(byte.wrapping_sub(self.prev));
+            self.prev = byte;
+        }
+    }
+
+    fn finish_encode(&mut self, _output: &mut Vec<u8>) {
+        self.prev = 0;
+    }
+
+    fn decode(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        output.reserve(data.len());
+        for &delta in data.iter() {
+            self.prev = self.prev.wrapping_add(delta);
+            output.push(self.prev);
+        }
+        Ok(())
+    }
+
+    fn finish_decode(&mut self, _output: &mut Vec<u8>) -> Result<(), Error> {
+        self.prev = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let mut delta = Delta::new();
+        let mut encoded = Vec::new();
+        delta.encode(&[10, 11, 12], &mut encoded);
+        delta.encode(&[12, 5, 255, 0], &mut encoded);
+        delta.finish_encode(&mut encoded);
+        assert_eq!(encoded, [10, 1, 1, 0, 249, 250, 1]);
+
+        let mut decoded = Vec::new();
+        delta.decode(&encoded[..2], &mut decoded).unwrap();
+        delta.decode(&encoded[2..], &mut decoded).unwrap();
+        delta.finish_decode(&mut decoded).unwrap();
+        assert_eq!(decoded, [10, 11, 12, 12, 5, 255, 0]);
+    }
+}
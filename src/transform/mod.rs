@@ -0,0 +1,46 @@
+mod byte_run_length;
+mod byte_transform;
+mod delta;
+mod move_to_front;
+mod transform_chain;
+mod zero_run;
+
+pub use byte_run_length::ByteRunLength;
+pub use byte_transform::{ByteStage, ByteTransform};
+pub use delta::Delta;
+pub use move_to_front::{MoveToFront, MtfVariant};
+pub use transform_chain::{TransformChain, TransformStage};
+pub use zero_run::{ZeroRunDecoder, ZeroRunEncoder};
+
+/// A symbol of the transforms: a byte or a 16-bit symbol.
+pub trait Symbol: Copy {
+    /// Returns the symbol as an index.
+    fn index(self) -> usize;
+
+    /// Returns the symbol for `index`, which must be in range of the symbol type.
+    fn from_index(index: usize) -> Self;
+}
+
+impl Symbol for u8 {
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    #[inline]
+    fn from_index(index: usize) -> Self {
+        index as u8
+    }
+}
+
+impl Symbol for u16 {
+    #[inline]
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    #[inline]
+    fn from_index(index: usize) -> Self {
+        index as u16
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::Error;
+use crate::transform::Symbol;
+
+/// Variants of the move-to-front transform.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MtfVariant {
+    /// Each symbol moves to the front.
+    Standard,
+
+    /// MTF-1: a symbol at index 1 moves to the front, and symbols further back move to index 1.
+    /// A symbol that occurs once between runs of another symbol doesn't displace it.
+    Mtf1,
+}
+
+/// Move-to-front transform, which codes each symbol as its index in a list of the symbols, and
+/// then moves the symbol towards the front. Recently used symbols get small indices, e.g. after
+/// a Burrows-Wheeler transform.
+///
+/// The list is kept across calls, so that a stream can be transformed in pieces.
+pub struct MoveToFront {
+    // The symbols, most recently used first.
+    table: Vec<u16>,
+
+    variant: MtfVariant,
+}
+
+impl MoveToFront {
+    /// Create an instance for symbols 0 to `num_symbols - 1`.
+    pub fn new(num_symbols: usize, variant: MtfVariant) -> Self {
+        assert!(num_symbols <= u16::MAX as usize + 1);
+        Self {
+            table: (0..num_symbols).map(|symbol| symbol as u16).collect(),
+            variant,
+        }
+    }
+
+    /// Start a new stream, with the symbols in order.
+    pub fn reset(&mut self) {
+        for (i, symbol) in self.table.iter_mut().enumerate() {
+            *symbol = i as u16;
+        }
+    }
+
+    /// Transform `symbols`, appending their indices to `output`.
+    pub fn encode<S: Symbol>(&mut self, symbols: &[S], output: &mut Vec<S>) {
+        output.reserve(symbols.len());
+        for &symbol in symbols.iter() {
+            let symbol = symbol.index() as u16;
+            let index = self
+                .table
+                .iter()
+                .position(|&s| s == symbol)
+                .expect("Symbol out of range");
+            output.push(S::from_index(index));
+            self.move_up(index);
+        }
+    }
+
+    /// Inverse transform of `indices`, appending the symbols to `output`.
+    pub fn decode<S: Symbol>(&mut self, indices: &[S], output: &mut Vec<S>) -> Result<(), Error> {
+        output.reserve(indices.len());
+        for &index in indices.iter() {
+            let index = index.index();
+            if index >= self.table.len() {
+                return Err(Error::CorruptData("Invalid move-to-front index"));
+            }
+            output.push(S::from_index(self.table[index] as usize));
+            self.move_up(index);
+        }
+        Ok(())
+    }
+
+    // Move the symbol at `index` towards the front.
+    fn move_up(&mut self, index: usize) {
+        match self.variant {
+            MtfVariant::Standard => self.table[..=index].rotate_right(1),
+            MtfVariant::Mtf1 => {
+                if index == 1 {
+                    self.table.swap(0, 1);
+                } else if index > 1 {
+                    self.table[1..=index].rotate_right(1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<S: Symbol + PartialEq + std::fmt::Debug>(
+        symbols: &[S],
+        num_symbols: usize,
+        variant: MtfVariant,
+    ) -> Vec<S> {
+        let mut indices: Vec<S> = Vec::new();
+        let mut mtf = MoveToFront::new(num_symbols, variant);
+        for chunk in symbols.chunks(7) {
+            mtf.encode(chunk, &mut indices);
+        }
+        let mut decoded: Vec<S> = Vec::new();
+        let mut inverse = MoveToFront::new(num_symbols, variant);
+        inverse.decode(&indices, &mut decoded).unwrap();
+        assert_eq!(decoded, symbols);
+        indices
+    }
+
+    #[test]
+    fn test_standard() {
+        let indices = round_trip(b"bananaaa", 256, MtfVariant::Standard);
+        assert_eq!(indices, [98, 98, 110, 1, 1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_mtf1() {
+        // New symbols only reach index 1, so 'a' stays at the front after the single 'b'.
+        let indices = round_trip(b"aabaab", 256, MtfVariant::Mtf1);
+        assert_eq!(indices, [97, 1, 98, 0, 0, 1]);
+        round_trip(
+            b"the quick brown fox jumps over the lazy dog",
+            256,
+            MtfVariant::Mtf1,
+        );
+    }
+
+    #[test]
+    fn test_u16_symbols() {
+        let symbols: Vec<u16> = (0..1000).map(|i| (i * i % 1000) as u16).collect();
+        for variant in [MtfVariant::Standard, MtfVariant::Mtf1] {
+            round_trip(&symbols, 1000, variant);
+        }
+
+        let mut mtf = MoveToFront::new(1000, MtfVariant::Standard);
+        assert!(mtf.decode(&[1000u16], &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut mtf = MoveToFront::new(256, MtfVariant::Standard);
+        let mut indices: Vec<u8> = Vec::new();
+        mtf.encode(b"zz", &mut indices);
+        mtf.reset();
+        mtf.encode(b"z", &mut indices);
+        assert_eq!(indices, [b'z', 0, b'z']);
+    }
+}
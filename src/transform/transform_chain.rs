@@ -0,0 +1,205 @@
+use std::mem;
+
+use crate::error::Error;
+use crate::transform::{MoveToFront, MtfVariant, ZeroRunDecoder, ZeroRunEncoder};
+
+/// A stage of a `TransformChain`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TransformStage {
+    /// Move-to-front transform.
+    MoveToFront,
+
+    /// MTF-1 variant of the move-to-front transform.
+    MoveToFront1,
+
+    /// Run-length coding of zeros with RUN_A/RUN_B, which adds one symbol.
+    ZeroRun,
+}
+
+// A stage with its state.
+enum Stage {
+    MoveToFront(MoveToFront),
+    ZeroRun(ZeroRunEncoder, ZeroRunDecoder),
+}
+
+/// A chain of symbol transforms applied in order, e.g. move-to-front then zero run-length
+/// coding, as the stages before an entropy coder. Decoding applies the inverse transforms in
+/// reverse order.
+///
+/// The symbols are 16-bit, as a stage may add symbols. A stream can be transformed in pieces,
+/// followed by a call to `finish_encode()` or `finish_decode()`.
+pub struct TransformChain {
+    stages: Vec<Stage>,
+
+    // Number of output symbols.
+    num_output_symbols: usize,
+}
+
+impl TransformChain {
+    /// Create a chain of `stages` for symbols 0 to `num_symbols - 1`.
+    pub fn new(stages: &[TransformStage], num_symbols: usize) -> Self {
+        let mut chain = Self {
+            stages: Vec::with_capacity(stages.len()),
+            num_output_symbols: num_symbols,
+        };
+        for stage in stages.iter() {
+            let n = chain.num_output_symbols;
+            chain.stages.push(match stage {
+                TransformStage::MoveToFront => {
+                    Stage::MoveToFront(MoveToFront::new(n, MtfVariant::Standard))
+                }
+                TransformStage::MoveToFront1 => {
+                    Stage::MoveToFront(MoveToFront::new(n, MtfVariant::Mtf1))
+                }
+                TransformStage::ZeroRun => {
+                    chain.num_output_symbols = ZeroRunEncoder::num_output_symbols(n);
+                    Stage::ZeroRun(ZeroRunEncoder::new(), ZeroRunDecoder::new())
+                }
+            });
+        }
+        assert!(chain.num_output_symbols <= u16::MAX as usize + 1);
+        chain
+    }
+
+    /// Returns true if there are no stages, so the output is the same as the input.
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Returns the number of output symbols of the last stage.
+    pub fn num_output_symbols(&self) -> usize {
+        self.num_output_symbols
+    }
+
+    /// Start a new stream.
+    pub fn reset(&mut self) {
+        self.reset_with_max_len(u64::MAX);
+    }
+
+    /// Start a new stream, which fails to decode to more than `max_len` symbols. This bounds the
+    /// output of invalid data, as a run of zeros is coded in a few symbols.
+    pub fn reset_with_max_len(&mut self, max_len: u64) {
+        for stage in self.stages.iter_mut() {
+            match stage {
+                Stage::MoveToFront(mtf) => mtf.reset(),
+                Stage::ZeroRun(encoder, decoder) => {
+                    // The inverse stages don't shrink the data, so the limit holds for each.
+                    *encoder = ZeroRunEncoder::new();
+                    *decoder = ZeroRunDecoder::with_max_len(max_len);
+                }
+            }
+        }
+    }
+
+    /// Transform `symbols` through all the stages, appending the output to `output`.
+    pub fn encode(&mut self, symbols: &[u16], output: &mut Vec<u16>) {
+        self.encode_stages(symbols.to_vec(), false, output);
+    }
+
+    /// Finish encoding the stream, writing out the symbols pending in the stages.
+    pub fn finish_encode(&mut self, output: &mut Vec<u16>) {
+        self.encode_stages(Vec::new(), true, output);
+    }
+
+    /// Inverse transform of `symbols` through all the stages, appending the output to `output`.
+    pub fn decode(&mut self, symbols: &[u16], output: &mut Vec<u16>) -> Result<(), Error> {
+        self.decode_stages(symbols.to_vec(), false, output)
+    }
+
+    /// Finish decoding the stream, writing out the symbols pending in the stages.
+    pub fn finish_decode(&mut self, output: &mut Vec<u16>) -> Result<(), Error> {
+        self.decode_stages(Vec::new(), true, output)
+    }
+
+    // Pass `data` through the stages in order. If `finish`, each stage is finished after its
+    // input.
+    fn encode_stages(&mut self, mut data: Vec<u16>, finish: bool, output: &mut Vec<u16>) {
+        let mut next: Vec<u16> = Vec::new();
+        for stage in self.stages.iter_mut() {
+            next.clear();
+            match stage {
+                Stage::MoveToFront(mtf) => mtf.encode(&data, &mut next),
+                Stage::ZeroRun(encoder, _) => {
+                    encoder.encode(&data, &mut next);
+                    if finish {
+                        encoder.finish(&mut next);
+                    }
+                }
+            }
+            mem::swap(&mut data, &mut next);
+        }
+        output.extend_from_slice(&data);
+    }
+
+    // Pass `data` through the inverse stages in reverse order.
+    fn decode_stages(
+        &mut self,
+        mut data: Vec<u16>,
+        finish: bool,
+        output: &mut Vec<u16>,
+    ) -> Result<(), Error> {
+        let mut next: Vec<u16> = Vec::new();
+        for stage in self.stages.iter_mut().rev() {
+            next.clear();
+            match stage {
+                Stage::MoveToFront(mtf) => mtf.decode(&data, &mut next)?,
+                Stage::ZeroRun(_, decoder) => {
+                    decoder.decode(&data, &mut next)?;
+                    if finish {
+                        decoder.finish(&mut next);
+                    }
+                }
+            }
+            mem::swap(&mut data, &mut next);
+        }
+        output.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(stages: &[TransformStage], symbols: &[u16]) -> Vec<u16> {
+        let mut encoded: Vec<u16> = Vec::new();
+        let mut chain = TransformChain::new(stages, 256);
+        for chunk in symbols.chunks(100) {
+            chain.encode(chunk, &mut encoded);
+        }
+        chain.finish_encode(&mut encoded);
+        assert!(encoded
+            .iter()
+            .all(|&symbol| (symbol as usize) < chain.num_output_symbols()));
+
+        let mut decoded: Vec<u16> = Vec::new();
+        let mut chain = TransformChain::new(stages, 256);
+        for chunk in encoded.chunks(10) {
+            chain.decode(chunk, &mut decoded).unwrap();
+        }
+        chain.finish_decode(&mut decoded).unwrap();
+        assert_eq!(decoded, symbols);
+        encoded
+    }
+
+    #[test]
+    fn test_stages() {
+        let symbols: Vec<u16> = b"aaaaaaaabbbbbbbbbbbbaaaaaaaaaaaaacccccccccc"
+            .repeat(20)
+            .iter()
+            .map(|&byte| byte as u16)
+            .collect();
+        use TransformStage::*;
+        assert_eq!(round_trip(&[], &symbols), symbols);
+        round_trip(&[MoveToFront], &symbols);
+        round_trip(&[MoveToFront1], &symbols);
+        round_trip(&[ZeroRun], &symbols);
+        round_trip(&[MoveToFront, ZeroRun, MoveToFront1], &symbols);
+        let encoded = round_trip(&[MoveToFront, ZeroRun], &symbols);
+        assert!(encoded.len() < symbols.len() / 2);
+        assert_eq!(
+            TransformChain::new(&[ZeroRun, ZeroRun], 256).num_output_symbols(),
+            258
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::error::Error;
+use crate::transform::Symbol;
+
+/// Output symbol of a digit 1 of a run length.
+pub const RUN_A: u16 = 0;
+
+/// Output symbol of a digit 2 of a run length.
+pub const RUN_B: u16 = 1;
+
+// Largest weight of a run length digit, which limits the run length to about 2^33.
+const MAX_DIGIT_WEIGHT: u64 = 1 << 32;
+
+/// Run-length encoder of zero symbols, as in bzip2.
+///
+/// A run of zeros is coded as its length in bijective base 2, least significant digit first,
+/// with the digits RUN_A (1) and RUN_B (2). The other symbols are shifted up by one, so there is
+/// one more output symbol than input symbols. This suits the output of move-to-front, where the
+/// runs of zeros are common.
+///
+/// A run is kept across calls, so that a stream can be encoded in pieces.
+pub struct ZeroRunEncoder {
+    // Length of the pending run of zeros.
+    run: u64,
+}
+
+impl ZeroRunEncoder {
+    pub fn new() -> Self {
+        Self { run: 0 }
+    }
+
+    /// Returns the number of output symbols, given the number of input symbols.
+    pub fn num_output_symbols(num_symbols: usize) -> usize {
+        num_symbols + 1
+    }
+
+    /// Encode `symbols`, appending the output to `output`.
+    pub fn encode<S: Symbol>(&mut self, symbols: &[S], output: &mut Vec<u16>) {
+        for &symbol in symbols.iter() {
+            let symbol = symbol.index();
+            if symbol == 0 {
+                self.run += 1;
+            } else {
+                self.flush_run(output);
+                output.push(symbol as u16 + 1);
+            }
+        }
+    }
+
+    /// Finish the stream, writing out the pending run.
+    pub fn finish(&mut self, output: &mut Vec<u16>) {
+        self.flush_run(output);
+    }
+
+    fn flush_run(&mut self, output: &mut Vec<u16>) {
+        let mut run = self.run;
+        while run > 0 {
+            if run & 1 == 1 {
+                output.push(RUN_A);
+                run = (run - 1) / 2;
+            } else {
+                output.push(RUN_B);
+                run = (run - 2) / 2;
+            }
+        }
+        self.run = 0;
+    }
+}
+
+/// Decoder of `ZeroRunEncoder`.
+pub struct ZeroRunDecoder {
+    // Length of the pending run of zeros.
+    run: u64,
+
+    // Weight of the next digit of the run length.
+    weight: u64,
+
+    // Number of symbols that may still be output.
+    remaining: u64,
+}
+
+impl ZeroRunDecoder {
+    pub fn new() -> Self {
+        Self::with_max_len(u64::MAX)
+    }
+
+    /// Create an instance that fails on a run that makes the output longer than `max_len`
+    /// symbols, e.g. the block size, to bound the output of invalid data.
+    pub fn with_max_len(max_len: u64) -> Self {
+        Self {
+            run: 0,
+            weight: 1,
+            remaining: max_len,
+        }
+    }
+
+    /// Decode `symbols`, appending the output to `output`.
+    pub fn decode<S: Symbol>(&mut self, symbols: &[u16], output: &mut Vec<S>) -> Result<(), Error> {
+        for &symbol in symbols.iter() {
+            if symbol == RUN_A || symbol == RUN_B {
+                if self.weight > MAX_DIGIT_WEIGHT {
+                    return Err(Error::CorruptData("Run too long"));
+                }
+                self.run += self.weight << symbol;
+                self.weight <<= 1;
+                if self.run > self.remaining {
+                    return Err(Error::CorruptData("Run too long"));
+                }
+            } else {
+                self.flush_run(output);
+                if self.remaining == 0 {
+                    return Err(Error::CorruptData("Output too long"));
+                }
+                self.remaining -= 1;
+                output.push(S::from_index(symbol as usize - 1));
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the stream, writing out the pending run.
+    pub fn finish<S: Symbol>(&mut self, output: &mut Vec<S>) {
+        self.flush_run(output);
+    }
+
+    fn flush_run<S: Symbol>(&mut self, output: &mut Vec<S>) {
+        output.resize(output.len() + self.run as usize, S::from_index(0));
+        self.remaining -= self.run;
+        self.run = 0;
+        self.weight = 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(symbols: &[u8]) -> Vec<u16> {
+        let mut output: Vec<u16> = Vec::new();
+        let mut encoder = ZeroRunEncoder::new();
+        encoder.encode(symbols, &mut output);
+        encoder.finish(&mut output);
+        output
+    }
+
+    fn decode(symbols: &[u16]) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::new();
+        let mut decoder = ZeroRunDecoder::new();
+        decoder.decode(symbols, &mut output).unwrap();
+        decoder.finish(&mut output);
+        output
+    }
+
+    #[test]
+    fn test_run_lengths() {
+        const A: u16 = RUN_A;
+        const B: u16 = RUN_B;
+        assert_eq!(encode(&[0]), [A]);
+        assert_eq!(encode(&[0, 0]), [B]);
+        assert_eq!(encode(&[0, 0, 0]), [A, A]);
+        assert_eq!(encode(&[0, 0, 0, 0]), [B, A]);
+        assert_eq!(encode(&[0, 0, 0, 0, 0]), [A, B]);
+        assert_eq!(encode(&[0, 0, 0, 0, 0, 0, 0]), [A, A, A]);
+        assert_eq!(encode(&[5, 0, 0, 255, 0]), [6, B, 256, A]);
+        assert_eq!(encode(&[]), []);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for len in 0..100 {
+            let mut symbols = vec![0u8; len];
+            symbols.push(1);
+            symbols.extend(vec![0u8; len * 3]);
+            assert_eq!(decode(&encode(&symbols)), symbols);
+        }
+
+        // In pieces, with runs across the pieces.
+        let symbols: Vec<u16> = (0..1000)
+            .map(|i| if i % 97 < 60 { 0 } else { i % 7 })
+            .collect();
+        let mut encoded: Vec<u16> = Vec::new();
+        let mut encoder = ZeroRunEncoder::new();
+        for chunk in symbols.chunks(13) {
+            encoder.encode(chunk, &mut encoded);
+        }
+        encoder.finish(&mut encoded);
+        let mut decoded: Vec<u16> = Vec::new();
+        let mut decoder = ZeroRunDecoder::new();
+        for chunk in encoded.chunks(3) {
+            decoder.decode(chunk, &mut decoded).unwrap();
+        }
+        decoder.finish(&mut decoded);
+        assert_eq!(decoded, symbols);
+    }
+
+    #[test]
+    fn test_run_too_long() {
+        let mut decoder = ZeroRunDecoder::new();
+        assert!(decoder.decode(&[RUN_B; 40], &mut Vec::<u8>::new()).is_err());
+
+        let mut decoder = ZeroRunDecoder::with_max_len(4);
+        assert!(decoder
+            .decode(&[RUN_B, RUN_A], &mut Vec::<u8>::new())
+            .is_ok());
+        assert!(decoder.decode(&[RUN_A], &mut Vec::<u8>::new()).is_err());
+
+        // The runs and the other symbols count towards the length.
+        let mut decoder = ZeroRunDecoder::with_max_len(6);
+        let mut output: Vec<u8> = Vec::new();
+        assert!(decoder.decode(&[RUN_B, 2, RUN_B], &mut output).is_ok());
+        assert!(decoder.decode(&[3, RUN_B], &mut output).is_err());
+        let mut decoder = ZeroRunDecoder::with_max_len(3);
+        assert!(decoder.decode(&[RUN_B, 2, 2], &mut output).is_err());
+    }
+}
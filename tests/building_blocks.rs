@@ -0,0 +1,57 @@
+use comprs::bits::{BitReader, BitWriter};
+use comprs::huffman::{PrefixCode, StaticHuffman};
+
+#[test]
+fn test_bit_writer_reader() {
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut bit_writer = BitWriter::new(&mut encoded);
+    for i in 0..100 {
+        bit_writer.write_bits(i, (i % 17 + 7) as u32);
+    }
+    bit_writer.align_to_byte();
+    bit_writer.write_bytes(b"end");
+    let bytes_written = bit_writer.finish().unwrap();
+    assert_eq!(bytes_written, encoded.len());
+
+    let mut reader = &encoded[..];
+    let mut bit_reader = BitReader::new(&mut reader);
+    for i in 0..100 {
+        assert_eq!(bit_reader.read_bits((i % 17 + 7) as u32), i);
+    }
+    bit_reader.align_to_byte();
+    let mut end = [0; 3];
+    assert_eq!(bit_reader.read_bytes(&mut end), 3);
+    assert_eq!(&end, b"end");
+    assert_eq!(bit_reader.finish().unwrap(), encoded.len());
+}
+
+#[test]
+fn test_huffman_code() {
+    let text = include_bytes!("../testdata/deflate/text.txt");
+    let mut weights = [0; 256];
+    for &byte in text.iter() {
+        weights[byte as usize] += 1;
+    }
+    let code = StaticHuffman::new(256).build_from_weights(&weights);
+    assert!(code.encoded_bits(&weights) < text.len() as u64 * 8);
+
+    // Write the table and the symbols, and read them back.
+    let encoder_table = code.generate_encoder_table();
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut bit_writer = BitWriter::new(&mut encoded);
+    code.encode_coding_table(&mut bit_writer);
+    for &byte in text.iter() {
+        let (bits, length) = encoder_table[byte as usize];
+        bit_writer.write_bits(bits as u64, length as u32);
+    }
+    bit_writer.finish().unwrap();
+
+    let mut reader = &encoded[..];
+    let mut bit_reader = BitReader::new(&mut reader);
+    let decoded_code = PrefixCode::decode_coding_table(&mut bit_reader).unwrap();
+    assert_eq!(decoded_code.code_lengths(), code.code_lengths());
+    let decoder = decoded_code.generate_decoder();
+    for &byte in text.iter() {
+        assert_eq!(decoder.decode(&mut bit_reader).unwrap(), byte as u16);
+    }
+}
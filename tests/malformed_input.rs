@@ -0,0 +1,48 @@
+use comprs::{compress, decompress, Error, FrameHeader, Method};
+
+const TEXT: &[u8] = include_bytes!("../testdata/deflate/text.txt");
+
+#[test]
+fn test_empty_input() {
+    for method in Method::ALL {
+        let compressed = compress(b"", method);
+        assert_eq!(decompress(&compressed).unwrap(), b"", "{}", method.name());
+    }
+}
+
+#[test]
+fn test_truncated() {
+    for method in Method::ALL {
+        let compressed = compress(&TEXT[..500], method);
+        for len in 0..compressed.len() {
+            let result = decompress(&compressed[..len]);
+            assert!(result.is_err(), "{} at {}", method.name(), len);
+        }
+
+        // Truncated data past the header.
+        let header = FrameHeader::read(&mut &compressed[..]).unwrap();
+        let len = header.to_bytes().len() + 1;
+        let result = decompress(&compressed[..len]);
+        assert!(
+            matches!(result, Err(Error::Truncated)),
+            "{}: {:?}",
+            method.name(),
+            result
+        );
+    }
+}
+
+#[test]
+fn test_corrupt() {
+    // Corrupt data may decode to wrong output, but must not panic or hang.
+    for method in Method::ALL {
+        let compressed = compress(&TEXT[..500], method);
+        for pos in (0..compressed.len()).step_by(3) {
+            for mask in [0x01, 0xff] {
+                let mut corrupt = compressed.clone();
+                corrupt[pos] ^= mask;
+                let _ = decompress(&corrupt);
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::io::{Read, Write};
+
+use comprs::{
+    compress, decompress, CompressWriter, Decoder, DecompressReader, Encoder, FrameChecksum,
+    FrameDecoder, FrameEncoder, FrameHeader, FrameOptions, InputSource, Method, OutputSink,
+    FRAME_MAGIC,
+};
+
+const TEXT: &[u8] = include_bytes!("../testdata/deflate/text.txt");
+
+#[test]
+fn test_compress_decompress() {
+    for method in Method::ALL {
+        for input in [&b"a"[..], b"abababababab", TEXT] {
+            let compressed = compress(input, method);
+            assert_eq!(&compressed[..4], &FRAME_MAGIC);
+            assert_eq!(decompress(&compressed).unwrap(), input, "{}", method.name());
+        }
+    }
+}
+
+#[test]
+fn test_frame_options() {
+    let mut output = OutputSink::memory(Vec::new());
+    let options = FrameOptions {
+        original_size: true,
+        checksum: Some(FrameChecksum::XxHash64),
+    };
+    let result = FrameEncoder::with_options(Method::Bzip2Coding, options)
+        .encode(&mut InputSource::memory(TEXT.to_vec()), &mut output)
+        .unwrap();
+    assert_eq!(result.bytes_read(), TEXT.len());
+    let compressed = output.take_memory();
+    assert_eq!(result.bytes_written(), compressed.len());
+
+    let header = FrameHeader::read(&mut &compressed[..]).unwrap();
+    assert_eq!(header.method, Method::Bzip2Coding);
+    assert_eq!(header.original_size, Some(TEXT.len() as u64));
+
+    let mut output = OutputSink::memory(Vec::new());
+    FrameDecoder::new()
+        .decode(&mut InputSource::memory(compressed), &mut output)
+        .unwrap();
+    assert_eq!(output.take_memory(), TEXT);
+}
+
+#[test]
+fn test_streaming() {
+    let mut writer = CompressWriter::with_checksum(
+        Vec::new(),
+        Method::MultiTableHuffmanCoding,
+        Some(FrameChecksum::Crc32),
+    );
+    for line in TEXT.split_inclusive(|&byte| byte == b'\n') {
+        writer.write_all(line).unwrap();
+    }
+    let compressed = writer.finish().unwrap();
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+
+    let mut decompressed = Vec::new();
+    DecompressReader::new(&compressed[..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(decompressed, TEXT);
+}
+
+#[test]
+fn test_reject_foreign_data() {
+    assert!(decompress(b"").is_err());
+    assert!(decompress(TEXT).is_err());
+}
+
+#[test]
+fn test_decompress_containers() {
+    for encoded in [
+        &include_bytes!("../testdata/gzip/text.txt.gz")[..],
+        &include_bytes!("../testdata/gzip/text.zlib")[..],
+        &include_bytes!("../testdata/bzip2/text.txt.bz2")[..],
+    ] {
+        assert_eq!(decompress(encoded).unwrap(), TEXT);
+    }
+}